@@ -1,5 +1,6 @@
 use clap::{Args, Subcommand, ValueEnum};
-use composer::config::PreferredInstallMethod;
+use composer::config::{CommandLineOverride, PreferredInstallMethod};
+use serde_json::Value;
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -23,7 +24,7 @@ pub enum Commands {
     /// Dump the shell completion script
     Completion,
     /// Sets config options
-    Config,
+    Config(ConfigArgs),
     /// Creates new project from a package into given directory
     CreateProject,
     /// Shows which packages cause the given package to be installed
@@ -45,7 +46,7 @@ pub enum Commands {
     Init,
     /// Installs the project dependencies from the composer.lock file if present, or falls back on the composer.json
     #[command(visible_alias = "i")]
-    Install,
+    Install(InstallArgs),
     /// Shows information about licenses of dependencies
     Licenses,
     /// List commands
@@ -75,16 +76,136 @@ pub enum Commands {
     Show,
     /// Shows a list of locally modified packages
     Status,
+    /// Manages the shared package store of the `store` install strategy
+    Store(StoreArgs),
     /// Shows package suggestions
     Suggests,
     /// Updates your dependencies to the latest version according to composer.json, and updates the composer.lock file
     #[command(visible_alias = "u")]
     #[command(visible_alias = "upgrade")]
-    Update,
+    Update(UpdateArgs),
     /// Validates a composer.json and composer.lock
     Validate,
 }
 
+impl Commands {
+    /// The config values that are overridden by flags of the command
+    pub fn config_overrides(&self) -> Vec<CommandLineOverride> {
+        match self {
+            Commands::Install(args) => args.config_overrides(),
+            Commands::Require(args) => args.config_overrides(),
+            Commands::Update(args) => args.config_overrides(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub struct ConfigArgs {
+    /// Setting key
+    pub setting_key: Option<String>,
+    /// List configuration settings
+    #[arg(long, short = 'l', default_value_t = false)]
+    pub list: bool,
+    /// Display where the config value is loaded from
+    #[arg(long, default_value_t = false)]
+    pub source: bool,
+}
+
+#[derive(Args, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub struct StoreArgs {
+    #[command(subcommand)]
+    pub command: StoreCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StoreCommands {
+    /// Removes the packages from the store which no project uses anymore
+    Prune,
+}
+
+#[derive(Args, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub struct InstallArgs {
+    /// Outputs the operations but will not execute anything (implicitly enables --verbose).
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+    /// Format of the operations listed by --dry-run.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Forces installation from package sources when possible, including VCS information.
+    #[arg(long, default_value_t = false)]
+    pub prefer_source: bool,
+    /// Forces installation from package dist (default behavior).
+    #[arg(long, default_value_t = false)]
+    pub prefer_dist: bool,
+    /// Forces installation from package (auto chooses source for dev versions, dist for the rest).
+    #[arg(long, value_enum)]
+    pub prefer_install: Option<PreferredInstallMethod>,
+    /// Disables installation of require-dev packages.
+    #[arg(long, default_value_t = false)]
+    pub no_dev: bool,
+    /// Do not output download progress.
+    #[arg(long, default_value_t = false)]
+    pub no_progress: bool,
+}
+
+impl InstallArgs {
+    fn config_overrides(&self) -> Vec<CommandLineOverride> {
+        preferred_install_override(self.prefer_source, self.prefer_dist, &self.prefer_install)
+            .into_iter()
+            .collect()
+    }
+}
+
+#[derive(Args, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub struct UpdateArgs {
+    /// Outputs the operations but will not execute anything (implicitly enables --verbose).
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+    /// Format of the operations listed by --dry-run.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Forces installation from package sources when possible, including VCS information.
+    #[arg(long, default_value_t = false)]
+    pub prefer_source: bool,
+    /// Forces installation from package dist (default behavior).
+    #[arg(long, default_value_t = false)]
+    pub prefer_dist: bool,
+    /// Forces installation from package (auto chooses source for dev versions, dist for the rest).
+    #[arg(long, value_enum)]
+    pub prefer_install: Option<PreferredInstallMethod>,
+    /// Disables installation of require-dev packages.
+    #[arg(long, default_value_t = false)]
+    pub no_dev: bool,
+    /// Skip the install step after updating the composer.lock file.
+    #[arg(long, default_value_t = false)]
+    pub no_install: bool,
+    /// Do not output download progress.
+    #[arg(long, default_value_t = false)]
+    pub no_progress: bool,
+    /// Ignore all platform requirements (php & ext- packages).
+    #[arg(long, default_value_t = false)]
+    pub ignore_platform_reqs: bool,
+    /// Prefer stable versions of dependencies (can also be set via the COMPOSER_PREFER_STABLE=1 env var).
+    #[arg(long, default_value_t = false)]
+    pub prefer_stable: bool,
+    /// Prefer lowest versions of dependencies (can also be set via the COMPOSER_PREFER_LOWEST=1 env var).
+    #[arg(long, default_value_t = false)]
+    pub prefer_lowest: bool,
+}
+
+impl UpdateArgs {
+    fn config_overrides(&self) -> Vec<CommandLineOverride> {
+        preferred_install_override(self.prefer_source, self.prefer_dist, &self.prefer_install)
+            .into_iter()
+            .collect()
+    }
+}
+
 #[derive(Args, Debug)]
 #[clap(rename_all = "kebab-case")]
 pub struct RequireArgs {
@@ -95,6 +216,9 @@ pub struct RequireArgs {
     /// Outputs the operations but will not execute anything (implicitly enables --verbose).
     #[arg(long, default_value_t = false)]
     pub dry_run: bool,
+    /// Format of the operations listed by --dry-run.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
     /// Forces installation from package sources when possible, including VCS information.
     #[arg(long, default_value_t = false)]
     pub prefer_source: bool,
@@ -102,8 +226,8 @@ pub struct RequireArgs {
     #[arg(long, default_value_t = false)]
     pub prefer_dist: bool,
     /// Forces installation from package (auto chooses source for dev versions, dist for the rest).
-    #[arg(long, value_enum, default_value_t = PreferredInstallMethod::Dist)]
-    pub prefer_install: PreferredInstallMethod,
+    #[arg(long, value_enum)]
+    pub prefer_install: Option<PreferredInstallMethod>,
     /// Write fixed version to the composer.json.
     #[arg(long, default_value_t = false)]
     pub fixed: bool,
@@ -166,6 +290,74 @@ pub struct RequireArgs {
     pub apcu_autoloader_prefix: Option<String>,
 }
 
+impl RequireArgs {
+    fn config_overrides(&self) -> Vec<CommandLineOverride> {
+        let mut overrides: Vec<_> =
+            preferred_install_override(self.prefer_source, self.prefer_dist, &self.prefer_install)
+                .into_iter()
+                .collect();
+
+        for (enabled, key, flag) in [
+            (self.sort_packages, "sort-packages", "--sort-packages"),
+            (
+                self.optimize_autoloader || self.classmap_authoritative,
+                "optimize-autoloader",
+                "--optimize-autoloader",
+            ),
+            (
+                self.classmap_authoritative,
+                "classmap-authoritative",
+                "--classmap-authoritative",
+            ),
+            (
+                self.apcu_autoloader || self.apcu_autoloader_prefix.is_some(),
+                "apcu-autoloader",
+                "--apcu-autoloader",
+            ),
+        ] {
+            if enabled {
+                overrides.push(config_override(key, true, flag));
+            }
+        }
+
+        overrides
+    }
+}
+
+/// The `preferred-install` set by `--prefer-source`, `--prefer-dist` or `--prefer-install`, in
+/// that order of precedence, which replaces the per-package patterns of the config
+fn preferred_install_override(
+    prefer_source: bool,
+    prefer_dist: bool,
+    prefer_install: &Option<PreferredInstallMethod>,
+) -> Option<CommandLineOverride> {
+    let (method, flag) = if prefer_source {
+        (PreferredInstallMethod::Source, "--prefer-source")
+    } else if prefer_dist {
+        (PreferredInstallMethod::Dist, "--prefer-dist")
+    } else {
+        (prefer_install.clone()?, "--prefer-install")
+    };
+
+    let name = method.to_possible_value()?;
+
+    Some(config_override("preferred-install", name.get_name(), flag))
+}
+
+fn config_override(key: &str, value: impl Into<Value>, flag: &str) -> CommandLineOverride {
+    CommandLineOverride {
+        key: key.into(),
+        value: value.into(),
+        flag: flag.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum AuditFormat {
     Table,
@@ -0,0 +1,51 @@
+use crate::app::commands::ConfigArgs;
+use anyhow::{bail, Result};
+use composer::config::{Config, ConfigEntry};
+
+pub fn config_handler(args: ConfigArgs, config: &Config) -> Result<()> {
+    let entries = config.entries()?;
+
+    if args.list {
+        for entry in &entries {
+            print_entry(entry, args.source);
+        }
+
+        return Ok(());
+    }
+
+    let Some(setting_key) = args.setting_key else {
+        bail!("Specify a setting key, or use --list to show all settings");
+    };
+
+    if let Some(entry) = entries.iter().find(|entry| entry.key == setting_key) {
+        match args.source {
+            true => println!("{} ({})", entry.value, entry.source),
+            false => println!("{}", entry.value),
+        }
+
+        return Ok(());
+    }
+
+    let prefix = format!("{setting_key}.");
+    let children: Vec<_> = entries
+        .iter()
+        .filter(|entry| entry.key.starts_with(&prefix))
+        .collect();
+
+    if children.is_empty() {
+        bail!("{setting_key} is not defined");
+    }
+
+    for entry in children {
+        print_entry(entry, args.source);
+    }
+
+    Ok(())
+}
+
+fn print_entry(entry: &ConfigEntry, with_source: bool) {
+    match with_source {
+        true => println!("[{}] {} ({})", entry.key, entry.value, entry.source),
+        false => println!("[{}] {}", entry.key, entry.value),
+    }
+}
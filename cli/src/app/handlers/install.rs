@@ -0,0 +1,77 @@
+use crate::app::commands::{InstallArgs, OutputFormat};
+use crate::app::progress::ConsoleProgress;
+use anyhow::{bail, Context, Result};
+use composer::cache::Cache;
+use composer::config::Config;
+use composer::downloader::DistDownloader;
+use composer::http::HttpDownloader;
+use composer::installer::Installer;
+use composer::lock_file::LockFile;
+use std::fs::File;
+
+pub fn install_handler(args: InstallArgs, config: &Config) -> Result<()> {
+    if !config.composer_lock.exists() {
+        bail!("No composer.lock file present, run the update command to create one");
+    }
+
+    let lock_file: LockFile = File::open(&config.composer_lock)?
+        .try_into()
+        .context("Could not read composer.lock")?;
+
+    install_lock_file(
+        &lock_file,
+        config,
+        !args.no_dev,
+        args.no_progress,
+        args.dry_run,
+        args.format,
+    )
+}
+
+/// Installs the packages of a lock file into `vendor-dir`, reporting the operations like Composer,
+/// or only lists the operations for a dry run, as JSON on stdout with `--format=json`
+pub fn install_lock_file(
+    lock_file: &LockFile,
+    config: &Config,
+    dev: bool,
+    no_progress: bool,
+    dry_run: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    eprintln!(
+        "Installing dependencies from lock file{}",
+        match dev {
+            true => " (including require-dev)",
+            false => "",
+        }
+    );
+
+    let cache = Cache::files(config);
+    let downloader =
+        DistDownloader::new(HttpDownloader::from_config(config)?).with_cache(cache.clone());
+    let installer = Installer::new(config, downloader).with_dev(dev);
+    let transaction = installer.plan(lock_file)?;
+
+    if dry_run {
+        match format {
+            OutputFormat::Text => eprintln!("{transaction}"),
+            OutputFormat::Json => println!("{}", transaction.to_json()?),
+        }
+
+        return Ok(());
+    }
+
+    eprintln!("{}", transaction.summary());
+    installer.execute(lock_file, &transaction, &ConsoleProgress::new(no_progress))?;
+
+    for operation in &transaction.operations {
+        eprintln!("  - {operation}");
+    }
+
+    let max_size = u64::from(config.cache_files_maxsize) * 1024 * 1024;
+    if let Err(error) = cache.gc(config.cache_files_ttl, max_size) {
+        eprintln!("Could not clean up the files cache: {error}");
+    }
+
+    Ok(())
+}
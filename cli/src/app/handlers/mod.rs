@@ -1 +1,5 @@
+pub mod config;
+pub mod install;
 pub mod require;
+pub mod store;
+pub mod update;
@@ -1,8 +1,158 @@
 use crate::app::commands::RequireArgs;
-use anyhow::Result;
+use crate::app::handlers::update::{
+    env_flag, read_composer_json, repository_set, request, update, UpdateOptions,
+};
+use anyhow::{bail, Context, Result};
+use composer::config::Config;
+use composer::lock_file::Package;
+use composer::resolver::{find_best_candidate, is_platform_package, recommended_constraint};
 
-pub fn require_handler(args: RequireArgs) -> Result<()> {
-    dbg!(args);
+/// Adds the packages to the require or require-dev of composer.json, then updates and installs the
+/// dependencies unless `--no-update` is given
+///
+/// Packages without a constraint are required with the one recommended for their best version,
+/// and composer.json is restored when the dependencies can't be updated.
+pub fn require_handler(args: RequireArgs, config: &Config) -> Result<()> {
+    let requirements = parse_requirements(args.packages.as_deref().unwrap_or_default());
 
-    Ok(())
+    if requirements.is_empty() {
+        bail!("No packages to require, pass them like vendor/package:^1.0");
+    }
+
+    let original = std::fs::read_to_string(&config.composer_json)
+        .with_context(|| format!("Could not open {}", config.composer_json.display()))?;
+    let mut composer_json = read_composer_json(config)?;
+    let options = UpdateOptions {
+        dev: !args.update_no_dev,
+        dry_run: args.dry_run,
+        format: args.format,
+        no_install: args.no_install,
+        no_progress: args.no_progress,
+        ignore_platform_reqs: args.ignore_platform_reqs,
+        prefer_stable: args.prefer_stable || env_flag("COMPOSER_PREFER_STABLE"),
+        prefer_lowest: args.prefer_lowest || env_flag("COMPOSER_PREFER_LOWEST"),
+    };
+    let repositories = repository_set(&composer_json, config)?;
+    let request = request(&composer_json, config, &options)?;
+
+    let mut added = Vec::new();
+
+    for (name, constraint) in requirements {
+        let constraint = match constraint {
+            Some(constraint) => constraint,
+            None => {
+                let candidates = match is_platform_package(&name) {
+                    true => request
+                        .platform
+                        .get(&name)
+                        .map(|version| vec![Package::new(&name, version)])
+                        .unwrap_or_default(),
+                    false => repositories
+                        .find_packages(&name)?
+                        .into_iter()
+                        .map(|(package, _)| package)
+                        .collect(),
+                };
+
+                let Some(package) = find_best_candidate(&request, &candidates) else {
+                    match candidates.is_empty() {
+                        true => bail!("Could not find package {name}."),
+                        false => bail!(
+                            "Could not find a version of package {name} matching your minimum-stability ({}). Require it with an explicit version constraint allowing its desired stability.",
+                            request.minimum_stability
+                        ),
+                    }
+                };
+
+                let constraint = match args.fixed {
+                    true => package.version.clone(),
+                    false => recommended_constraint(&request, package),
+                };
+                eprintln!("Using version {constraint} for {name}");
+
+                constraint
+            }
+        };
+
+        added.push((name, constraint));
+    }
+
+    let (section, other) = match args.dev {
+        true => (&mut composer_json.require_dev, &mut composer_json.require),
+        false => (&mut composer_json.require, &mut composer_json.require_dev),
+    };
+    let requires = section.get_or_insert_with(Default::default);
+
+    for (name, constraint) in added {
+        if let Some(other) = other.as_mut() {
+            other.retain(|other, _| !other.eq_ignore_ascii_case(&name));
+        }
+
+        requires.insert(name, constraint);
+    }
+
+    if config.sort_packages {
+        requires.sort_by_cached_key(|name, _| sort_key(name));
+    }
+
+    if !args.dry_run {
+        std::fs::write(&config.composer_json, composer_json.to_json_string()?)
+            .context("Could not write composer.json")?;
+        eprintln!("{} has been updated", config.composer_json.display());
+    }
+
+    if args.no_update {
+        return Ok(());
+    }
+
+    let result = update(&composer_json, &repositories, config, &options);
+
+    if result.is_err() && !args.dry_run {
+        eprintln!(
+            "Installation failed, reverting {} to its original content.",
+            config.composer_json.display()
+        );
+        std::fs::write(&config.composer_json, original).context("Could not write composer.json")?;
+    }
+
+    result
+}
+
+/// Reads `vendor/package:^1.0`, `vendor/package=^1.0` and `vendor/package ^1.0` arguments, or a
+/// package name followed by its constraint as the next argument, like Composer
+fn parse_requirements(arguments: &[String]) -> Vec<(String, Option<String>)> {
+    let mut requirements: Vec<(String, Option<String>)> = Vec::new();
+
+    for argument in arguments.iter().map(|argument| argument.trim()) {
+        if let Some((name, constraint)) = argument.split_once([' ', ':', '=']) {
+            requirements.push((name.into(), Some(constraint.trim().into())));
+            continue;
+        }
+
+        match requirements.last_mut() {
+            Some((_, constraint @ None))
+                if !argument.contains('/') && !is_platform_package(argument) =>
+            {
+                *constraint = Some(argument.into());
+            }
+            _ => requirements.push((argument.into(), None)),
+        }
+    }
+
+    requirements
+}
+
+/// Sorts PHP first, then the extensions, libraries and other platform packages, then the others,
+/// the way Composer does with `sort-packages`
+fn sort_key(name: &str) -> String {
+    let prefix = match name {
+        _ if !is_platform_package(name) => "5",
+        _ if name.starts_with("php") => "0",
+        _ if name.starts_with("hhvm") => "1",
+        _ if name.starts_with("ext") => "2",
+        _ if name.starts_with("lib") => "3",
+        _ => "4",
+    };
+
+    format!("{prefix}-{}", name.to_lowercase())
 }
@@ -0,0 +1,25 @@
+use crate::app::commands::{StoreArgs, StoreCommands};
+use anyhow::Result;
+use composer::config::Config;
+use composer::installer::Store;
+
+pub fn store_handler(args: StoreArgs, config: &Config) -> Result<()> {
+    let store = Store::from_config(config);
+
+    match args.command {
+        StoreCommands::Prune => {
+            let removed = store.prune()?;
+
+            eprintln!(
+                "Removed {removed} unused package{} from {}",
+                match removed {
+                    1 => "",
+                    _ => "s",
+                },
+                store.dir().display()
+            );
+        }
+    }
+
+    Ok(())
+}
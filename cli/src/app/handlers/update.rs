@@ -0,0 +1,143 @@
+use crate::app::commands::{OutputFormat, UpdateArgs};
+use crate::app::handlers::install::install_lock_file;
+use anyhow::{Context, Result};
+use composer::composer_json::ComposerJson;
+use composer::config::Config;
+use composer::http::HttpDownloader;
+use composer::repository::{parse_repositories, RepositoryKind, RepositorySet};
+use composer::resolver::{platform_packages, resolve, IgnorePlatformReqs, Request};
+use std::fs::File;
+
+/// How the dependencies are resolved and installed, from the flags of the update and require
+/// commands
+pub struct UpdateOptions {
+    pub dev: bool,
+    pub dry_run: bool,
+    pub format: OutputFormat,
+    pub no_install: bool,
+    pub no_progress: bool,
+    pub ignore_platform_reqs: bool,
+    pub prefer_stable: bool,
+    pub prefer_lowest: bool,
+}
+
+pub fn update_handler(args: UpdateArgs, config: &Config) -> Result<()> {
+    let composer_json = read_composer_json(config)?;
+    let repositories = repository_set(&composer_json, config)?;
+
+    update(
+        &composer_json,
+        &repositories,
+        config,
+        &UpdateOptions {
+            dev: !args.no_dev,
+            dry_run: args.dry_run,
+            format: args.format,
+            no_install: args.no_install,
+            no_progress: args.no_progress,
+            ignore_platform_reqs: args.ignore_platform_reqs,
+            prefer_stable: args.prefer_stable || env_flag("COMPOSER_PREFER_STABLE"),
+            prefer_lowest: args.prefer_lowest || env_flag("COMPOSER_PREFER_LOWEST"),
+        },
+    )
+}
+
+pub fn read_composer_json(config: &Config) -> Result<ComposerJson> {
+    File::open(&config.composer_json)
+        .with_context(|| format!("Could not open {}", config.composer_json.display()))?
+        .try_into()
+        .context("Could not read composer.json")
+}
+
+/// Whether a boolean environment variable like `COMPOSER_PREFER_STABLE` is set
+pub fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+}
+
+/// The repositories of a composer.json
+pub fn repository_set(composer_json: &ComposerJson, config: &Config) -> Result<RepositorySet> {
+    eprintln!("Loading composer repositories with package information");
+
+    let downloader = HttpDownloader::from_config(config)?;
+    let configs = parse_repositories(composer_json.repositories.as_ref())?;
+
+    for config in &configs {
+        if let RepositoryKind::Unsupported {
+            repository_type,
+            url,
+        } = &config.kind
+        {
+            eprintln!(
+                "Warning: {repository_type} repositories are not supported, {url} is skipped"
+            );
+        }
+    }
+
+    Ok(RepositorySet::from_configs(configs, config, &downloader)?)
+}
+
+/// The request to resolve a composer.json with the given options
+pub fn request(
+    composer_json: &ComposerJson,
+    config: &Config,
+    options: &UpdateOptions,
+) -> Result<Request> {
+    let mut request = Request::from_composer_json(composer_json, platform_packages(config))?;
+    request.prefer_stable |= options.prefer_stable;
+    request.prefer_lowest = options.prefer_lowest;
+
+    if options.ignore_platform_reqs {
+        request.ignore_platform_reqs = IgnorePlatformReqs::All;
+    }
+
+    Ok(request)
+}
+
+/// Resolves the requirements of a composer.json, writes composer.lock and installs it, or only
+/// lists the operations for a dry run
+///
+/// Like Composer, the lock file always contains the require-dev packages, `dev` only decides
+/// whether they are installed.
+pub fn update(
+    composer_json: &ComposerJson,
+    repositories: &RepositorySet,
+    config: &Config,
+    options: &UpdateOptions,
+) -> Result<()> {
+    let request = request(composer_json, config, options)?;
+
+    eprintln!(
+        "Updating dependencies{}",
+        match options.dev {
+            true => " (including require-dev)",
+            false => "",
+        }
+    );
+
+    let resolution = resolve(&request, &|name| repositories.find_packages(name))?;
+
+    for duplicate in repositories.duplicates() {
+        eprintln!("Warning: {duplicate}");
+    }
+
+    let lock_file = resolution.into_lock_file(&request, composer_json.content_hash()?);
+
+    if !options.dry_run {
+        eprintln!("Writing lock file");
+        std::fs::write(&config.composer_lock, lock_file.to_json_string()?)
+            .context("Could not write composer.lock")?;
+    }
+
+    if options.no_install {
+        return Ok(());
+    }
+
+    install_lock_file(
+        &lock_file,
+        config,
+        options.dev,
+        options.no_progress,
+        options.dry_run,
+        options.format,
+    )
+}
@@ -1,5 +1,6 @@
 pub mod commands;
 pub mod handlers;
+pub mod progress;
 
 use crate::app::commands::Commands;
 use clap::Parser;
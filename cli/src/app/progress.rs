@@ -0,0 +1,84 @@
+use composer::downloader::ProgressReporter;
+use composer::lock_file::Package;
+use std::io::Write;
+use std::sync::Mutex;
+
+const BAR_WIDTH: usize = 28;
+
+/// Reports downloads on stderr like Composer: a line per package, followed by a progress bar
+/// unless it is disabled with `--no-progress`
+pub struct ConsoleProgress {
+    bar: bool,
+    /// The number of finished and total downloads
+    counts: Mutex<(usize, usize)>,
+}
+
+impl ConsoleProgress {
+    pub fn new(no_progress: bool) -> Self {
+        ConsoleProgress {
+            bar: !no_progress,
+            counts: Mutex::new((0, 0)),
+        }
+    }
+
+    fn draw(&self, finished: usize, total: usize) {
+        let filled = match total {
+            0 => BAR_WIDTH,
+            _ => BAR_WIDTH * finished / total,
+        };
+        let bar = match filled {
+            BAR_WIDTH => "=".repeat(BAR_WIDTH),
+            _ => format!(
+                "{}>{}",
+                "=".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled - 1)
+            ),
+        };
+        let percent = (finished * 100).checked_div(total).unwrap_or(100);
+
+        eprint!("\r {finished}/{total} [{bar}] {percent:>3}%");
+        let _ = std::io::stderr().flush();
+    }
+
+    fn clear(&self) {
+        if self.bar {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+impl ProgressReporter for ConsoleProgress {
+    fn begin(&self, total: usize) {
+        *self.counts.lock().expect("counts lock") = (0, total);
+    }
+
+    fn started(&self, package: &Package) {
+        let counts = self.counts.lock().expect("counts lock");
+
+        self.clear();
+        eprintln!("  - Downloading {} ({})", package.name, package.version);
+
+        if self.bar {
+            self.draw(counts.0, counts.1);
+        }
+    }
+
+    fn finished(&self, _package: &Package) {
+        let mut counts = self.counts.lock().expect("counts lock");
+        counts.0 += 1;
+
+        if self.bar {
+            self.draw(counts.0, counts.1);
+        }
+    }
+
+    fn cached(&self, package: &Package) {
+        self.finished(package);
+    }
+
+    fn end(&self) {
+        let _counts = self.counts.lock().expect("counts lock");
+
+        self.clear();
+    }
+}
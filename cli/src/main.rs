@@ -1,4 +1,8 @@
+use crate::app::handlers::config::config_handler;
+use crate::app::handlers::install::install_handler;
 use crate::app::handlers::require::require_handler;
+use crate::app::handlers::store::store_handler;
+use crate::app::handlers::update::update_handler;
 use crate::app::{commands::Commands, App};
 use anyhow::Result;
 use clap::Parser;
@@ -8,12 +12,16 @@ mod app;
 
 fn main() -> Result<()> {
     let args = App::parse();
-    let config = Config::build()?;
+    let config = Config::build_with_overrides(args.command.config_overrides())?;
 
-    let _ = match args.command {
-        Commands::Require(args) => require_handler(args),
+    match args.command {
+        Commands::Config(args) => config_handler(args, &config),
+        Commands::Install(args) => install_handler(args, &config),
+        Commands::Require(args) => require_handler(args, &config),
+        Commands::Store(args) => store_handler(args, &config),
+        Commands::Update(args) => update_handler(args, &config),
         _ => todo!(),
-    };
+    }?;
 
     Ok(())
 }
@@ -0,0 +1,190 @@
+use crate::auth::Credentials;
+use crate::secret::Secret;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Looks up credentials through an external command, similar to git's credential helpers
+///
+/// The command is run through the shell with `get` appended, receives the request on stdin and
+/// answers on stdout, both as `key=value` lines:
+///
+/// ```text
+/// protocol=https
+/// host=repo.example.com
+/// ```
+///
+/// The helper answers with `username` and `password`, or with `bearer`, and answers nothing when
+/// it has no credentials for the host. Answers are cached for the lifetime of the helper.
+#[derive(Debug)]
+pub struct CredentialHelper {
+    command: String,
+    cache: Mutex<HashMap<(String, String), Option<Credentials>>>,
+}
+
+impl CredentialHelper {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(
+        &self,
+        protocol: &str,
+        host: &str,
+    ) -> Result<Option<Credentials>, CredentialHelperError> {
+        let key = (protocol.to_string(), host.to_string());
+
+        if let Some(credentials) = self.cache.lock().expect("cache lock").get(&key) {
+            return Ok(credentials.clone());
+        }
+
+        let credentials = self.run(protocol, host)?;
+
+        self.cache
+            .lock()
+            .expect("cache lock")
+            .insert(key, credentials.clone());
+
+        Ok(credentials)
+    }
+
+    fn run(
+        &self,
+        protocol: &str,
+        host: &str,
+    ) -> Result<Option<Credentials>, CredentialHelperError> {
+        let mut child = shell(&format!("{} get", self.command))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| CredentialHelperError::Spawn(self.command.clone(), error))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "protocol={protocol}\nhost={host}\n")
+                .map_err(|error| CredentialHelperError::Spawn(self.command.clone(), error))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|error| CredentialHelperError::Spawn(self.command.clone(), error))?;
+
+        if !output.status.success() {
+            return Err(CredentialHelperError::Failed(
+                self.command.clone(),
+                output.status.code(),
+            ));
+        }
+
+        let output = String::from_utf8(output.stdout)
+            .map_err(|_| CredentialHelperError::InvalidOutput(self.command.clone()))?;
+
+        parse_response(&output)
+            .map_err(|_| CredentialHelperError::InvalidOutput(self.command.clone()))
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+
+    shell
+}
+
+fn parse_response(output: &str) -> Result<Option<Credentials>, ()> {
+    let mut username = None;
+    let mut password = None;
+    let mut bearer = None;
+
+    for line in output.lines() {
+        if line.is_empty() {
+            break;
+        }
+
+        let (key, value) = line.split_once('=').ok_or(())?;
+
+        match key {
+            "username" => username = Some(value.to_string()),
+            "password" => password = Some(Secret::new(value)),
+            "bearer" => bearer = Some(Secret::new(value)),
+            _ => {}
+        }
+    }
+
+    Ok(match (bearer, username, password) {
+        (Some(token), _, _) => Some(Credentials::Bearer(token)),
+        (None, Some(username), Some(password)) => Some(Credentials::Basic { username, password }),
+        (None, None, None) => None,
+        _ => return Err(()),
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum CredentialHelperError {
+    #[error("Could not run credential helper {0}: {1}")]
+    Spawn(String, std::io::Error),
+
+    #[error("Credential helper {0} failed with exit code {1:?}")]
+    Failed(String, Option<i32>),
+
+    #[error("Credential helper {0} returned an invalid response")]
+    InvalidOutput(String),
+}
+
+#[cfg(unix)]
+#[test]
+fn test_credential_helper() {
+    let dir = tempfile::tempdir().unwrap();
+    let calls = dir.path().join("calls");
+    let script = dir.path().join("helper.sh");
+
+    std::fs::write(
+        &script,
+        format!(
+            r#"
+            echo called >> "{}"
+            while IFS='=' read -r key value; do
+                [ -z "$key" ] && break
+                [ "$key" = host ] && host="$value"
+            done
+            case "$host" in
+                basic.example.com) printf 'username=user\npassword=hunter2\n' ;;
+                bearer.example.com) printf 'bearer=token\n' ;;
+            esac
+            "#,
+            calls.display()
+        ),
+    )
+    .unwrap();
+
+    let helper = CredentialHelper::new(format!("sh {}", script.display()));
+
+    for _ in 0..2 {
+        assert!(matches!(
+            helper.get("https", "basic.example.com").unwrap(),
+            Some(Credentials::Basic { username, password }) if username == "user" && password.expose() == "hunter2"
+        ));
+    }
+
+    assert!(matches!(
+        helper.get("https", "bearer.example.com").unwrap(),
+        Some(Credentials::Bearer(token)) if token.expose() == "token"
+    ));
+    assert!(helper.get("https", "other.example.com").unwrap().is_none());
+
+    assert_eq!(std::fs::read_to_string(calls).unwrap().lines().count(), 3);
+}
@@ -0,0 +1,252 @@
+pub mod credential_helper;
+
+use crate::auth::credential_helper::{CredentialHelper, CredentialHelperError};
+use crate::composer_json::ComposerJson;
+use crate::config::{Config, GitlabToken};
+use crate::repository::{parse_repositories, RepositoryKind};
+use crate::secret::Secret;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use url::Url;
+
+/// Credentials to authenticate against a repository host
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Basic {
+        username: String,
+        password: Secret,
+    },
+    Bearer(Secret),
+    /// A GitHub OAuth token, sent as `Authorization: token ...`
+    GithubToken(Secret),
+    /// A GitLab private token, sent in the `PRIVATE-TOKEN` header
+    GitlabToken(Secret),
+    /// A Bitbucket OAuth consumer, which is exchanged for an access token for requests to
+    /// bitbucket.org and sent as basic auth to other hosts, like in Composer
+    BitbucketOAuth {
+        consumer_key: String,
+        consumer_secret: Secret,
+    },
+}
+
+impl Credentials {
+    /// The name and value of the HTTP header that carries the credentials
+    pub fn header(&self) -> (&'static str, String) {
+        match self {
+            Credentials::Basic { username, password } => {
+                let encoded = BASE64_STANDARD.encode(format!("{username}:{}", password.expose()));
+
+                ("Authorization", format!("Basic {encoded}"))
+            }
+            Credentials::Bearer(token) => ("Authorization", format!("Bearer {}", token.expose())),
+            Credentials::GithubToken(token) => {
+                ("Authorization", format!("token {}", token.expose()))
+            }
+            Credentials::GitlabToken(token) => ("PRIVATE-TOKEN", token.expose().to_string()),
+            Credentials::BitbucketOAuth {
+                consumer_key,
+                consumer_secret,
+            } => Credentials::Basic {
+                username: consumer_key.clone(),
+                password: consumer_secret.clone(),
+            }
+            .header(),
+        }
+    }
+}
+
+/// The credentials of each host, from the auth config and the credential helper
+///
+/// Like Composer, when several kinds of credentials are configured for a host, `bearer` wins over
+/// `http-basic`, which wins over the GitLab, GitHub and Bitbucket tokens. The credential helper is only asked
+/// for hosts without configured credentials, and only for the `github-domains`, the
+/// `gitlab-domains` and the hosts of the project's repositories, so it never learns about requests
+/// to public hosts like repo.packagist.org.
+#[derive(Debug, Default)]
+pub struct HostCredentials {
+    hosts: HashMap<String, Credentials>,
+    helper: Option<CredentialHelper>,
+    helper_hosts: HashSet<String>,
+}
+
+impl HostCredentials {
+    pub fn from_config(config: &Config) -> Self {
+        let mut hosts = HashMap::new();
+        let entries = |map: &Option<HashMap<String, Secret>>| {
+            map.iter()
+                .flatten()
+                .map(|(host, token)| (host.to_lowercase(), token.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        for (host, consumer) in config.bitbucket_oauth.iter().flatten() {
+            let credentials = Credentials::BitbucketOAuth {
+                consumer_key: consumer.consumer_key.clone(),
+                consumer_secret: consumer.consumer_secret.clone(),
+            };
+            hosts.insert(host.to_lowercase(), credentials);
+        }
+
+        for (host, token) in entries(&config.github_oauth) {
+            hosts.insert(host, Credentials::GithubToken(token));
+        }
+
+        for (host, token) in entries(&config.gitlab_oauth) {
+            hosts.insert(host, Credentials::Bearer(token));
+        }
+
+        for (host, token) in config.gitlab_tokens.iter().flatten() {
+            let credentials = match token {
+                GitlabToken::Token(token) => Credentials::GitlabToken(token.clone()),
+                GitlabToken::TokenWithUsername(token) => Credentials::Basic {
+                    username: token.username.clone(),
+                    password: token.token.clone(),
+                },
+            };
+            hosts.insert(host.to_lowercase(), credentials);
+        }
+
+        for (host, basic) in config.http_basic.iter().flatten() {
+            let credentials = Credentials::Basic {
+                username: basic.username.clone(),
+                password: basic.password.clone(),
+            };
+            hosts.insert(host.to_lowercase(), credentials);
+        }
+
+        for (host, token) in entries(&config.bearer) {
+            hosts.insert(host, Credentials::Bearer(token));
+        }
+
+        HostCredentials {
+            hosts,
+            helper: config
+                .credential_helper
+                .as_deref()
+                .map(CredentialHelper::new),
+            helper_hosts: helper_hosts(config),
+        }
+    }
+
+    /// The credentials of a host, which includes the port when it isn't the default one
+    ///
+    /// Credentials for `github.com` and `bitbucket.org` are used for their `api.` hosts too.
+    pub fn get(
+        &self,
+        protocol: &str,
+        host: &str,
+    ) -> Result<Option<Credentials>, CredentialHelperError> {
+        let host = host.to_lowercase();
+        let origin = match host.as_str() {
+            "api.github.com" => "github.com",
+            "api.bitbucket.org" => "bitbucket.org",
+            _ => host.as_str(),
+        };
+
+        if let Some(credentials) = self.hosts.get(origin) {
+            return Ok(Some(credentials.clone()));
+        }
+
+        let hostname = host.split(':').next().unwrap_or_default();
+
+        match &self.helper {
+            Some(helper)
+                if self.helper_hosts.contains(&host) || self.helper_hosts.contains(hostname) =>
+            {
+                helper.get(protocol, &host)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The hosts the credential helper is asked about, see [`HostCredentials`]
+fn helper_hosts(config: &Config) -> HashSet<String> {
+    let mut hosts: HashSet<String> = config
+        .github_domains
+        .iter()
+        .chain(&config.gitlab_domains)
+        .map(|domain| domain.to_lowercase())
+        .collect();
+
+    let composer_json: Option<ComposerJson> = File::open(&config.composer_json)
+        .ok()
+        .and_then(|file| file.try_into().ok());
+    let repositories = composer_json
+        .and_then(|composer_json| parse_repositories(composer_json.repositories.as_ref()).ok())
+        .unwrap_or_default();
+
+    for repository in repositories
+        .iter()
+        .filter(|repository| !repository.is_packagist())
+    {
+        let (RepositoryKind::Composer { url } | RepositoryKind::Vcs { url }) = &repository.kind
+        else {
+            continue;
+        };
+
+        if let Some(url) = Url::parse(url).ok().filter(|url| url.has_host()) {
+            let host = url.host_str().unwrap_or_default().to_lowercase();
+
+            hosts.insert(match url.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host,
+            });
+        }
+    }
+
+    hosts
+}
+
+#[cfg(unix)]
+#[test]
+fn test_helper_hosts() {
+    use crate::config::test_config;
+
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let script = project.path().join("helper.sh");
+    std::fs::write(&script, "cat > /dev/null; printf 'bearer=token\\n'").unwrap();
+
+    let composer_json = format!(
+        r#"{{
+            "repositories": [
+                {{"type": "composer", "url": "https://repo.example.com:8443"}},
+                {{"type": "vcs", "url": "https://git.example.com/acme/lib.git"}}
+            ],
+            "config": {{
+                "credential-helper": "sh {}",
+                "bitbucket-oauth": {{"bitbucket.org": {{"consumer-key": "key", "consumer-secret": "secret"}}}}
+            }}
+        }}"#,
+        script.display()
+    );
+    let credentials =
+        HostCredentials::from_config(&test_config(project.path(), home.path(), &composer_json));
+
+    for host in ["repo.example.com:8443", "git.example.com", "github.com"] {
+        assert!(credentials.get("https", host).unwrap().is_some(), "{host}");
+    }
+
+    for host in [
+        "repo.packagist.org",
+        "repo.example.com",
+        "other.example.com",
+    ] {
+        assert!(credentials.get("https", host).unwrap().is_none(), "{host}");
+    }
+
+    let bitbucket = credentials
+        .get("https", "api.bitbucket.org")
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        &bitbucket,
+        Credentials::BitbucketOAuth { consumer_key, .. } if consumer_key == "key"
+    ));
+    assert_eq!(
+        bitbucket.header(),
+        ("Authorization", "Basic a2V5OnNlY3JldA==".to_string())
+    );
+}
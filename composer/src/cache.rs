@@ -0,0 +1,283 @@
+use crate::config::Config;
+use regex::Regex;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+/// The file every process using a cache locks, shared while reading and writing entries and
+/// exclusively while collecting garbage
+const LOCK_FILE: &str = ".lock";
+
+/// A directory of cached files, laid out like Composer's caches so both can share them
+///
+/// Writing is best effort: a cache that can't be written, or is read-only because of
+/// `cache-read-only`, never fails the command that uses it.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    read_only: bool,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>, read_only: bool) -> Self {
+        Cache {
+            dir: dir.into(),
+            read_only,
+        }
+    }
+
+    /// The cache of a repository in `cache-repo-dir`, in a directory named after its URL
+    pub fn for_repository(cache_repo_dir: &Path, url: &str, read_only: bool) -> Self {
+        static UNSAFE: OnceLock<Regex> = OnceLock::new();
+        let unsafe_chars =
+            UNSAFE.get_or_init(|| Regex::new(r"(?i)[^a-z0-9.]").expect("valid regex"));
+
+        let url = crate::secret::sanitize_urls(url);
+        let name = unsafe_chars.replace_all(&url, "-");
+
+        Cache::new(cache_repo_dir.join(name.as_ref()), read_only)
+    }
+
+    /// The cache of dist archives in `cache-files-dir`
+    pub fn files(config: &Config) -> Self {
+        Cache::new(&*config.cache_files_dir, config.cache_read_only)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The path of an entry, see [`entry_path`]
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(entry_path(key))
+    }
+
+    pub fn read(&self, key: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.path(key)).ok()
+    }
+
+    /// Writes an entry through a temporary file, so readers never see a partial entry
+    pub fn write(&self, key: &str, data: &[u8]) {
+        if self.read_only {
+            return;
+        }
+
+        let path = self.path(key);
+        let write = || -> std::io::Result<()> {
+            let parent = path.parent().unwrap_or(&self.dir);
+            std::fs::create_dir_all(parent)?;
+
+            let temp = temp_path(&path);
+            let result = std::fs::File::create(&temp)
+                .and_then(|mut file| file.write_all(data))
+                .and_then(|_| std::fs::rename(&temp, &path));
+
+            if result.is_err() {
+                let _ = std::fs::remove_file(&temp);
+            }
+
+            result
+        };
+
+        let _ = write();
+    }
+}
+
+impl Cache {
+    /// Takes a shared lock on the cache until the returned lock is dropped, so that no other
+    /// process collects garbage while entries are in use
+    ///
+    /// Returns `None` when the cache directory can't be created, e.g. in a read-only cache.
+    pub fn lock(&self) -> Option<CacheLock> {
+        let file = lock_file(&self.dir).ok()?;
+        file.lock_shared().ok()?;
+
+        Some(CacheLock { _file: file })
+    }
+
+    /// Marks an entry as recently used, so it is the last to be evicted
+    pub fn touch(&self, key: &str) {
+        if self.read_only {
+            return;
+        }
+
+        if let Ok(file) = File::options().write(true).open(self.path(key)) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+    }
+
+    /// Removes the entries not used for longer than `ttl`, then the least recently used ones
+    /// until the cache is no larger than `max_size` bytes, and returns how many were removed
+    ///
+    /// Nothing is collected in a read-only cache, nor while another process holds a lock on it.
+    pub fn gc(&self, ttl: Duration, max_size: u64) -> std::io::Result<usize> {
+        if self.read_only || !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let lock = lock_file(&self.dir)?;
+
+        if lock.try_lock().is_err() {
+            return Ok(0);
+        }
+
+        let mut entries = Vec::new();
+        collect_entries(&self.dir, &mut entries)?;
+        entries.sort_by_key(|(_, modified, _)| *modified);
+
+        let expired = SystemTime::now()
+            .checked_sub(ttl)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut size: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        let mut removed = 0;
+
+        for (path, modified, entry_size) in entries {
+            if modified >= expired && size <= max_size {
+                break;
+            }
+
+            std::fs::remove_file(&path)?;
+            size -= entry_size;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+}
+
+/// A shared lock on a cache, released when dropped
+#[derive(Debug)]
+pub struct CacheLock {
+    _file: File,
+}
+
+/// Opens the lock file of a directory, creating both when they don't exist
+pub(crate) fn lock_file(dir: &Path) -> std::io::Result<File> {
+    std::fs::create_dir_all(dir)?;
+
+    File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE))
+}
+
+/// Collects the files of a directory and its subdirectories, with their modification time and size
+fn collect_entries(
+    dir: &Path,
+    entries: &mut Vec<(PathBuf, SystemTime, u64)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            collect_entries(&entry.path(), entries)?;
+        } else if entry.file_name() != LOCK_FILE {
+            entries.push((entry.path(), metadata.modified()?, metadata.len()));
+        }
+    }
+
+    Ok(())
+}
+
+/// The relative path of a cache entry, where characters Composer doesn't allow in keys are
+/// replaced by `-`
+///
+/// Keys may contain `/` to nest entries in directories, but empty, `.` and `..` segments are
+/// replaced too, so an entry never resolves outside the cache directory.
+pub(crate) fn entry_path(key: &str) -> PathBuf {
+    static UNSAFE: OnceLock<Regex> = OnceLock::new();
+    let unsafe_chars =
+        UNSAFE.get_or_init(|| Regex::new(r"(?i)[^a-z0-9._~$-]").expect("valid regex"));
+
+    key.split('/')
+        .map(|segment| match segment {
+            "" | "." | ".." => "-".to_string(),
+            segment => unsafe_chars.replace_all(segment, "-").into_owned(),
+        })
+        .collect()
+}
+
+/// A path next to `path` which is unique to this write
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+
+    path.with_file_name(name)
+}
+
+#[test]
+fn test_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Cache::for_repository(dir.path(), "https://repo.example.com/", false);
+
+    assert_eq!(
+        cache.dir().file_name().unwrap(),
+        "https---repo.example.com-"
+    );
+    assert!(cache.read("packages.json").is_none());
+
+    cache.write("provider-acme~lib.json", b"{}");
+    assert_eq!(cache.read("provider-acme~lib.json").unwrap(), b"{}");
+    assert!(cache.path("a b?.json").ends_with("a-b-.json"));
+    assert_eq!(
+        cache.path("acme/lib/../../../../tmp/x"),
+        cache.dir().join("acme/lib/-/-/-/-/tmp/x")
+    );
+    assert_eq!(cache.path("/etc/passwd"), cache.dir().join("-/etc/passwd"));
+
+    let read_only = Cache::new(cache.dir(), true);
+    read_only.write("packages.json", b"{}");
+    assert!(read_only.read("packages.json").is_none());
+    assert!(read_only.read("provider-acme~lib.json").is_some());
+}
+
+#[test]
+fn test_gc() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = Cache::new(dir.path(), false);
+    let now = SystemTime::now();
+    let hours_ago = |hours: u64| now - Duration::from_secs(hours * 3600);
+
+    for (key, size, used) in [
+        ("acme/expired/1.0.0-a.zip", 10, hours_ago(48)),
+        ("acme/old/1.0.0-b.zip", 40, hours_ago(12)),
+        ("acme/recent/1.0.0-c.zip", 40, hours_ago(6)),
+        ("acme/new/1.0.0-d.zip", 40, hours_ago(1)),
+    ] {
+        cache.write(key, &vec![0; size]);
+        let file = File::options().write(true).open(cache.path(key)).unwrap();
+        file.set_modified(used).unwrap();
+    }
+
+    cache.touch("acme/old/1.0.0-b.zip");
+
+    let lock = cache.lock().unwrap();
+    assert_eq!(cache.gc(Duration::from_secs(24 * 3600), 80).unwrap(), 0);
+    drop(lock);
+
+    assert_eq!(
+        Cache::new(dir.path(), true).gc(Duration::ZERO, 0).unwrap(),
+        0
+    );
+
+    assert_eq!(cache.gc(Duration::from_secs(24 * 3600), 80).unwrap(), 2);
+    assert!(cache.read("acme/expired/1.0.0-a.zip").is_none());
+    assert!(cache.read("acme/recent/1.0.0-c.zip").is_none());
+    assert!(cache.read("acme/old/1.0.0-b.zip").is_some());
+    assert!(cache.read("acme/new/1.0.0-d.zip").is_some());
+}
@@ -0,0 +1,508 @@
+use crate::lock_file::{Author, AutoloadConfig, Funding};
+use indexmap::IndexMap;
+use md5::{Digest, Md5};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::Read;
+use std::ops::{Deref, DerefMut};
+
+/// The root `composer.json` of a project or package
+///
+/// All keys of the official schema are modelled. Keys that are unknown to the schema are kept in
+/// [`ComposerJson::other`] and the original key order is remembered, so reading and writing a file
+/// without changing it yields the same document.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ComposerJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_feature_branches: Option<Vec<String>>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub package_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<Ordered<Author>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support: Option<Ordered<Support>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funding: Option<Vec<Ordered<Funding>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_dev: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provide: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggest: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoload: Option<Ordered<AutoloadConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoload_dev: Option<Ordered<AutoloadConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_path: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_stability: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer_stable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repositories: Option<Repositories>,
+    /// The raw `config` section, which is applied on top of the global configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Map<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<IndexMap<String, Script>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripts_descriptions: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scripts_aliases: Option<IndexMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<Bin>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<Ordered<Archive>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub php_ext: Option<Ordered<PhpExt>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abandoned: Option<Abandoned>,
+    #[serde(rename = "_comment", skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Value>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+    #[serde(skip)]
+    key_order: Vec<String>,
+}
+
+impl ComposerJson {
+    pub fn from_json_str(data: &str) -> Result<Self, ComposerJsonError> {
+        let value: Map<String, Value> = serde_json::from_str(data)?;
+        let key_order = value.keys().cloned().collect();
+
+        let mut composer_json: ComposerJson = serde_json::from_value(Value::Object(value))?;
+        composer_json.key_order = key_order;
+
+        Ok(composer_json)
+    }
+
+    /// Serializes the document the way Composer writes it: pretty-printed with 4 spaces,
+    /// unescaped slashes and unicode, and a trailing newline
+    pub fn to_json_string(&self) -> Result<String, ComposerJsonError> {
+        let mut data = to_pretty_json(&Value::Object(self.to_map()?))?;
+        data.push('\n');
+
+        Ok(data)
+    }
+
+    /// The `content-hash` of the lock file, computed like Composer from the keys which affect the
+    /// resolution, so Composer sees the lock file as up to date and the other way around
+    pub fn content_hash(&self) -> Result<String, ComposerJsonError> {
+        const RELEVANT_KEYS: [&str; 11] = [
+            "name",
+            "version",
+            "require",
+            "require-dev",
+            "conflict",
+            "replace",
+            "provide",
+            "minimum-stability",
+            "prefer-stable",
+            "repositories",
+            "extra",
+        ];
+
+        let mut content = self.to_map()?;
+        let platform = content
+            .get("config")
+            .and_then(|config| config.get("platform"))
+            .cloned();
+        content.retain(|key, _| RELEVANT_KEYS.contains(&key.as_str()));
+
+        if let Some(platform) = platform {
+            let mut config = Map::new();
+            config.insert("platform".into(), platform);
+            content.insert("config".into(), Value::Object(config));
+        }
+
+        let mut entries: Vec<_> = content.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut data = String::new();
+        write_php_json(&Value::Object(entries.into_iter().collect()), &mut data);
+
+        Ok(format!("{:x}", Md5::digest(data.as_bytes())))
+    }
+
+    fn to_map(&self) -> Result<Map<String, Value>, serde_json::Error> {
+        let Value::Object(value) = serde_json::to_value(self)? else {
+            unreachable!("ComposerJson always serializes to an object");
+        };
+
+        Ok(in_key_order(value, &self.key_order))
+    }
+}
+
+impl TryInto<ComposerJson> for File {
+    type Error = ComposerJsonError;
+
+    fn try_into(mut self) -> Result<ComposerJson, Self::Error> {
+        let mut data = String::new();
+        self.read_to_string(&mut data)?;
+
+        ComposerJson::from_json_str(&data)
+    }
+}
+
+/// The entries of `value` in `key_order`, followed by the keys that weren't in it
+fn in_key_order(mut value: Map<String, Value>, key_order: &[String]) -> Map<String, Value> {
+    let mut ordered = Map::with_capacity(value.len());
+
+    for key in key_order {
+        if let Some(entry) = value.remove(key) {
+            ordered.insert(key.clone(), entry);
+        }
+    }
+
+    ordered.append(&mut value);
+
+    ordered
+}
+
+/// Writes a value like PHP's `json_encode` without flags: with escaped slashes and unicode, and
+/// empty objects as the empty arrays they are decoded to
+fn write_php_json(value: &Value, data: &mut String) {
+    match value {
+        Value::Array(items) => {
+            data.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    data.push(',');
+                }
+                write_php_json(item, data);
+            }
+            data.push(']');
+        }
+        Value::Object(entries) if entries.is_empty() => data.push_str("[]"),
+        Value::Object(entries) => {
+            data.push('{');
+            for (index, (key, item)) in entries.iter().enumerate() {
+                if index > 0 {
+                    data.push(',');
+                }
+                write_php_string(key, data);
+                data.push(':');
+                write_php_json(item, data);
+            }
+            data.push('}');
+        }
+        Value::String(string) => write_php_string(string, data),
+        value => data.push_str(&value.to_string()),
+    }
+}
+
+fn write_php_string(string: &str, data: &mut String) {
+    data.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => data.push_str("\\\""),
+            '\\' => data.push_str("\\\\"),
+            '/' => data.push_str("\\/"),
+            '\n' => data.push_str("\\n"),
+            '\r' => data.push_str("\\r"),
+            '\t' => data.push_str("\\t"),
+            '\u{8}' => data.push_str("\\b"),
+            '\u{c}' => data.push_str("\\f"),
+            char if char.is_ascii() && !char.is_ascii_control() => data.push(char),
+            char => {
+                let mut units = [0; 2];
+                for unit in char.encode_utf16(&mut units) {
+                    data.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+
+    data.push('"');
+}
+
+/// A nested object of the schema which remembers the order of its keys, so it is written back
+/// the way it was read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ordered<T> {
+    pub value: T,
+    key_order: Vec<String>,
+}
+
+impl<T> From<T> for Ordered<T> {
+    fn from(value: T) -> Self {
+        Ordered {
+            value,
+            key_order: Vec::new(),
+        }
+    }
+}
+
+impl<T> Deref for Ordered<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Ordered<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Ordered<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Map::<String, Value>::deserialize(deserializer)?;
+        let key_order = value.keys().cloned().collect();
+
+        Ok(Ordered {
+            value: serde_json::from_value(Value::Object(value))
+                .map_err(serde::de::Error::custom)?,
+            key_order,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Ordered<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match serde_json::to_value(&self.value).map_err(serde::ser::Error::custom)? {
+            Value::Object(value) => in_key_order(value, &self.key_order).serialize(serializer),
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+pub(crate) fn to_pretty_json<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    let mut buffer = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+
+    value.serialize(&mut serializer)?;
+
+    Ok(String::from_utf8(buffer).expect("serde_json only writes valid UTF-8"))
+}
+
+#[derive(Debug)]
+pub enum ComposerJsonError {
+    ReadError(std::io::Error),
+    DeserializeError(serde_json::Error),
+}
+
+impl From<std::io::Error> for ComposerJsonError {
+    fn from(error: std::io::Error) -> Self {
+        Self::ReadError(error)
+    }
+}
+
+impl From<serde_json::Error> for ComposerJsonError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::DeserializeError(error)
+    }
+}
+
+impl Display for ComposerJsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to read composer.json: {}",
+            match self {
+                ComposerJsonError::ReadError(error) => error.to_string(),
+                ComposerJsonError::DeserializeError(error) => error.to_string(),
+            }
+        )
+    }
+}
+
+impl Error for ComposerJsonError {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum License {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Bin {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Script {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+/// `repositories` can either be a list or a map keyed by an arbitrary name
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Repositories {
+    List(Vec<Value>),
+    Map(IndexMap<String, Value>),
+}
+
+/// Whether the package is abandoned, optionally naming the package to use instead
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Abandoned {
+    Flag(bool),
+    Replacement(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Support {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issues: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wiki: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub irc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security: Option<String>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Archive {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<String>>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PhpExt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_zts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_nts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_families: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_families_exclude: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configure_options: Option<Vec<Ordered<PhpExtConfigureOption>>>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct PhpExtConfigureOption {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub needs_value: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[test]
+fn test_simple_composer_json() {
+    let composer_json: ComposerJson = File::open("./fixtures/simple.json")
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+    assert_eq!(composer_json.name.as_deref(), Some("composer-rs/simple"));
+    assert_eq!(
+        composer_json.require.as_ref().unwrap().get("slim/slim"),
+        Some(&"^4.12".to_string())
+    );
+    assert_eq!(composer_json.config.as_ref().unwrap().len(), 1);
+}
+
+#[test]
+fn test_composer_json_round_trip() {
+    for fixture in ["./fixtures/simple.json", "./fixtures/full.json"] {
+        let data = std::fs::read_to_string(fixture).unwrap();
+        let composer_json = ComposerJson::from_json_str(&data).unwrap();
+
+        assert_eq!(composer_json.to_json_string().unwrap(), data, "{fixture}");
+    }
+}
+
+#[test]
+fn test_content_hash() {
+    let composer_json = ComposerJson::from_json_str(
+        r#"{
+            "name": "acme/app",
+            "description": "Not relevant",
+            "require": {"acme/lib": "^1.0 || ~2.0"},
+            "extra": {},
+            "config": {"sort-packages": true, "platform": {"php": "8.2.0"}}
+        }"#,
+    )
+    .unwrap();
+
+    // md5 of {"config":{"platform":{"php":"8.2.0"}},"extra":[],"name":"acme\/app","require":{"acme\/lib":"^1.0 || ~2.0"}}
+    assert_eq!(
+        composer_json.content_hash().unwrap(),
+        "ade17cfe732393997f0686bcdfa9649a"
+    );
+}
@@ -0,0 +1,519 @@
+use crate::config::{ConfigError, ConfigSource, AUTH_KEYS};
+use crate::dirs::Directories;
+use crate::secret::REDACTED;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use zeroize::Zeroize;
+
+/// Config keys that can be overridden by a `COMPOSER_*` environment variable holding a plain string
+const ENV_STRING_KEYS: [&str; 9] = [
+    "vendor-dir",
+    "bin-dir",
+    "data-dir",
+    "cache-dir",
+    "cache-files-dir",
+    "cache-repo-dir",
+    "cache-vcs-dir",
+    "cafile",
+    "capath",
+];
+
+/// Composer's defaults, apart from the platform specific directories
+const DEFAULTS: &str = r#"{
+    "allow-plugins": {},
+    "allow-superuser": false,
+    "apcu-autoloader": false,
+    "archive-format": "tar",
+    "audit": {
+        "ignore": [],
+        "abandoned": "fail"
+    },
+    "autoloader-suffix": null,
+    "bearer": {},
+    "bin-compat": "auto",
+    "bin-dir": "{$vendor-dir}/bin",
+    "bitbucket-oauth": {},
+    "cache-files-dir": "{$cache-dir}/files",
+    "cache-files-maxsize": "300MiB",
+    "cache-files-ttl": null,
+    "cache-read-only": false,
+    "cache-repo-dir": "{$cache-dir}/repo",
+    "cache-ttl": 15552000,
+    "cache-vcs-dir": "{$cache-dir}/vcs",
+    "cafile": null,
+    "capath": null,
+    "classmap-authoritative": false,
+    "credential-helper": null,
+    "disable-tls": false,
+    "discard-changes": false,
+    "github-domains": ["github.com"],
+    "github-expose-hostname": true,
+    "github-oauth": {},
+    "github-protocols": ["https", "ssh", "git"],
+    "gitlab-domains": ["gitlab.com"],
+    "gitlab-oauth": {},
+    "gitlab-protocol": null,
+    "gitlab-token": {},
+    "htaccess-protect": true,
+    "http-basic": {},
+    "install-strategy": "vendor",
+    "lock": true,
+    "notify-on-install": true,
+    "optimize-autoloader": false,
+    "platform": {},
+    "platform-check": "php-only",
+    "preferred-install": "dist",
+    "prepend-autoloader": true,
+    "process-timeout": 300,
+    "secure-http": true,
+    "secure-svn-domains": [],
+    "sort-packages": false,
+    "store-auths": "prompt",
+    "use-github-api": true,
+    "use-include-path": true,
+    "use-parent-dir": "prompt",
+    "vendor-dir": "vendor"
+}"#;
+
+/// The maximum depth of nested `{$key}` placeholders, to guard against self-referencing values
+const MAX_PLACEHOLDER_DEPTH: usize = 16;
+
+/// Config values in their raw JSON form, as found in `config.json` and `composer.json`
+///
+/// Layers are merged in order of precedence, the last merged layer winning. Placeholders such as
+/// `{$vendor-dir}` are only expanded when a value is read, so a default like
+/// `"bin-dir": "{$vendor-dir}/bin"` follows a `vendor-dir` set by a later layer.
+///
+/// The layer that last set each key is remembered, so the origin of a value can be reported. The
+/// children of maps are tracked as `parent.child` keys, as maps like `http-basic` merge the hosts
+/// of several layers.
+#[derive(Debug)]
+pub(crate) struct RawConfig {
+    values: Map<String, Value>,
+    sources: HashMap<String, ConfigSource>,
+}
+
+impl RawConfig {
+    pub(crate) fn with_defaults(dirs: &Directories) -> Self {
+        let mut values: Map<String, Value> =
+            serde_json::from_str(DEFAULTS).expect("the defaults are valid JSON");
+
+        for (key, dir) in [
+            ("archive-dir", &dirs.archive_dir),
+            ("cache-dir", &dirs.cache_dir),
+            ("data-dir", &dirs.data_dir),
+            ("home", &dirs.home_dir),
+        ] {
+            values.insert(key.into(), dir.to_string_lossy().into());
+        }
+
+        let sources = values
+            .keys()
+            .map(|key| (key.clone(), ConfigSource::Default))
+            .collect();
+
+        Self { values, sources }
+    }
+
+    /// Merges a `config` section on top of the current values, following Composer's merge rules
+    pub(crate) fn merge(&mut self, config: Map<String, Value>, source: ConfigSource) {
+        for (key, value) in config {
+            self.sources.insert(key.clone(), source.clone());
+
+            let children: Vec<String> = match &value {
+                Value::Object(children) => children.keys().cloned().collect(),
+                Value::String(_) if key == "preferred-install" => vec!["*".into()],
+                _ => Vec::new(),
+            };
+
+            let merged = match (key.as_str(), self.values.remove(&key), value) {
+                (
+                    "github-domains" | "gitlab-domains",
+                    Some(Value::Array(mut existing)),
+                    Value::Array(new),
+                ) => {
+                    for domain in new {
+                        if !existing.contains(&domain) {
+                            existing.push(domain);
+                        }
+                    }
+
+                    Value::Array(existing)
+                }
+                // A string is turned into the `*` pattern, patterns set again keep their place and
+                // `*` is moved last, so that it only applies to packages no other pattern matches.
+                // The `--prefer-*` flags replace all patterns instead.
+                ("preferred-install", Some(existing), new)
+                    if (existing.is_object() || new.is_object())
+                        && !matches!(source, ConfigSource::CommandLine(_)) =>
+                {
+                    let patterns = |value: Value| match value {
+                        Value::Object(patterns) => patterns,
+                        value => Map::from_iter([("*".to_string(), value)]),
+                    };
+                    let mut merged = patterns(existing);
+                    merged.extend(patterns(new));
+
+                    let (wildcard, mut merged): (Map<_, _>, Map<_, _>) =
+                        merged.into_iter().partition(|(pattern, _)| pattern == "*");
+                    merged.extend(wildcard);
+
+                    Value::Object(merged)
+                }
+                (
+                    "allow-plugins" | "platform",
+                    Some(Value::Object(mut existing)),
+                    Value::Object(new),
+                ) => {
+                    // Newly merged patterns take priority, so they are moved to the front
+                    let mut merged = new;
+
+                    for (name, value) in existing.iter_mut() {
+                        if !merged.contains_key(name) {
+                            merged.insert(name.clone(), value.take());
+                        }
+                    }
+
+                    Value::Object(merged)
+                }
+                (
+                    "audit" | "bearer" | "bitbucket-oauth" | "github-oauth" | "gitlab-oauth"
+                    | "gitlab-token" | "http-basic",
+                    Some(Value::Object(mut existing)),
+                    Value::Object(new),
+                ) => {
+                    existing.extend(new);
+
+                    Value::Object(existing)
+                }
+                (_, _, value) => {
+                    let prefix = format!("{key}.");
+                    self.sources.retain(|child, _| !child.starts_with(&prefix));
+
+                    value
+                }
+            };
+
+            for child in children {
+                self.sources
+                    .insert(format!("{key}.{child}"), source.clone());
+            }
+
+            self.values.insert(key, merged);
+        }
+    }
+
+    /// Applies the `COMPOSER_*` environment variables that override config values
+    pub(crate) fn merge_env(
+        &mut self,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        let mut overrides = Vec::new();
+
+        for key in ENV_STRING_KEYS {
+            let name = env_var_name(key);
+
+            if let Some(value) = env(&name) {
+                overrides.push((key, Value::String(value), name));
+            }
+        }
+
+        if let Some(value) = env("COMPOSER_PROCESS_TIMEOUT") {
+            // Mirrors PHP's integer cast, where anything non-numeric becomes 0
+            let timeout = value.trim().parse::<u32>().unwrap_or(0);
+            overrides.push((
+                "process-timeout",
+                timeout.into(),
+                "COMPOSER_PROCESS_TIMEOUT".into(),
+            ));
+        }
+
+        if let Some(value) = env("COMPOSER_HTACCESS_PROTECT") {
+            let protect = value != "false" && is_truthy(&value);
+            overrides.push((
+                "htaccess-protect",
+                protect.into(),
+                "COMPOSER_HTACCESS_PROTECT".into(),
+            ));
+        }
+
+        if let Some(value) = env("COMPOSER_ALLOW_SUPERUSER") {
+            overrides.push((
+                "allow-superuser",
+                is_truthy(&value).into(),
+                "COMPOSER_ALLOW_SUPERUSER".into(),
+            ));
+        }
+
+        if let Some(value) = env("COMPOSER_DISCARD_CHANGES") {
+            let discard = match value.as_str() {
+                "stash" => Value::String(value),
+                "true" | "1" => Value::Bool(true),
+                "false" | "0" => Value::Bool(false),
+                _ => {
+                    return Err(ConfigError::InvalidValue(
+                        "COMPOSER_DISCARD_CHANGES".into(),
+                        "one of true, false or stash".into(),
+                    ))
+                }
+            };
+            overrides.push((
+                "discard-changes",
+                discard,
+                "COMPOSER_DISCARD_CHANGES".into(),
+            ));
+        }
+
+        if let Some(value) = env("COMPOSER_AUDIT_ABANDONED") {
+            if !matches!(value.as_str(), "ignore" | "report" | "fail") {
+                return Err(ConfigError::InvalidValue(
+                    "COMPOSER_AUDIT_ABANDONED".into(),
+                    "one of ignore, report or fail".into(),
+                ));
+            }
+
+            overrides.push((
+                "audit",
+                json!({ "abandoned": value }),
+                "COMPOSER_AUDIT_ABANDONED".into(),
+            ));
+        }
+
+        for (key, value, name) in overrides {
+            let mut config = Map::new();
+            config.insert(key.into(), value);
+
+            self.merge(config, ConfigSource::Environment(name));
+        }
+
+        Ok(())
+    }
+
+    /// Wipes all credentials once they have been resolved, leaving a marker per host so listing
+    /// the config still shows which hosts have credentials
+    pub(crate) fn redact_credentials(&mut self) {
+        for key in AUTH_KEYS {
+            if let Some(Value::Object(hosts)) = self.values.get_mut(key) {
+                for credentials in hosts.values_mut() {
+                    zeroize_value(credentials);
+                    *credentials = Value::String(REDACTED.into());
+                }
+            }
+        }
+    }
+
+    /// Where a key, or the child of a map as `parent.child`, was last set
+    pub(crate) fn source_of(&self, key: &str) -> Option<&ConfigSource> {
+        self.sources.get(key).or_else(|| {
+            let (parent, child) = key.split_once('.')?;
+            self.values.get(parent)?.as_object()?.get(child)?;
+
+            self.sources.get(parent)
+        })
+    }
+
+    /// Lists all values the way `config --list` shows them, with nested maps flattened to
+    /// `parent.child` keys
+    pub(crate) fn entries(&self) -> Result<Vec<(String, String, &ConfigSource)>, ConfigError> {
+        let mut entries = Vec::new();
+
+        for (key, value) in &self.values {
+            let source = &self.sources[key];
+
+            match value {
+                Value::String(_) => {
+                    let expanded = self.get_expanded(key)?.unwrap_or_default();
+                    entries.push((key.clone(), expanded, source));
+                }
+                Value::Object(children) if !children.is_empty() => {
+                    for (child, value) in children {
+                        let child = format!("{key}.{child}");
+                        let source = self.sources.get(&child).unwrap_or(source);
+                        entries.push((child, display_value(value), source));
+                    }
+                }
+                value => entries.push((key.clone(), display_value(value), source)),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key).filter(|value| !value.is_null())
+    }
+
+    /// Reads a string value with all `{$key}` placeholders expanded
+    pub(crate) fn get_expanded(&self, key: &str) -> Result<Option<String>, ConfigError> {
+        self.expand(key, 0)
+    }
+
+    fn expand(&self, key: &str, depth: usize) -> Result<Option<String>, ConfigError> {
+        if depth > MAX_PLACEHOLDER_DEPTH {
+            return Err(ConfigError::InvalidValue(
+                key.into(),
+                "a value without circular placeholders".into(),
+            ));
+        }
+
+        let value = match self.get(key) {
+            None => return Ok(None),
+            Some(Value::String(value)) => value,
+            Some(_) => return Err(ConfigError::InvalidValue(key.into(), "a string".into())),
+        };
+
+        let mut expanded = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+
+        while let Some(start) = rest.find("{$") {
+            let Some(length) = rest[start..].find('}') else {
+                break;
+            };
+
+            let placeholder = &rest[start + 2..start + length];
+
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(&self.expand(placeholder, depth + 1)?.unwrap_or_default());
+            rest = &rest[start + length + 1..];
+        }
+
+        expanded.push_str(rest);
+
+        Ok(Some(expanded))
+    }
+}
+
+/// Converts a config key such as `cache-files-dir` to its environment variable `COMPOSER_CACHE_FILES_DIR`
+pub(crate) fn env_var_name(key: &str) -> String {
+    format!("COMPOSER_{}", key.to_uppercase().replace('-', "_"))
+}
+
+fn zeroize_value(value: &mut Value) {
+    match value {
+        Value::String(value) => value.zeroize(),
+        Value::Array(items) => items.iter_mut().for_each(zeroize_value),
+        Value::Object(children) => children.values_mut().for_each(zeroize_value),
+        _ => {}
+    }
+}
+
+/// Formats a value like Composer's `config` command does
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".into(),
+        Value::String(value) => value.clone(),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(display_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(children) => format!(
+            "{{{}}}",
+            children
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", display_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        value => value.to_string(),
+    }
+}
+
+/// Mirrors PHP's boolean cast of a string
+fn is_truthy(value: &str) -> bool {
+    !value.is_empty() && value != "0"
+}
+
+#[test]
+fn test_later_layers_take_precedence() {
+    let mut raw = RawConfig {
+        values: Map::new(),
+        sources: HashMap::new(),
+    };
+
+    raw.merge(
+        json!({ "vendor-dir": "lib", "github-domains": ["github.com"] })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ConfigSource::Global(std::path::Path::new("config.json").into()),
+    );
+    raw.merge(
+        json!({ "vendor-dir": "deps", "github-domains": ["git.example.com"] })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ConfigSource::Project(std::path::Path::new("composer.json").into()),
+    );
+
+    assert_eq!(raw.get("vendor-dir"), Some(&json!("deps")));
+    assert_eq!(
+        raw.get("github-domains"),
+        Some(&json!(["github.com", "git.example.com"]))
+    );
+    assert_eq!(
+        raw.source_of("vendor-dir"),
+        Some(&ConfigSource::Project(
+            std::path::Path::new("composer.json").into()
+        ))
+    );
+
+    for layer in [
+        json!({ "preferred-install": "dist" }),
+        json!({ "preferred-install": { "acme/*": "source", "*": "auto" } }),
+        json!({ "preferred-install": { "other/*": "source", "acme/*": "dist" } }),
+    ] {
+        raw.merge(layer.as_object().unwrap().clone(), ConfigSource::Default);
+    }
+
+    assert_eq!(
+        serde_json::to_string(raw.get("preferred-install").unwrap()).unwrap(),
+        r#"{"acme/*":"dist","other/*":"source","*":"auto"}"#
+    );
+
+    let global = ConfigSource::Global(std::path::Path::new("auth.json").into());
+    let project = ConfigSource::Project(std::path::Path::new("auth.json").into());
+    raw.merge(
+        json!({ "http-basic": { "a.example.com": "a", "b.example.com": "b" } })
+            .as_object()
+            .unwrap()
+            .clone(),
+        global.clone(),
+    );
+    raw.merge(
+        json!({ "http-basic": { "b.example.com": "c" } })
+            .as_object()
+            .unwrap()
+            .clone(),
+        project.clone(),
+    );
+
+    assert_eq!(raw.source_of("http-basic.a.example.com"), Some(&global));
+    assert_eq!(raw.source_of("http-basic.b.example.com"), Some(&project));
+    let entries = raw.entries().unwrap();
+    let source = |key: &str| entries.iter().find(|entry| entry.0 == key).unwrap().2;
+    assert_eq!(source("http-basic.a.example.com"), &global);
+    assert_eq!(source("preferred-install.other/*"), &ConfigSource::Default);
+}
+
+#[test]
+fn test_placeholders_are_expanded() {
+    let mut raw = RawConfig {
+        values: Map::new(),
+        sources: HashMap::new(),
+    };
+
+    raw.merge(
+        json!({ "vendor-dir": "lib", "bin-dir": "{$vendor-dir}/bin", "loop": "{$loop}" })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ConfigSource::Default,
+    );
+
+    assert_eq!(raw.get_expanded("bin-dir").unwrap(), Some("lib/bin".into()));
+    assert!(raw.get_expanded("loop").is_err());
+}
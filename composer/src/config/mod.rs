@@ -0,0 +1,626 @@
+mod layers;
+mod resolve;
+
+use crate::composer_json::{ComposerJson, ComposerJsonError};
+use crate::config::layers::RawConfig;
+use crate::dirs::{Directories, DirectoryError};
+use crate::secret::Secret;
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::Error;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug)]
+pub struct Config {
+    pub allow_plugins: PackageSetting<bool>,
+    pub allow_superuser: bool,
+    pub apcu_autoloader: bool,
+    pub archive_dir: Box<Path>,
+    pub archive_format: String,
+    pub audit: AuditConfig,
+    pub autoloader_suffix: Option<String>,
+    pub bearer: Option<HashMap<String, Secret>>,
+    pub bin_compat: BinaryCompatibility,
+    pub bin_dir: Box<Path>,
+    pub bitbucket_oauth: Option<HashMap<String, BitbucketToken>>,
+    pub cache_dir: Box<Path>,
+    pub cache_files_dir: Box<Path>,
+    /// The max cache files in MiB
+    pub cache_files_maxsize: u32,
+    pub cache_files_ttl: Duration,
+    pub cache_read_only: bool,
+    pub cache_repo_dir: Box<Path>,
+    pub cache_vcs_dir: Box<Path>,
+    pub cafile: Option<Box<Path>>,
+    pub capath: Option<Box<Path>>,
+    pub classmap_authoritative: bool,
+    pub composer_home: Box<Path>,
+    pub composer_json: Box<Path>,
+    pub composer_lock: Box<Path>,
+    pub data_dir: Box<Path>,
+    pub disable_tls: bool,
+    pub discard_changes: DiscardChanges,
+    pub github_domains: Vec<String>,
+    pub github_expose_hostname: bool,
+    pub github_oauth: Option<HashMap<String, Secret>>,
+    pub github_protocols: Vec<String>,
+    pub gitlab_domains: Vec<String>,
+    pub gitlab_oauth: Option<HashMap<String, Secret>>,
+    pub gitlab_protocol: Option<String>,
+    pub gitlab_tokens: Option<HashMap<String, GitlabToken>>,
+    pub htaccess_protect: bool,
+    pub http_basic: Option<HashMap<String, HttpBasicToken>>,
+    /// Whether dists are extracted into `vendor-dir` or into the store, see
+    /// [`Store`](crate::installer::Store)
+    pub install_strategy: InstallStrategy,
+    pub lock: bool,
+    pub notify_on_install: bool,
+    pub optimize_autoloader: bool,
+    pub platform: Option<HashMap<String, String>>,
+    pub platform_check: PlatformCheck,
+    pub preferred_install: PackageSetting<PreferredInstallMethod>,
+    pub prepend_autoloader: bool,
+    pub process_timeout: u32,
+    pub secure_http: bool,
+    pub secure_svn_domains: Vec<String>,
+    pub sort_packages: bool,
+    pub store_auths: Confirmation,
+    /// A command that provides credentials instead of storing them in auth.json, see
+    /// [`CredentialHelper`](crate::auth::credential_helper::CredentialHelper)
+    pub credential_helper: Option<String>,
+    pub use_github_api: bool,
+    pub use_include_path: bool,
+    pub use_parent_dir: Confirmation,
+    pub vendor_dir: Box<Path>,
+    raw: RawConfig,
+}
+
+impl Config {
+    pub fn build() -> Result<Self, ConfigError> {
+        Config::build_with_overrides(Vec::new())
+    }
+
+    /// Builds the config with values set by command line flags, which take precedence over all
+    /// other sources
+    pub fn build_with_overrides(overrides: Vec<CommandLineOverride>) -> Result<Self, ConfigError> {
+        let cwd = std::env::current_dir()?;
+        let env = |name: &str| std::env::var(name).ok();
+        let dirs = Directories::resolve(&env)?;
+
+        Config::load(&cwd, &dirs, &env, overrides)
+    }
+
+    /// Loads the config in Composer's order of precedence: the built-in defaults, then
+    /// `$COMPOSER_HOME/config.json` and `auth.json`, then `COMPOSER_AUTH`, then the `config`
+    /// section of the project's composer.json and the `auth.json` next to it, then the other
+    /// `COMPOSER_*` environment variables and finally the command line flags
+    pub(crate) fn load(
+        cwd: &Path,
+        dirs: &Directories,
+        env: &dyn Fn(&str) -> Option<String>,
+        overrides: Vec<CommandLineOverride>,
+    ) -> Result<Self, ConfigError> {
+        let (composer_json, composer_lock) = determine_composer_file_paths(cwd, env);
+        let global_config = dirs.home_dir.join("config.json");
+        let global_auth = dirs.home_dir.join("auth.json");
+        let project_auth = composer_json
+            .parent()
+            .unwrap_or(cwd)
+            .join("auth.json")
+            .into_boxed_path();
+
+        let mut raw = RawConfig::with_defaults(dirs);
+
+        if env("COMPOSER_HOME").is_some_and(|home| !home.is_empty()) {
+            let mut config = Map::new();
+
+            for key in ["home", "data-dir", "cache-dir"] {
+                config.insert(key.into(), raw.get(key).cloned().unwrap_or_default());
+            }
+
+            raw.merge(config, ConfigSource::Environment("COMPOSER_HOME".into()));
+        }
+
+        raw.merge(
+            read_config_section(&global_config)?,
+            ConfigSource::Global(global_config.into_boxed_path()),
+        );
+        raw.merge(
+            read_auth_file(&global_auth)?,
+            ConfigSource::Global(global_auth.into_boxed_path()),
+        );
+
+        if let Some(auth) = env("COMPOSER_AUTH") {
+            let auth = parse_auth(&auth)
+                .map_err(|error| ConfigError::InvalidAuth("COMPOSER_AUTH".into(), error))?;
+
+            raw.merge(auth, ConfigSource::Environment("COMPOSER_AUTH".into()));
+        }
+
+        raw.merge(
+            read_config_section(&composer_json)?,
+            ConfigSource::Project(composer_json.clone()),
+        );
+        raw.merge(
+            read_auth_file(&project_auth)?,
+            ConfigSource::Project(project_auth),
+        );
+        raw.merge_env(env)?;
+
+        for CommandLineOverride { key, value, flag } in overrides {
+            let mut config = Map::new();
+            config.insert(key, value);
+
+            raw.merge(config, ConfigSource::CommandLine(flag));
+        }
+
+        Config::from_raw(raw, cwd, composer_json, composer_lock, env)
+    }
+
+    /// Where the value of a config key, such as `vendor-dir` or `http-basic.example.com`, was last
+    /// set
+    pub fn source_of(&self, key: &str) -> Option<&ConfigSource> {
+        self.raw.source_of(key)
+    }
+
+    /// All config values as strings, along with where they were set
+    pub fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        Ok(self
+            .raw
+            .entries()?
+            .into_iter()
+            .map(|(key, value, source)| ConfigEntry {
+                key,
+                value,
+                source: source.clone(),
+            })
+            .collect())
+    }
+}
+
+/// A config value set by a command line flag, such as `--prefer-source`
+#[derive(Debug, Clone)]
+pub struct CommandLineOverride {
+    pub key: String,
+    pub value: Value,
+    pub flag: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+/// The origin of a config value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    /// `$COMPOSER_HOME/config.json` or `$COMPOSER_HOME/auth.json`
+    Global(Box<Path>),
+    /// The `config` section of the project's composer.json, or the `auth.json` next to it
+    Project(Box<Path>),
+    /// A `COMPOSER_*` environment variable
+    Environment(String),
+    /// A command line flag
+    CommandLine(String),
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "defaults"),
+            ConfigSource::Global(path) | ConfigSource::Project(path) => {
+                write!(f, "{}", path.display())
+            }
+            ConfigSource::Environment(name) => write!(f, "${name}"),
+            ConfigSource::CommandLine(flag) => write!(f, "{flag}"),
+        }
+    }
+}
+
+/// The config keys that hold credentials, which can also be set in auth.json and `COMPOSER_AUTH`
+const AUTH_KEYS: [&str; 6] = [
+    "bearer",
+    "bitbucket-oauth",
+    "github-oauth",
+    "gitlab-oauth",
+    "gitlab-token",
+    "http-basic",
+];
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not determine config directory for {0}")]
+    CannotDetermineConfigDirectory(String),
+
+    #[error("Could not open file or directory {0}")]
+    IoError(std::io::Error),
+
+    #[error("Could not read config from {0}: {1}")]
+    InvalidFile(String, ComposerJsonError),
+
+    #[error("Could not read auth config from {0}: {1}")]
+    InvalidAuth(String, serde_json::Error),
+
+    #[error("Invalid value for config key {0}, expected {1}")]
+    InvalidValue(String, String),
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(value: Error) -> Self {
+        ConfigError::IoError(value)
+    }
+}
+
+impl From<DirectoryError> for ConfigError {
+    fn from(value: DirectoryError) -> Self {
+        ConfigError::CannotDetermineConfigDirectory(match value {
+            DirectoryError::CannotDetermineDirectory(dir) => dir,
+        })
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, Default)]
+pub enum PreferredInstallMethod {
+    #[default]
+    Dist,
+    Source,
+    Auto,
+}
+
+/// A setting for all packages, or per package name pattern in the order they are configured
+#[derive(Debug)]
+pub enum PackageSetting<T> {
+    Selective(IndexMap<String, T>),
+    Global(T),
+}
+
+impl<T> Default for PackageSetting<T> {
+    fn default() -> Self {
+        Self::Selective(IndexMap::new())
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditConfig {
+    pub ignore: Option<AuditIgnore>,
+    pub abandoned: AuditAbandoned,
+}
+
+#[derive(Debug)]
+pub enum AuditIgnore {
+    Simple(Vec<String>),
+    Detailed(HashMap<String, String>),
+}
+
+#[derive(Debug, Default)]
+pub enum AuditAbandoned {
+    #[default]
+    Fail,
+    Ignore,
+    Report,
+}
+
+#[derive(Debug, Default)]
+pub enum Confirmation {
+    #[default]
+    Prompt,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum GitlabToken {
+    Token(Secret),
+    TokenWithUsername(GitlabTokenWithUsername),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitlabTokenWithUsername {
+    pub username: String,
+    pub token: Secret,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BitbucketToken {
+    pub consumer_key: String,
+    pub consumer_secret: Secret,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpBasicToken {
+    pub username: String,
+    pub password: Secret,
+}
+
+#[derive(Debug, Default)]
+pub enum BinaryCompatibility {
+    #[default]
+    Auto,
+    Proxy,
+    Full,
+}
+
+#[derive(Debug, Default)]
+pub enum DiscardChanges {
+    #[default]
+    Never,
+    Always,
+    Stash,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum InstallStrategy {
+    /// Extracts dists into `vendor-dir`
+    #[default]
+    Vendor,
+    /// Extracts dists into the store in `data-dir`, and symlinks them into `vendor-dir`
+    Store,
+}
+
+#[derive(Debug, Default)]
+pub enum PlatformCheck {
+    #[default]
+    PhpOnly,
+    All,
+    None,
+}
+
+/// Reads the credentials of an auth.json, if the file exists
+fn read_auth_file(path: &Path) -> Result<Map<String, Value>, ConfigError> {
+    if !path.is_file() {
+        return Ok(Map::new());
+    }
+
+    let data = std::fs::read_to_string(path)?;
+
+    parse_auth(&data).map_err(|error| ConfigError::InvalidAuth(path.display().to_string(), error))
+}
+
+/// Parses the contents of an auth.json or `COMPOSER_AUTH`, keeping only the credential keys
+fn parse_auth(data: &str) -> Result<Map<String, Value>, serde_json::Error> {
+    let mut auth: Map<String, Value> = serde_json::from_str(data)?;
+    auth.retain(|key, _| AUTH_KEYS.contains(&key.as_str()));
+
+    Ok(auth)
+}
+
+/// Reads the `config` section of a composer.json or config.json, if the file exists
+fn read_config_section(path: &Path) -> Result<Map<String, Value>, ConfigError> {
+    if !path.is_file() {
+        return Ok(Map::new());
+    }
+
+    let file = File::open(path)?;
+    let composer_json: ComposerJson = file
+        .try_into()
+        .map_err(|error| ConfigError::InvalidFile(path.display().to_string(), error))?;
+
+    Ok(composer_json.config.unwrap_or_default())
+}
+
+fn determine_composer_file_paths(
+    cwd: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> (Box<Path>, Box<Path>) {
+    let composer_json = env("COMPOSER").unwrap_or_else(|| "composer.json".into());
+    let composer_lock = match composer_json.strip_suffix(".json") {
+        Some(stem) => format!("{stem}.lock"),
+        None => format!("{composer_json}.lock"),
+    };
+
+    (
+        cwd.join(composer_json).into_boxed_path(),
+        cwd.join(composer_lock).into_boxed_path(),
+    )
+}
+
+/// Writes the composer.json of a test project and loads its config, with the Composer home in
+/// `home_dir` and no environment variables
+#[cfg(test)]
+pub(crate) fn test_config(project_dir: &Path, home_dir: &Path, composer_json: &str) -> Config {
+    std::fs::write(project_dir.join("composer.json"), composer_json).unwrap();
+
+    let dirs = Directories {
+        data_dir: home_dir.into(),
+        archive_dir: project_dir.into(),
+        cache_dir: home_dir.join("cache").into_boxed_path(),
+        home_dir: home_dir.into(),
+    };
+
+    Config::load(project_dir, &dirs, &|_| None, Vec::new()).unwrap()
+}
+
+#[test]
+fn test_config_layers() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+
+    std::fs::write(
+        home.path().join("config.json"),
+        r#"{"config": {"vendor-dir": "global-vendor", "process-timeout": 100, "sort-packages": true}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        project.path().join("composer.json"),
+        r#"{"config": {"vendor-dir": "lib/vendor", "preferred-install": {"acme/*": "source"}}}"#,
+    )
+    .unwrap();
+
+    let dirs = Directories {
+        data_dir: home.path().into(),
+        archive_dir: project.path().into(),
+        cache_dir: home.path().join("cache").into_boxed_path(),
+        home_dir: home.path().into(),
+    };
+    let env = |name: &str| match name {
+        "COMPOSER_PROCESS_TIMEOUT" => Some("50".to_string()),
+        "COMPOSER_CACHE_DIR" => Some("/tmp/composer-cache".to_string()),
+        _ => None,
+    };
+
+    let overrides = vec![CommandLineOverride {
+        key: "optimize-autoloader".into(),
+        value: Value::Bool(true),
+        flag: "--optimize-autoloader".into(),
+    }];
+
+    let config = Config::load(project.path(), &dirs, &env, overrides).unwrap();
+
+    assert_eq!(&*config.vendor_dir, project.path().join("lib/vendor"));
+    assert_eq!(&*config.bin_dir, project.path().join("lib/vendor/bin"));
+    assert_eq!(config.process_timeout, 50);
+    assert!(config.sort_packages);
+    assert_eq!(
+        &*config.cache_files_dir,
+        Path::new("/tmp/composer-cache/files")
+    );
+    assert_eq!(config.cache_files_maxsize, 300);
+    assert!(config.optimize_autoloader);
+    assert_eq!(
+        config.source_of("vendor-dir"),
+        Some(&ConfigSource::Project(
+            project.path().join("composer.json").into_boxed_path()
+        ))
+    );
+    assert_eq!(
+        config.source_of("sort-packages"),
+        Some(&ConfigSource::Global(
+            home.path().join("config.json").into_boxed_path()
+        ))
+    );
+    assert_eq!(
+        config.source_of("process-timeout"),
+        Some(&ConfigSource::Environment(
+            "COMPOSER_PROCESS_TIMEOUT".into()
+        ))
+    );
+    assert_eq!(
+        config.source_of("optimize-autoloader"),
+        Some(&ConfigSource::CommandLine("--optimize-autoloader".into()))
+    );
+    assert_eq!(config.source_of("lock"), Some(&ConfigSource::Default));
+    assert!(matches!(
+        config.preferred_install,
+        PackageSetting::Selective(patterns) if matches!(patterns.get("acme/*"), Some(PreferredInstallMethod::Source))
+    ));
+
+    let overrides = vec![CommandLineOverride {
+        key: "preferred-install".into(),
+        value: Value::from("dist"),
+        flag: "--prefer-dist".into(),
+    }];
+
+    let config = Config::load(project.path(), &dirs, &env, overrides).unwrap();
+
+    assert!(matches!(
+        config.preferred_install,
+        PackageSetting::Global(PreferredInstallMethod::Dist)
+    ));
+    assert_eq!(
+        config.source_of("preferred-install"),
+        Some(&ConfigSource::CommandLine("--prefer-dist".into()))
+    );
+}
+
+#[test]
+fn test_auth_layers() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+
+    std::fs::write(
+        home.path().join("auth.json"),
+        r#"{"github-oauth": {"github.com": "global", "git.example.com": "enterprise"}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        project.path().join("auth.json"),
+        r#"{
+            "github-oauth": {"github.com": "project"},
+            "http-basic": {"repo.example.com": {"username": "user", "password": "hunter2"}},
+            "bitbucket-oauth": {"bitbucket.org": {"consumer-key": "key", "consumer-secret": "bitbucket-s3cr3t"}}
+        }"#,
+    )
+    .unwrap();
+
+    let dirs = Directories {
+        data_dir: home.path().into(),
+        archive_dir: project.path().into(),
+        cache_dir: home.path().join("cache").into_boxed_path(),
+        home_dir: home.path().into(),
+    };
+    let env = |name: &str| {
+        match name {
+        "COMPOSER_AUTH" => Some(
+            r#"{"github-oauth": {"github.com": "env"}, "gitlab-token": {"gitlab.com": "plain", "gitlab.example.com": {"username": "user", "token": "token"}}, "bearer": {"api.example.com": "bearer"}}"#
+                .to_string(),
+        ),
+        _ => None,
+    }
+    };
+
+    let config = Config::load(project.path(), &dirs, &env, Vec::new()).unwrap();
+
+    let github_oauth = config.github_oauth.as_ref().unwrap();
+    assert_eq!(github_oauth["github.com"].expose(), "project");
+    assert_eq!(github_oauth["git.example.com"].expose(), "enterprise");
+
+    let http_basic = config.http_basic.as_ref().unwrap();
+    assert_eq!(http_basic["repo.example.com"].username, "user");
+    assert_eq!(http_basic["repo.example.com"].password.expose(), "hunter2");
+
+    let bitbucket_oauth = config.bitbucket_oauth.as_ref().unwrap();
+    assert_eq!(bitbucket_oauth["bitbucket.org"].consumer_key, "key");
+
+    let gitlab_tokens = config.gitlab_tokens.as_ref().unwrap();
+    assert!(
+        matches!(&gitlab_tokens["gitlab.com"], GitlabToken::Token(token) if token.expose() == "plain")
+    );
+    assert!(matches!(
+        &gitlab_tokens["gitlab.example.com"],
+        GitlabToken::TokenWithUsername(token) if token.username == "user" && token.token.expose() == "token"
+    ));
+
+    assert_eq!(
+        config.bearer.as_ref().unwrap()["api.example.com"].expose(),
+        "bearer"
+    );
+    assert!(config.gitlab_oauth.is_none());
+
+    let debug = format!("{config:?}");
+    for secret in ["hunter2", "bitbucket-s3cr3t", "enterprise", "plain"] {
+        assert!(!debug.contains(secret));
+        assert!(config
+            .entries()
+            .unwrap()
+            .iter()
+            .all(|entry| !entry.value.contains(secret)));
+    }
+    assert_eq!(
+        config.source_of("gitlab-token"),
+        Some(&ConfigSource::Environment("COMPOSER_AUTH".into()))
+    );
+    assert_eq!(
+        config.source_of("github-oauth"),
+        Some(&ConfigSource::Project(
+            project.path().join("auth.json").into_boxed_path()
+        ))
+    );
+    assert_eq!(
+        config.source_of("github-oauth.git.example.com"),
+        Some(&ConfigSource::Global(
+            home.path().join("auth.json").into_boxed_path()
+        ))
+    );
+}
@@ -0,0 +1,365 @@
+use crate::config::layers::RawConfig;
+use crate::config::{
+    AuditAbandoned, AuditConfig, AuditIgnore, BinaryCompatibility, Config, ConfigError,
+    Confirmation, DiscardChanges, InstallStrategy, PackageSetting, PlatformCheck,
+    PreferredInstallMethod,
+};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+impl Config {
+    /// Turns the merged raw values into a typed config, expanding placeholders and resolving
+    /// relative directories against `base_dir`
+    pub(crate) fn from_raw(
+        raw: RawConfig,
+        base_dir: &Path,
+        composer_json: Box<Path>,
+        composer_lock: Box<Path>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let values = Values {
+            raw: &raw,
+            base_dir,
+            env,
+        };
+
+        let mut config = Self {
+            allow_plugins: values.package_setting("allow-plugins", |value| value.as_bool())?,
+            allow_superuser: values.bool("allow-superuser")?,
+            apcu_autoloader: values.bool("apcu-autoloader")?,
+            archive_dir: values.dir("archive-dir")?,
+            archive_format: values.string("archive-format")?.unwrap_or_default(),
+            audit: values.audit()?,
+            autoloader_suffix: values.string("autoloader-suffix")?,
+            bearer: values.credentials("bearer")?,
+            bin_compat: match values.string("bin-compat")?.as_deref() {
+                Some("auto") | None => BinaryCompatibility::Auto,
+                Some("proxy") => BinaryCompatibility::Proxy,
+                Some("full") => BinaryCompatibility::Full,
+                Some(_) => return Err(invalid("bin-compat", "one of auto, proxy or full")),
+            },
+            bin_dir: values.dir("bin-dir")?,
+            bitbucket_oauth: values.credentials("bitbucket-oauth")?,
+            cache_dir: values.dir("cache-dir")?,
+            cache_files_dir: values.dir("cache-files-dir")?,
+            cache_files_maxsize: values.size_in_mib("cache-files-maxsize")?,
+            cache_files_ttl: match values.u32("cache-files-ttl")? {
+                Some(ttl) => Duration::from_secs(ttl.into()),
+                None => Duration::from_secs(values.u32("cache-ttl")?.unwrap_or_default().into()),
+            },
+            cache_read_only: values.bool("cache-read-only")?,
+            cache_repo_dir: values.dir("cache-repo-dir")?,
+            cache_vcs_dir: values.dir("cache-vcs-dir")?,
+            cafile: values.path("cafile")?,
+            capath: values.path("capath")?,
+            classmap_authoritative: values.bool("classmap-authoritative")?,
+            composer_home: values.dir("home")?,
+            composer_json,
+            composer_lock,
+            data_dir: values.dir("data-dir")?,
+            disable_tls: values.bool("disable-tls")?,
+            discard_changes: match raw.get("discard-changes") {
+                Some(Value::Bool(false)) | None => DiscardChanges::Never,
+                Some(Value::Bool(true)) => DiscardChanges::Always,
+                Some(Value::String(value)) if value == "stash" => DiscardChanges::Stash,
+                Some(_) => return Err(invalid("discard-changes", "true, false or \"stash\"")),
+            },
+            github_domains: values.string_list("github-domains")?,
+            github_expose_hostname: values.bool("github-expose-hostname")?,
+            github_oauth: values.credentials("github-oauth")?,
+            github_protocols: values.string_list("github-protocols")?,
+            gitlab_domains: values.string_list("gitlab-domains")?,
+            gitlab_oauth: values.credentials("gitlab-oauth")?,
+            gitlab_protocol: values.string("gitlab-protocol")?,
+            gitlab_tokens: values.credentials("gitlab-token")?,
+            htaccess_protect: values.bool("htaccess-protect")?,
+            http_basic: values.credentials("http-basic")?,
+            install_strategy: match raw.get("install-strategy") {
+                Some(Value::String(value)) if value == "store" => InstallStrategy::Store,
+                Some(Value::String(value)) if value == "vendor" => InstallStrategy::Vendor,
+                None => InstallStrategy::default(),
+                Some(_) => return Err(invalid("install-strategy", "\"vendor\" or \"store\"")),
+            },
+            lock: values.bool("lock")?,
+            notify_on_install: values.bool("notify-on-install")?,
+            optimize_autoloader: values.bool("optimize-autoloader")?,
+            platform: values.platform()?,
+            platform_check: match raw.get("platform-check") {
+                Some(Value::String(value)) if value == "php-only" => PlatformCheck::PhpOnly,
+                Some(Value::Bool(true)) => PlatformCheck::All,
+                Some(Value::Bool(false)) => PlatformCheck::None,
+                None => PlatformCheck::default(),
+                Some(_) => return Err(invalid("platform-check", "true, false or \"php-only\"")),
+            },
+            preferred_install: values.package_setting("preferred-install", |value| {
+                PreferredInstallMethod::from_str(value.as_str()?, true).ok()
+            })?,
+            prepend_autoloader: values.bool("prepend-autoloader")?,
+            process_timeout: values.u32("process-timeout")?.unwrap_or_default(),
+            secure_http: values.bool("secure-http")?,
+            secure_svn_domains: values.string_list("secure-svn-domains")?,
+            sort_packages: values.bool("sort-packages")?,
+            store_auths: values.confirmation("store-auths")?,
+            credential_helper: values.string("credential-helper")?,
+            use_github_api: values.bool("use-github-api")?,
+            use_include_path: values.bool("use-include-path")?,
+            use_parent_dir: values.confirmation("use-parent-dir")?,
+            vendor_dir: values.dir("vendor-dir")?,
+            raw,
+        };
+
+        config.raw.redact_credentials();
+
+        Ok(config)
+    }
+}
+
+struct Values<'a> {
+    raw: &'a RawConfig,
+    base_dir: &'a Path,
+    env: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl Values<'_> {
+    fn bool(&self, key: &str) -> Result<bool, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(false),
+            Some(value) => value.as_bool().ok_or_else(|| invalid(key, "a boolean")),
+        }
+    }
+
+    fn u32(&self, key: &str) -> Result<Option<u32>, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_u64()
+                .and_then(|value| u32::try_from(value).ok())
+                .map(Some)
+                .ok_or_else(|| invalid(key, "a positive integer")),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(invalid(key, "a string")),
+        }
+    }
+
+    fn string_list(&self, key: &str) -> Result<Vec<String>, ConfigError> {
+        let Some(value) = self.raw.get(key) else {
+            return Ok(Vec::new());
+        };
+
+        value
+            .as_array()
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|item| item.as_str().map(String::from))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| invalid(key, "a list of strings"))
+    }
+
+    fn confirmation(&self, key: &str) -> Result<Confirmation, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(Confirmation::default()),
+            Some(Value::Bool(true)) => Ok(Confirmation::Always),
+            Some(Value::Bool(false)) => Ok(Confirmation::Never),
+            Some(Value::String(value)) if value == "prompt" => Ok(Confirmation::Prompt),
+            Some(_) => Err(invalid(key, "true, false or \"prompt\"")),
+        }
+    }
+
+    /// A path with placeholders, `~` and a leading environment variable expanded
+    fn path(&self, key: &str) -> Result<Option<Box<Path>>, ConfigError> {
+        let Some(path) = self.raw.get_expanded(key)? else {
+            return Ok(None);
+        };
+
+        let path = path.trim_end_matches(['/', '\\']);
+        let path = expand_path(path, self.env);
+
+        Ok(Some(PathBuf::from(path).into_boxed_path()))
+    }
+
+    /// A directory, which is resolved against the base directory when relative
+    fn dir(&self, key: &str) -> Result<Box<Path>, ConfigError> {
+        let path = self.path(key)?.ok_or_else(|| invalid(key, "a directory"))?;
+
+        if path.is_absolute() || path.to_string_lossy().contains("://") {
+            return Ok(path);
+        }
+
+        Ok(self.base_dir.join(path).into_boxed_path())
+    }
+
+    /// Parses sizes such as `300MiB`, `1G` or a plain number of bytes, rounding down to MiB
+    fn size_in_mib(&self, key: &str) -> Result<u32, ConfigError> {
+        const MIB: f64 = 1024.0 * 1024.0;
+
+        let bytes = match self.raw.get(key) {
+            None => return Ok(0),
+            Some(Value::Number(bytes)) => bytes.as_f64(),
+            Some(Value::String(size)) => {
+                let size = size.trim().to_lowercase();
+                let unit_start = size
+                    .find(|character: char| !character.is_ascii_digit() && character != '.')
+                    .unwrap_or(size.len());
+                let (number, unit) = size.split_at(unit_start);
+                let multiplier = match unit.trim() {
+                    "" | "b" => Some(1.0),
+                    "k" | "kb" | "kib" => Some(1024.0),
+                    "m" | "mb" | "mib" => Some(MIB),
+                    "g" | "gb" | "gib" => Some(1024.0 * MIB),
+                    _ => None,
+                };
+
+                number
+                    .parse::<f64>()
+                    .ok()
+                    .zip(multiplier)
+                    .map(|(number, multiplier)| number * multiplier)
+            }
+            Some(_) => None,
+        };
+
+        bytes
+            .map(|bytes| (bytes / MIB) as u32)
+            .ok_or_else(|| invalid(key, "a size such as 300MiB"))
+    }
+
+    fn package_setting<T>(
+        &self,
+        key: &str,
+        parse: impl Fn(&Value) -> Option<T>,
+    ) -> Result<PackageSetting<T>, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(PackageSetting::default()),
+            Some(Value::Object(patterns)) => patterns
+                .iter()
+                .map(|(pattern, value)| Some((pattern.clone(), parse(value)?)))
+                .collect::<Option<IndexMap<_, _>>>()
+                .map(PackageSetting::Selective)
+                .ok_or_else(|| invalid(key, "a map of package patterns")),
+            Some(value) => parse(value)
+                .map(PackageSetting::Global)
+                .ok_or_else(|| invalid(key, "a valid value or a map of package patterns")),
+        }
+    }
+
+    /// Credentials keyed by host, which are `None` when there are none
+    fn credentials<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<HashMap<String, T>>, ConfigError> {
+        match self.raw.get(key) {
+            None => Ok(None),
+            Some(Value::Object(hosts)) if hosts.is_empty() => Ok(None),
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|_| invalid(key, "a map of credentials keyed by host")),
+        }
+    }
+
+    fn audit(&self) -> Result<AuditConfig, ConfigError> {
+        let empty = Map::new();
+        let audit = match self.raw.get("audit") {
+            None => &empty,
+            Some(Value::Object(audit)) => audit,
+            Some(_) => return Err(invalid("audit", "an object")),
+        };
+
+        let ignore = match audit.get("ignore") {
+            None | Some(Value::Null) => None,
+            Some(Value::Array(ids)) if ids.is_empty() => None,
+            Some(Value::Array(ids)) => ids
+                .iter()
+                .map(|id| id.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+                .map(AuditIgnore::Simple),
+            Some(Value::Object(ids)) => ids
+                .iter()
+                .map(|(id, reason)| Some((id.clone(), reason.as_str()?.to_string())))
+                .collect::<Option<HashMap<_, _>>>()
+                .map(AuditIgnore::Detailed),
+            Some(_) => return Err(invalid("audit.ignore", "a list or map of advisory IDs")),
+        };
+
+        let abandoned = match audit.get("abandoned").and_then(Value::as_str) {
+            None | Some("fail") => AuditAbandoned::Fail,
+            Some("report") => AuditAbandoned::Report,
+            Some("ignore") => AuditAbandoned::Ignore,
+            Some(_) => return Err(invalid("audit.abandoned", "one of ignore, report or fail")),
+        };
+
+        Ok(AuditConfig { ignore, abandoned })
+    }
+
+    fn platform(&self) -> Result<Option<HashMap<String, String>>, ConfigError> {
+        let platform = match self.raw.get("platform") {
+            None => return Ok(None),
+            Some(Value::Object(platform)) if platform.is_empty() => return Ok(None),
+            Some(Value::Object(platform)) => platform,
+            Some(_) => return Err(invalid("platform", "a map of platform packages")),
+        };
+
+        let mut packages = HashMap::new();
+
+        for (name, version) in platform {
+            match version {
+                // `false` disables a platform package that was overridden by another layer
+                Value::Bool(false) => {}
+                Value::String(version) => {
+                    packages.insert(name.clone(), version.clone());
+                }
+                _ => return Err(invalid("platform", "versions or false")),
+            }
+        }
+
+        Ok(Some(packages))
+    }
+}
+
+fn invalid(key: &str, expected: &str) -> ConfigError {
+    ConfigError::InvalidValue(key.into(), expected.into())
+}
+
+/// Expands a leading `~` to the user's home directory and a leading `$VAR` or `%VAR%` to the value
+/// of that environment variable
+fn expand_path(path: &str, env: &dyn Fn(&str) -> Option<String>) -> String {
+    if let Some(rest) = path.strip_prefix('~') {
+        if rest.is_empty() || rest.starts_with(['/', '\\']) {
+            if let Some(home) = env("HOME").or_else(|| env("USERPROFILE")) {
+                return format!("{home}{rest}");
+            }
+        }
+    }
+
+    let variable = if let Some(rest) = path.strip_prefix('$') {
+        let end = rest
+            .find(|character: char| !character.is_alphanumeric() && character != '_')
+            .unwrap_or(rest.len());
+
+        Some((&rest[..end], &rest[end..]))
+    } else if let Some(rest) = path.strip_prefix('%') {
+        rest.split_once('%')
+    } else {
+        None
+    };
+
+    match variable {
+        Some((name, rest)) if !name.is_empty() => match env(name) {
+            Some(value) => format!("{value}{rest}"),
+            None => path.to_string(),
+        },
+        _ => path.to_string(),
+    }
+}
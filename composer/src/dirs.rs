@@ -9,6 +9,24 @@ pub struct Directories {
     pub home_dir: Box<Path>,
 }
 
+impl Directories {
+    /// Determines the directories for the current platform, honoring `COMPOSER_HOME` the way
+    /// Composer does: it becomes both the home and data directory, and holds the cache
+    pub fn resolve(env: &dyn Fn(&str) -> Option<String>) -> Result<Directories, DirectoryError> {
+        let mut dirs = Self::try_get_default_directories()?;
+
+        if let Some(composer_home) = env("COMPOSER_HOME").filter(|home| !home.is_empty()) {
+            let home_dir = PathBuf::from(composer_home);
+
+            dirs.cache_dir = home_dir.join("cache").into_boxed_path();
+            dirs.data_dir = home_dir.clone().into_boxed_path();
+            dirs.home_dir = home_dir.into_boxed_path();
+        }
+
+        Ok(dirs)
+    }
+}
+
 #[cfg(windows)]
 impl Directories {
     fn try_get_default_directories() -> Result<Directories, DirectoryError> {
@@ -20,8 +38,8 @@ impl Directories {
         let cache_dir = PathBuf::from(local_app_data_dir).join("Composer");
         let home_dir = PathBuf::from(roaming_app_data_dir);
         let data_dir = home_dir.join("Composer");
-        let archive_dir =
-            current_dir().map_err(|_| DirectoryError::CannotDetermineDirectory("current".into))?;
+        let archive_dir = current_dir()
+            .map_err(|_| DirectoryError::CannotDetermineDirectory("current".into()))?;
 
         Ok(Self {
             home_dir: home_dir.into_boxed_path(),
@@ -56,8 +74,8 @@ impl Directories {
             |xdg_cache_dir| PathBuf::from(xdg_cache_dir).join("composer"),
         );
 
-        let archive_dir =
-            current_dir().map_err(|_| DirectoryError::CannotDetermineDirectory("current".into))?;
+        let archive_dir = current_dir()
+            .map_err(|_| DirectoryError::CannotDetermineDirectory("current".into()))?;
 
         Ok(Self {
             home_dir: home_dir.into_boxed_path(),
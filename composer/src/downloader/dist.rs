@@ -0,0 +1,353 @@
+use crate::cache::{entry_path, temp_path, Cache};
+use crate::downloader::DownloadError;
+use crate::http::HttpDownloader;
+use crate::lock_file::{LockFile, Package, PackageDist};
+use crate::repository::sha1_file;
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// How many downloads run at once unless `COMPOSER_MAX_PARALLEL_HTTP` says otherwise
+const MAX_PARALLEL_HTTP: usize = 12;
+
+/// Receives the progress of [`DistDownloader::download_all`], which may call it from several
+/// threads at once
+pub trait ProgressReporter: Sync {
+    /// Called once before any download starts, with the number of packages to download
+    fn begin(&self, _total: usize) {}
+
+    fn started(&self, _package: &Package) {}
+
+    /// Called while a dist is written, with the bytes written so far and the size when known
+    fn progress(&self, _package: &Package, _downloaded: u64, _size: Option<u64>) {}
+
+    fn finished(&self, _package: &Package) {}
+
+    /// Called instead of `started` and `finished` when the dist is taken from the cache
+    fn cached(&self, _package: &Package) {}
+
+    /// Called once all downloads are done, or one of them failed
+    fn end(&self) {}
+}
+
+/// A reporter which reports nothing
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {}
+
+/// Downloads the dist archives of packages, several at a time
+pub struct DistDownloader {
+    http: HttpDownloader,
+    max_parallel: usize,
+    cache: Option<Cache>,
+}
+
+impl DistDownloader {
+    /// A downloader running as many downloads at once as `COMPOSER_MAX_PARALLEL_HTTP` allows,
+    /// which defaults to 12 and is capped at 50
+    pub fn new(http: HttpDownloader) -> Self {
+        let max_parallel = max_parallel_http(&|name| std::env::var(name).ok());
+
+        DistDownloader {
+            http,
+            max_parallel,
+            cache: None,
+        }
+    }
+
+    /// Takes dists from the cache when they are there, and stores new ones in it unless it is
+    /// read-only
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_max_parallel(mut self, max_parallel: usize) -> Self {
+        self.max_parallel = max_parallel.max(1);
+        self
+    }
+
+    /// Downloads the dists of all packages of a lock file into `dir`, see
+    /// [`download_all`](Self::download_all)
+    pub fn download_lock_file(
+        &self,
+        lock_file: &LockFile,
+        dev: bool,
+        dir: &Path,
+        reporter: &dyn ProgressReporter,
+    ) -> Result<Vec<(String, PathBuf)>, DownloadError> {
+        let mut packages: Vec<_> = lock_file.packages.iter().collect();
+
+        if dev {
+            packages.extend(&lock_file.packages_dev);
+        }
+
+        self.download_all(&packages, dir, reporter)
+    }
+
+    /// Downloads the dists of packages, and returns the archive of each package by name in the
+    /// order of the packages
+    ///
+    /// Each archive is written to `<package name>/<sha1 of the dist URL>.<dist type>` in the cache,
+    /// or in `dir` when there is no cache or it is read-only. It is downloaded to a temporary file
+    /// next to it, which is only renamed once its `shasum`, when given, matches, and removed when
+    /// the download fails. Packages without dist or with a `path` dist are skipped.
+    /// No download is started after one failed.
+    pub fn download_all(
+        &self,
+        packages: &[&Package],
+        dir: &Path,
+        reporter: &dyn ProgressReporter,
+    ) -> Result<Vec<(String, PathBuf)>, DownloadError> {
+        let jobs: Vec<(&Package, &PackageDist)> = packages
+            .iter()
+            .filter_map(|package| Some((*package, package.dist.as_ref()?)))
+            .filter(|(_, dist)| dist.dist_type != "path")
+            .collect();
+
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results = Mutex::new(Vec::new());
+
+        let _lock = self.cache.as_ref().and_then(Cache::lock);
+        reporter.begin(jobs.len());
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.max_parallel.min(jobs.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+
+                    if index >= jobs.len() || failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let (package, dist) = jobs[index];
+                    let result = self.download(package, dist, dir, reporter);
+
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+
+                    results.lock().expect("results lock").push((index, result));
+                });
+            }
+        });
+
+        reporter.end();
+
+        let mut results = results.into_inner().expect("results lock");
+        results.sort_by_key(|(index, _)| *index);
+
+        results
+            .into_iter()
+            .map(|(index, result)| Ok((jobs[index].0.name.clone(), result?)))
+            .collect()
+    }
+
+    fn download(
+        &self,
+        package: &Package,
+        dist: &PackageDist,
+        dir: &Path,
+        reporter: &dyn ProgressReporter,
+    ) -> Result<PathBuf, DownloadError> {
+        let key = cache_key(package, dist);
+        let checksum_matches = |path: &Path| -> Result<bool, DownloadError> {
+            match dist.shasum.as_deref().filter(|shasum| !shasum.is_empty()) {
+                Some(shasum) => Ok(sha1_file(path)
+                    .map_err(|error| DownloadError::Io(path.display().to_string(), error))?
+                    .eq_ignore_ascii_case(shasum)),
+                None => Ok(true),
+            }
+        };
+
+        let target = match &self.cache {
+            Some(cache) => {
+                let cached = cache.path(&key);
+
+                if cached.is_file() && checksum_matches(&cached)? {
+                    cache.touch(&key);
+                    reporter.cached(package);
+
+                    return Ok(cached);
+                }
+
+                match cache.is_read_only() {
+                    true => dir.join(entry_path(&key)),
+                    false => cached,
+                }
+            }
+            None => dir.join(entry_path(&key)),
+        };
+        let part = temp_path(&target);
+        let io_error = |error| DownloadError::Io(target.display().to_string(), error);
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        reporter.started(package);
+
+        let url = dist_url(&dist.url);
+        let downloaded = self.http.download(&url, &part, &|downloaded, size| {
+            reporter.progress(package, downloaded, size)
+        });
+
+        if let Err(error) = downloaded {
+            let _ = std::fs::remove_file(&part);
+
+            return Err(error.into());
+        }
+
+        if !checksum_matches(&part)? {
+            let _ = std::fs::remove_file(&part);
+
+            return Err(DownloadError::Checksum(dist.url.clone()));
+        }
+
+        std::fs::rename(&part, &target).map_err(io_error)?;
+        reporter.finished(package);
+
+        Ok(target)
+    }
+}
+
+/// The cache key of a dist archive, from the package name and a hash of the dist URL, like
+/// Composer
+///
+/// Keying by the whole URL rather than the version and reference keeps a package of a third party
+/// repository from pre-populating the cache for the package of the same name from another
+/// repository, e.g. packagist.org.
+pub(crate) fn cache_key(package: &Package, dist: &PackageDist) -> String {
+    format!(
+        "{}/{:x}.{}",
+        package.name.to_lowercase(),
+        Sha1::digest(dist.url.as_bytes()),
+        dist.dist_type
+    )
+}
+
+/// Dists of artifact repositories have a local path as URL, relative to the project
+fn dist_url(url: &str) -> String {
+    match url.contains("://") {
+        true => url.to_string(),
+        false => {
+            let path = std::path::absolute(url).unwrap_or_else(|_| PathBuf::from(url));
+
+            format!("file://{}", path.display())
+        }
+    }
+}
+
+fn max_parallel_http(env: &dyn Fn(&str) -> Option<String>) -> usize {
+    env("COMPOSER_MAX_PARALLEL_HTTP")
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map(|value| value.clamp(1, 50))
+        .unwrap_or(MAX_PARALLEL_HTTP)
+}
+
+#[test]
+fn test_download_all() {
+    use crate::config::test_config;
+    use crate::http::test_server;
+
+    let dir = tempfile::tempdir().unwrap();
+    let archives = dir.path().join("archives");
+    std::fs::create_dir(&archives).unwrap();
+
+    let mut packages = Vec::new();
+    for index in 0..5 {
+        let archive = archives.join(format!("lib-{index}.zip"));
+        std::fs::write(&archive, format!("archive {index}")).unwrap();
+
+        let mut package = Package::new(format!("acme/lib-{index}"), "1.0.0");
+        package.dist = Some(PackageDist {
+            dist_type: "zip".into(),
+            url: format!("file://{}", archive.display()),
+            reference: None,
+            shasum: Some(sha1_file(&archive).unwrap()),
+        });
+        packages.push(package);
+    }
+
+    let (url, server) = test_server(vec![(200, vec![], b"streamed".to_vec())]);
+    let mut package = Package::new("acme/remote", "2.0.0");
+    package.dist = Some(PackageDist {
+        dist_type: "tar".into(),
+        url: format!("{url}/remote.tar"),
+        reference: None,
+        shasum: None,
+    });
+    packages.push(package);
+    packages.push(Package::new("acme/metapackage", "1.0.0"));
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl ProgressReporter for Recorder {
+        fn begin(&self, total: usize) {
+            self.0.lock().unwrap().push(format!("begin {total}"));
+        }
+
+        fn finished(&self, package: &Package) {
+            self.0.lock().unwrap().push(package.name.clone());
+        }
+
+        fn cached(&self, package: &Package) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("cached {}", package.name));
+        }
+    }
+
+    let home = tempfile::tempdir().unwrap();
+    let config = test_config(
+        dir.path(),
+        home.path(),
+        r#"{"config": {"secure-http": false}}"#,
+    );
+    let http = HttpDownloader::load(&config, &|_| None).unwrap();
+    let cache = Cache::new(dir.path().join("cache"), false);
+    let downloader = DistDownloader::new(http)
+        .with_max_parallel(3)
+        .with_cache(cache.clone());
+    let target = dir.path().join("downloads");
+    let recorder = Recorder::default();
+
+    let refs: Vec<_> = packages.iter().collect();
+    let downloaded = downloader.download_all(&refs, &target, &recorder).unwrap();
+
+    assert_eq!(downloaded.len(), 6);
+    assert_eq!(downloaded[5].0, "acme/remote");
+    assert_eq!(std::fs::read(&downloaded[5].1).unwrap(), b"streamed");
+    assert_eq!(std::fs::read(&downloaded[2].1).unwrap(), b"archive 2");
+    assert!(downloaded[2].1.starts_with(cache.dir().join("acme/lib-2")));
+    assert_eq!(
+        downloaded[2].1,
+        cache.path(&cache_key(&packages[2], packages[2].dist.as_ref().unwrap()))
+    );
+    assert!(server.join().unwrap()[0].starts_with("GET /remote.tar"));
+
+    // The test server is gone, so the remote dist can only come from the cache
+    assert_eq!(
+        downloader.download_all(&refs, &target, &recorder).unwrap(),
+        downloaded
+    );
+
+    let events = recorder.0.into_inner().unwrap();
+    assert_eq!(events.len(), 14);
+    assert_eq!(events[0], "begin 6");
+    assert_eq!(events[7], "begin 6");
+    assert_eq!(events.iter().filter(|e| e.starts_with("cached")).count(), 6);
+
+    packages[0].dist.as_mut().unwrap().shasum = Some("0".repeat(40));
+    assert!(matches!(
+        downloader.download_all(&[&packages[0]], &target, &NoProgress),
+        Err(DownloadError::Checksum(_))
+    ));
+
+    assert_eq!(max_parallel_http(&|_| Some("100".into())), 50);
+    assert_eq!(max_parallel_http(&|_| None), 12);
+}
@@ -0,0 +1,557 @@
+use crate::cache::temp_path;
+use crate::downloader::DownloadError;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// How many symlinks are followed to resolve one, like the limit of Linux
+const MAX_SYMLINKS: usize = 40;
+
+/// The kinds of archives dists come in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+}
+
+impl ArchiveFormat {
+    /// Detects the format of an archive from its first bytes, or its extension for tar archives
+    /// without `ustar` magic
+    ///
+    /// The extension alone isn't enough, as dists of type `tar` are compressed as often as not.
+    pub fn detect(path: &Path) -> std::io::Result<Option<Self>> {
+        let mut head = Vec::with_capacity(512);
+        File::open(path)?.take(512).read_to_end(&mut head)?;
+
+        let format = match head.as_slice() {
+            [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] => Some(ArchiveFormat::Zip),
+            [0x1f, 0x8b, ..] => Some(ArchiveFormat::TarGz),
+            [b'B', b'Z', b'h', ..] => Some(ArchiveFormat::TarBz2),
+            [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => Some(ArchiveFormat::TarXz),
+            head if head.get(257..262) == Some(b"ustar") => Some(ArchiveFormat::Tar),
+            _ => None,
+        };
+
+        let extension = || {
+            let name = path.file_name()?.to_str()?.to_lowercase();
+
+            match name.rsplit_once('.')?.1 {
+                "zip" => Some(ArchiveFormat::Zip),
+                "tar" => Some(ArchiveFormat::Tar),
+                _ => None,
+            }
+        };
+
+        Ok(format.or_else(extension))
+    }
+}
+
+/// Extracts an archive into `target`, usually the install path of a package in `vendor-dir`
+///
+/// Like Composer, when the archive holds a single directory, its content is extracted instead of
+/// the directory itself. The archive is extracted into a temporary directory next to `target`,
+/// which then replaces `target`, so a failed extraction leaves `target` as it was.
+///
+/// Entries which would be written outside of `target`, through `..` or absolute paths, and
+/// symlinks pointing outside of it fail the extraction. Executable files stay executable.
+pub fn extract(archive: &Path, target: &Path) -> Result<(), DownloadError> {
+    let archive_error =
+        |reason: String| DownloadError::Archive(archive.display().to_string(), reason);
+    let format = ArchiveFormat::detect(archive)
+        .map_err(|error| archive_error(error.to_string()))?
+        .ok_or_else(|| archive_error("unsupported archive format".into()))?;
+
+    let io_error = |error| DownloadError::Io(target.display().to_string(), error);
+    let parent = target.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent).map_err(io_error)?;
+
+    let temp = temp_path(target);
+    std::fs::create_dir(&temp).map_err(io_error)?;
+
+    let mut extraction = Extraction {
+        archive,
+        root: temp.clone(),
+        symlinks: Vec::new(),
+    };
+    let result = extraction
+        .extract(format)
+        .and_then(|_| extraction.create_symlinks())
+        .and_then(|_| replace(&content_dir(&temp), target).map_err(io_error));
+
+    let _ = std::fs::remove_dir_all(&temp);
+
+    result
+}
+
+/// An archive being extracted into a directory
+///
+/// Symlinks are only created once all other entries are written, so no entry can be written
+/// through a symlink.
+struct Extraction<'a> {
+    archive: &'a Path,
+    root: PathBuf,
+    symlinks: Vec<(PathBuf, String)>,
+}
+
+impl Extraction<'_> {
+    fn extract(&mut self, format: ArchiveFormat) -> Result<(), DownloadError> {
+        let file = File::open(self.archive).map_err(|error| self.archive_error(error))?;
+
+        match format {
+            ArchiveFormat::Zip => self.extract_zip(file),
+            ArchiveFormat::Tar => self.extract_tar(BufReader::new(file)),
+            ArchiveFormat::TarGz => self.extract_tar(flate2::read::MultiGzDecoder::new(file)),
+            ArchiveFormat::TarBz2 => {
+                self.extract_tar(bzip2::read::MultiBzDecoder::new(BufReader::new(file)))
+            }
+            ArchiveFormat::TarXz => {
+                let mut tar = Vec::new();
+                lzma_rs::xz_decompress(&mut BufReader::new(file), &mut tar)
+                    .map_err(|error| self.archive_error(error))?;
+
+                self.extract_tar(Cursor::new(tar))
+            }
+        }
+    }
+
+    fn extract_zip(&mut self, file: File) -> Result<(), DownloadError> {
+        let mut zip = zip::ZipArchive::new(file).map_err(|error| self.archive_error(error))?;
+
+        for index in 0..zip.len() {
+            let mut entry = zip
+                .by_index(index)
+                .map_err(|error| self.archive_error(error))?;
+            let Some(path) = self.entry_path(entry.name())? else {
+                continue;
+            };
+
+            if entry.is_dir() {
+                self.create_dir(&path)?;
+            } else if entry.is_symlink() {
+                let mut original = String::new();
+                entry
+                    .read_to_string(&mut original)
+                    .map_err(|error| self.archive_error(error))?;
+                self.add_symlink(path, original)?;
+            } else {
+                let mode = entry.unix_mode();
+                self.write_file(&path, &mut entry, mode)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn extract_tar(&mut self, reader: impl Read) -> Result<(), DownloadError> {
+        let mut tar = tar::Archive::new(reader);
+
+        for entry in tar.entries().map_err(|error| self.archive_error(error))? {
+            let mut entry = entry.map_err(|error| self.archive_error(error))?;
+            let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let Some(path) = self.entry_path(&name)? else {
+                continue;
+            };
+            let link_name = entry
+                .link_name_bytes()
+                .map(|name| String::from_utf8_lossy(&name).into_owned());
+
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => self.create_dir(&path)?,
+                tar::EntryType::Regular | tar::EntryType::Continuous => {
+                    let mode = entry.header().mode().ok();
+                    self.write_file(&path, &mut entry, mode)?;
+                }
+                tar::EntryType::Symlink => {
+                    self.add_symlink(path, link_name.unwrap_or_default())?;
+                }
+                tar::EntryType::Link => {
+                    let original = link_name.unwrap_or_default();
+                    let Some(original) = self.entry_path(&original)? else {
+                        return Err(self.unsafe_path(&name));
+                    };
+                    let target = self.root.join(&path);
+
+                    self.create_dir(path.parent().unwrap_or(Path::new("")))?;
+                    std::fs::copy(self.root.join(original), &target)
+                        .map_err(|error| self.io_error(&target, error))?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The path of an entry relative to the root, or `None` for the root itself
+    fn entry_path(&self, name: &str) -> Result<Option<PathBuf>, DownloadError> {
+        let mut path = PathBuf::new();
+
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(self.unsafe_path(name))
+                }
+            }
+        }
+
+        Ok(Some(path).filter(|path| !path.as_os_str().is_empty()))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), DownloadError> {
+        let dir = self.root.join(path);
+
+        std::fs::create_dir_all(&dir).map_err(|error| self.io_error(&dir, error))
+    }
+
+    fn write_file(
+        &self,
+        path: &Path,
+        reader: &mut dyn Read,
+        mode: Option<u32>,
+    ) -> Result<(), DownloadError> {
+        self.create_dir(path.parent().unwrap_or(Path::new("")))?;
+
+        let target = self.root.join(path);
+        let mut file = File::create(&target).map_err(|error| self.io_error(&target, error))?;
+        std::io::copy(reader, &mut file).map_err(|error| self.archive_error(error))?;
+
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = match mode & 0o111 {
+                0 => 0o644,
+                _ => 0o755,
+            };
+            file.set_permissions(std::fs::Permissions::from_mode(mode))
+                .map_err(|error| self.io_error(&target, error))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        Ok(())
+    }
+
+    /// Records a symlink, which must point to a relative path within the root
+    fn add_symlink(&mut self, path: PathBuf, original: String) -> Result<(), DownloadError> {
+        let mut depth = path.components().count() - 1;
+
+        for component in Path::new(&original).components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir if depth > 0 => depth -= 1,
+                _ => return Err(self.unsafe_path(&path.display().to_string())),
+            }
+        }
+
+        self.symlinks.push((path, original));
+
+        Ok(())
+    }
+
+    /// Creates the recorded symlinks, and checks that none of them resolves outside of the root
+    /// through another one
+    ///
+    /// A symlink whose parent goes through a symlink created before it is rejected before anything
+    /// is created, as its directories could otherwise end up outside of the root.
+    fn create_symlinks(&self) -> Result<(), DownloadError> {
+        for (path, original) in &self.symlinks {
+            let parent = path.parent().unwrap_or(Path::new(""));
+            let mut ancestor = self.root.clone();
+
+            for component in parent.components() {
+                ancestor.push(component);
+
+                if ancestor.is_symlink() {
+                    return Err(self.unsafe_path(&path.display().to_string()));
+                }
+            }
+
+            self.create_dir(parent)?;
+
+            let link = self.root.join(path);
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(original, &link)
+                .map_err(|error| self.io_error(&link, error))?;
+            #[cfg(windows)]
+            let _ = std::os::windows::fs::symlink_file(original, &link);
+        }
+
+        for (path, _) in &self.symlinks {
+            if self.resolve_symlink(path).is_none() {
+                return Err(self.unsafe_path(&path.display().to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The path a recorded symlink points to relative to the root, or `None` when it leaves the
+    /// root or loops
+    ///
+    /// The path is resolved lexically, following the other recorded symlinks, so a symlink whose
+    /// target doesn't exist can't hide that it leaves the root.
+    fn resolve_symlink(&self, path: &Path) -> Option<PathBuf> {
+        let mut resolved = PathBuf::new();
+        let mut pending: Vec<Component> = path.components().rev().collect();
+        let mut followed = 0;
+
+        while let Some(component) = pending.pop() {
+            match component {
+                Component::Normal(name) => {
+                    resolved.push(name);
+
+                    let original = self.symlinks.iter().find(|(link, _)| *link == resolved);
+
+                    if let Some((_, original)) = original {
+                        followed += 1;
+
+                        if followed > MAX_SYMLINKS {
+                            return None;
+                        }
+
+                        resolved.pop();
+                        pending.extend(Path::new(original).components().rev());
+                    }
+                }
+                Component::ParentDir if resolved.pop() => {}
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        Some(resolved)
+    }
+
+    fn archive_error(&self, error: impl ToString) -> DownloadError {
+        DownloadError::Archive(self.archive.display().to_string(), error.to_string())
+    }
+
+    fn unsafe_path(&self, entry: &str) -> DownloadError {
+        DownloadError::UnsafePath(self.archive.display().to_string(), entry.to_string())
+    }
+
+    fn io_error(&self, path: &Path, error: std::io::Error) -> DownloadError {
+        DownloadError::Io(path.display().to_string(), error)
+    }
+}
+
+/// The directory holding the content of an extracted archive, which is its only directory when
+/// there is nothing else but a `.DS_Store` next to it
+fn content_dir(dir: &Path) -> PathBuf {
+    let entries: Vec<_> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name() != ".DS_Store")
+        .collect();
+
+    match entries.as_slice() {
+        [entry] if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) => entry.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// Moves `source` to `target`, putting back what was at `target` when that fails
+pub(crate) fn replace(source: &Path, target: &Path) -> std::io::Result<()> {
+    let backup = match std::fs::symlink_metadata(target) {
+        Ok(_) => {
+            let backup = temp_path(target);
+            std::fs::rename(target, &backup)?;
+
+            Some(backup)
+        }
+        Err(_) => None,
+    };
+
+    if let Err(error) = std::fs::rename(source, target) {
+        if let Some(backup) = &backup {
+            let _ = std::fs::rename(backup, target);
+        }
+
+        return Err(error);
+    }
+
+    match backup {
+        Some(backup) if backup.is_dir() && !backup.is_symlink() => std::fs::remove_dir_all(backup),
+        Some(backup) => std::fs::remove_file(backup),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+fn tar_archive(entries: &[(&str, tar::EntryType, &str, u32)]) -> Vec<u8> {
+    let mut tar = tar::Builder::new(Vec::new());
+
+    for (name, entry_type, data, mode) in entries {
+        let mut header = tar::Header::new_ustar();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(*entry_type);
+        header.set_mode(*mode);
+
+        let data = match entry_type {
+            tar::EntryType::Symlink | tar::EntryType::Link => {
+                header.as_old_mut().linkname[..data.len()].copy_from_slice(data.as_bytes());
+                ""
+            }
+            _ => data,
+        };
+
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        tar.append(&header, data.as_bytes()).unwrap();
+    }
+
+    tar.into_inner().unwrap()
+}
+
+#[test]
+fn test_extract() {
+    use std::io::Write;
+    use tar::EntryType;
+
+    let dir = tempfile::tempdir().unwrap();
+    let vendor = dir.path().join("vendor");
+    let target = vendor.join("acme/lib");
+
+    let zip_path = dir.path().join("lib.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.add_directory("acme-lib-1a2b3c/", options).unwrap();
+    zip.start_file(
+        "acme-lib-1a2b3c/src/Lib.php",
+        options.unix_permissions(0o600),
+    )
+    .unwrap();
+    zip.write_all(b"<?php").unwrap();
+    zip.start_file("acme-lib-1a2b3c/bin/lib", options.unix_permissions(0o700))
+        .unwrap();
+    zip.write_all(b"#!/usr/bin/env php").unwrap();
+    zip.add_symlink("acme-lib-1a2b3c/lib.php", "src/Lib.php", options)
+        .unwrap();
+    zip.finish().unwrap();
+
+    extract(&zip_path, &target).unwrap();
+
+    assert_eq!(std::fs::read(target.join("src/Lib.php")).unwrap(), b"<?php");
+    assert_eq!(std::fs::read(target.join("lib.php")).unwrap(), b"<?php");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path: &str| target.join(path).metadata().unwrap().permissions().mode();
+        assert_eq!(mode("bin/lib") & 0o777, 0o755);
+        assert_eq!(mode("src/Lib.php") & 0o777, 0o644);
+    }
+
+    let tar = tar_archive(&[
+        ("./composer.json", EntryType::Regular, "{}", 0o644),
+        ("./src/", EntryType::Directory, "", 0o755),
+        ("./src/Lib.php", EntryType::Regular, "<?php // 2", 0o644),
+        ("./src/Copy.php", EntryType::Link, "src/Lib.php", 0o644),
+    ]);
+
+    let mut compressed = Vec::new();
+    lzma_rs::xz_compress(&mut tar.as_slice(), &mut compressed).unwrap();
+    let archives = [
+        ("lib.tar", tar.clone()),
+        ("lib.tar.xz", compressed),
+        ("lib.tgz", {
+            let mut gz = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            gz.write_all(&tar).unwrap();
+            gz.finish().unwrap()
+        }),
+        ("lib.tar.bz2", {
+            let mut bz = bzip2::write::BzEncoder::new(Vec::new(), Default::default());
+            bz.write_all(&tar).unwrap();
+            bz.finish().unwrap()
+        }),
+    ];
+
+    for (name, data) in archives {
+        let path = dir.path().join(name);
+        std::fs::write(&path, data).unwrap();
+
+        extract(&path, &target).unwrap();
+
+        assert!(target.join("composer.json").is_file(), "{name}");
+        assert!(!target.join("bin").exists(), "{name}");
+        assert_eq!(
+            std::fs::read(target.join("src/Copy.php")).unwrap(),
+            b"<?php // 2"
+        );
+    }
+
+    assert_eq!(std::fs::read_dir(vendor.join("acme")).unwrap().count(), 1);
+}
+
+#[test]
+fn test_extract_unsafe() {
+    use std::io::Write;
+    use tar::EntryType;
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("vendor/acme/lib");
+
+    let zip_path = dir.path().join("slip.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+    zip.start_file(
+        "lib/../../evil.php",
+        zip::write::SimpleFileOptions::default(),
+    )
+    .unwrap();
+    zip.write_all(b"<?php").unwrap();
+    zip.finish().unwrap();
+
+    let archives = [
+        tar_archive(&[("/tmp/evil.php", EntryType::Regular, "<?php", 0o644)]),
+        tar_archive(&[("lib/link", EntryType::Symlink, "../../etc", 0o777)]),
+        tar_archive(&[
+            ("lib/up", EntryType::Symlink, "..", 0o777),
+            ("lib/a/b", EntryType::Symlink, "../up/..", 0o777),
+        ]),
+        tar_archive(&[
+            ("lib/up", EntryType::Symlink, "..", 0o777),
+            ("lib/a/b", EntryType::Symlink, "../up/../evil", 0o777),
+        ]),
+        tar_archive(&[
+            ("lib/a", EntryType::Symlink, "b", 0o777),
+            ("lib/b", EntryType::Symlink, "a", 0o777),
+        ]),
+        tar_archive(&[("lib/passwd", EntryType::Link, "/etc/passwd", 0o644)]),
+        tar_archive(&[
+            ("d/l1", EntryType::Symlink, "..", 0o777),
+            ("d/l1/l2", EntryType::Symlink, "../..", 0o777),
+            ("d/l1/l2/outside/y", EntryType::Symlink, "z", 0o777),
+        ]),
+    ];
+
+    assert!(matches!(
+        extract(&zip_path, &target),
+        Err(DownloadError::UnsafePath(..))
+    ));
+
+    for (index, data) in archives.into_iter().enumerate() {
+        let path = dir.path().join(format!("{index}.tar"));
+        std::fs::write(&path, data).unwrap();
+
+        assert!(
+            matches!(extract(&path, &target), Err(DownloadError::UnsafePath(..))),
+            "{index}"
+        );
+    }
+
+    assert!(!dir.path().join("evil.php").exists());
+    assert!(!dir.path().join("vendor/outside").exists());
+    assert_eq!(
+        std::fs::read_dir(target.parent().unwrap()).unwrap().count(),
+        0
+    );
+}
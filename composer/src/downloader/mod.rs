@@ -0,0 +1,42 @@
+mod dist;
+mod extract;
+mod path;
+
+pub use dist::{DistDownloader, NoProgress, ProgressReporter};
+pub(crate) use extract::replace;
+pub use extract::{extract, ArchiveFormat};
+pub(crate) use path::{copy_dir, remove, symlink_dir};
+pub use path::{install_path, PathInstall};
+
+use crate::http::HttpError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    #[error("Source path \"{0}\" is not found for package {1}")]
+    SourceNotFound(String, String),
+
+    #[error("Symlink from \"{0}\" to \"{1}\" failed: {2}")]
+    Symlink(String, String, std::io::Error),
+
+    #[error("Could not write to \"{0}\": {1}")]
+    Io(String, std::io::Error),
+
+    #[error("{0}")]
+    Http(HttpError),
+
+    #[error("The checksum verification of the file failed (downloaded from {0})")]
+    Checksum(String),
+
+    #[error("Could not extract \"{0}\": {1}")]
+    Archive(String, String),
+
+    #[error("The archive \"{0}\" contains \"{1}\" which points outside of the package directory")]
+    UnsafePath(String, String),
+}
+
+impl From<HttpError> for DownloadError {
+    fn from(value: HttpError) -> Self {
+        DownloadError::Http(value)
+    }
+}
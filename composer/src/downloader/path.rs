@@ -0,0 +1,164 @@
+use crate::downloader::DownloadError;
+use crate::lock_file::Package;
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+/// How a package from a path repository was installed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathInstall {
+    Symlink,
+    Copy,
+}
+
+/// Installs a package of dist type `path` into `target`, by symlinking or copying `source`
+///
+/// The package's `transport-options` decide how: `symlink: true` only symlinks, `false` only
+/// copies and otherwise a copy is made when symlinking fails. Symlinks are relative unless
+/// `relative` is `false`.
+pub fn install_path(
+    package: &Package,
+    source: &Path,
+    target: &Path,
+) -> Result<PathInstall, DownloadError> {
+    let source = source.canonicalize().map_err(|_| {
+        DownloadError::SourceNotFound(source.display().to_string(), package.name.clone())
+    })?;
+
+    let option = |key: &str| {
+        package
+            .transport_options
+            .as_ref()
+            .and_then(|options| options.get(key))
+            .and_then(Value::as_bool)
+    };
+    let symlink = option("symlink");
+    let relative = option("relative").unwrap_or(true);
+
+    let io_error = |error| DownloadError::Io(target.display().to_string(), error);
+
+    remove(target).map_err(io_error)?;
+
+    let parent = target.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent).map_err(io_error)?;
+
+    if symlink != Some(false) {
+        let link = match relative {
+            true => relative_path(&parent.canonicalize().map_err(io_error)?, &source),
+            false => source.clone(),
+        };
+
+        match symlink_dir(&link, target) {
+            Ok(()) => return Ok(PathInstall::Symlink),
+            Err(error) if symlink == Some(true) => {
+                return Err(DownloadError::Symlink(
+                    source.display().to_string(),
+                    target.display().to_string(),
+                    error,
+                ))
+            }
+            Err(_) => {}
+        }
+    }
+
+    copy_dir(&source, target).map_err(io_error)?;
+
+    Ok(PathInstall::Copy)
+}
+
+/// The shortest path from a directory to another path, both absolute
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+
+    for _ in common..from.len() {
+        path.push("..");
+    }
+
+    path.extend(&to[common..]);
+
+    path
+}
+
+pub(crate) fn remove(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn symlink_dir(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+pub(crate) fn symlink_dir(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(original, link)
+}
+
+/// Copies a directory recursively, keeping symlinks and permissions
+pub(crate) fn copy_dir(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(target)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let destination = target.join(entry.file_name());
+
+        if file_type.is_symlink() {
+            let original = std::fs::read_link(entry.path())?;
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(original, &destination)?;
+            #[cfg(windows)]
+            std::fs::copy(entry.path().join(original), &destination).map(|_| ())?;
+        } else if file_type.is_dir() {
+            copy_dir(&entry.path(), &destination)?;
+        } else {
+            std::fs::copy(entry.path(), &destination)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_install_path() {
+    let project = tempfile::tempdir().unwrap();
+    let source = project.path().join("packages/lib");
+    let vendor = project.path().join("vendor");
+
+    std::fs::create_dir_all(source.join("src")).unwrap();
+    std::fs::write(source.join("src/Lib.php"), "<?php").unwrap();
+
+    let mut package = Package::new("acme/lib", "dev-main");
+    let target = vendor.join("acme/lib");
+
+    assert_eq!(
+        install_path(&package, &source, &target).unwrap(),
+        PathInstall::Symlink
+    );
+    assert_eq!(
+        std::fs::read_link(&target).unwrap(),
+        Path::new("../../packages/lib")
+    );
+    assert!(target.join("src/Lib.php").is_file());
+
+    package.transport_options = serde_json::from_str(r#"{"symlink": false}"#).unwrap();
+
+    assert_eq!(
+        install_path(&package, &source, &target).unwrap(),
+        PathInstall::Copy
+    );
+    assert!(!std::fs::symlink_metadata(&target).unwrap().is_symlink());
+    assert!(target.join("src/Lib.php").is_file());
+
+    assert!(matches!(
+        install_path(&package, &project.path().join("missing"), &target),
+        Err(DownloadError::SourceNotFound(..))
+    ));
+}
@@ -0,0 +1,613 @@
+mod proxy;
+mod tls;
+
+pub use proxy::Proxies;
+
+use crate::auth::credential_helper::CredentialHelperError;
+use crate::auth::{Credentials, HostCredentials};
+use crate::config::Config;
+use crate::secret::{sanitize_urls, Secret};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+const USER_AGENT: &str = concat!(
+    "Composer/2.6.0 (composer-rs ",
+    env!("CARGO_PKG_VERSION"),
+    ")"
+);
+
+/// The result of one attempt at a request, where errors tell whether a retry is worth it
+type Attempt<T> = Result<T, (HttpError, bool)>;
+
+/// How often a request failing with a transient error is retried
+const RETRIES: u32 = 3;
+
+/// The delay before the first retry, doubled for each following retry
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Where `bitbucket-oauth` consumers are exchanged for an access token
+const BITBUCKET_TOKEN_URL: &str = "https://bitbucket.org/site/oauth2/access_token";
+
+/// Fetches files over http(s), or from the local filesystem for `file://` URLs
+///
+/// Requests go through the proxy configured for their scheme unless the host is listed in
+/// `no_proxy`, and carry the credentials configured for their host. Connection failures, `429`
+/// and `5xx` responses are retried with an exponential backoff.
+#[derive(Debug, Clone)]
+pub struct HttpDownloader {
+    direct: ureq::Agent,
+    http_proxy: Option<ureq::Agent>,
+    https_proxy: Option<ureq::Agent>,
+    proxies: Proxies,
+    credentials: Arc<HostCredentials>,
+    /// The Bitbucket access tokens obtained so far, by consumer key
+    bitbucket_tokens: Arc<Mutex<HashMap<String, Secret>>>,
+    secure_http: bool,
+    disable_tls: bool,
+    retries: u32,
+    retry_delay: Duration,
+    offline: bool,
+}
+
+/// The validators of a cached response, sent back so the server only answers with the response
+/// when it changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheValidators {
+    pub last_modified: Option<String>,
+    pub etag: Option<String>,
+}
+
+/// The response to a conditional request
+#[derive(Debug, PartialEq)]
+pub enum Conditional {
+    Modified(Vec<u8>, CacheValidators),
+    /// The cached response is still valid, the server answered `304 Not Modified`
+    NotModified,
+}
+
+impl Default for HttpDownloader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpDownloader {
+    /// A downloader with Composer's default settings, without proxies nor credentials
+    pub fn new() -> Self {
+        HttpDownloader {
+            direct: agent(None, None).expect("agent without proxy"),
+            http_proxy: None,
+            https_proxy: None,
+            proxies: Proxies::default(),
+            credentials: Arc::new(HostCredentials::default()),
+            bitbucket_tokens: Arc::default(),
+            secure_http: true,
+            disable_tls: false,
+            retries: RETRIES,
+            retry_delay: RETRY_DELAY,
+            offline: false,
+        }
+    }
+
+    /// A downloader using the TLS, `secure-http` and auth settings of the config, with the
+    /// proxies of the environment
+    ///
+    /// The network is disabled when `COMPOSER_DISABLE_NETWORK` is set, like in Composer, and only
+    /// cached files can be used.
+    pub fn from_config(config: &Config) -> Result<Self, HttpError> {
+        HttpDownloader::load(config, &|name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn load(
+        config: &Config,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, HttpError> {
+        let tls = tls::tls_config(config.cafile.as_deref(), config.capath.as_deref())?;
+        let proxies = Proxies::from_env(env);
+        let proxy_agent = |proxy: &Option<String>| {
+            proxy
+                .as_deref()
+                .map(|proxy| agent(tls.clone(), Some(proxy)))
+                .transpose()
+        };
+
+        Ok(HttpDownloader {
+            direct: agent(tls.clone(), None)?,
+            http_proxy: proxy_agent(&proxies.http)?,
+            https_proxy: proxy_agent(&proxies.https)?,
+            proxies,
+            credentials: Arc::new(HostCredentials::from_config(config)),
+            bitbucket_tokens: Arc::default(),
+            secure_http: config.secure_http,
+            disable_tls: config.disable_tls,
+            retries: RETRIES,
+            retry_delay: RETRY_DELAY,
+            offline: env("COMPOSER_DISABLE_NETWORK")
+                .is_some_and(|value| !matches!(value.as_str(), "" | "0" | "prime")),
+        })
+    }
+
+    /// Sets how often transient failures are retried, and the delay before the first retry
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Disables the network, so every request fails with [`HttpError::Offline`]
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Downloads the contents of a URL
+    pub fn get(&self, url: &str) -> Result<Vec<u8>, HttpError> {
+        if let Some(path) = file_path(url) {
+            return std::fs::read(&path).map_err(|error| match error.kind() {
+                std::io::ErrorKind::NotFound => HttpError::NotFound(url.to_string()),
+                _ => HttpError::Io(url.to_string(), error),
+            });
+        }
+
+        self.send(url, &[], &|response, url| read_body(response, url))
+    }
+
+    /// Downloads the contents of a URL unless it didn't change since it was cached with the given
+    /// validators, with `If-Modified-Since` and `If-None-Match`
+    pub fn get_if_modified(
+        &self,
+        url: &str,
+        cached: &CacheValidators,
+    ) -> Result<Conditional, HttpError> {
+        if file_path(url).is_some() {
+            return Ok(Conditional::Modified(
+                self.get(url)?,
+                CacheValidators::default(),
+            ));
+        }
+
+        let mut headers = Vec::new();
+        headers.extend(
+            cached
+                .last_modified
+                .as_ref()
+                .map(|value| ("If-Modified-Since", value.clone())),
+        );
+        headers.extend(
+            cached
+                .etag
+                .as_ref()
+                .map(|value| ("If-None-Match", value.clone())),
+        );
+
+        self.send(url, &headers, &|response, url| {
+            if response.status() == 304 {
+                return Ok(Conditional::NotModified);
+            }
+
+            let validators = CacheValidators {
+                last_modified: response.header("Last-Modified").map(str::to_string),
+                etag: response.header("ETag").map(str::to_string),
+            };
+
+            Ok(Conditional::Modified(read_body(response, url)?, validators))
+        })
+    }
+
+    /// Streams the contents of a URL to a file, calling `progress` with the number of bytes
+    /// written so far and the size of the file when it is known
+    pub fn download(
+        &self,
+        url: &str,
+        target: &Path,
+        progress: &dyn Fn(u64, Option<u64>),
+    ) -> Result<u64, HttpError> {
+        let write_error = |error| HttpError::Write(target.display().to_string(), error);
+
+        if let Some(path) = file_path(url) {
+            let size = std::fs::copy(&path, target).map_err(|error| match error.kind() {
+                std::io::ErrorKind::NotFound if !path.exists() => {
+                    HttpError::NotFound(url.to_string())
+                }
+                _ => write_error(error),
+            })?;
+            progress(size, Some(size));
+
+            return Ok(size);
+        }
+
+        self.send(url, &[], &|response, url| {
+            let size = response
+                .header("Content-Length")
+                .and_then(|length| length.parse().ok());
+            let mut reader = response.into_reader();
+            let mut file = File::create(target).map_err(|error| (write_error(error), false))?;
+            let mut buffer = vec![0; 64 * 1024];
+            let mut written = 0;
+
+            loop {
+                let read = reader
+                    .read(&mut buffer)
+                    .map_err(|error| (HttpError::Io(url.to_string(), error), true))?;
+
+                if read == 0 {
+                    return Ok(written);
+                }
+
+                file.write_all(&buffer[..read])
+                    .map_err(|error| (write_error(error), false))?;
+                written += read as u64;
+                progress(written, size);
+            }
+        })
+    }
+
+    /// Sends a GET request and reads the response, retrying transient failures
+    fn send<T>(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        read: &dyn Fn(ureq::Response, &str) -> Attempt<T>,
+    ) -> Result<T, HttpError> {
+        let display_url = sanitize_urls(url);
+
+        if self.offline {
+            return Err(HttpError::Offline(display_url));
+        }
+
+        let mut parsed = Url::parse(url).map_err(|_| HttpError::InvalidUrl(display_url.clone()))?;
+
+        if self.disable_tls && parsed.scheme() == "https" {
+            // Switching between the special schemes http and https can't fail
+            let _ = parsed.set_scheme("http");
+        } else if self.secure_http && parsed.scheme() == "http" {
+            return Err(HttpError::Insecure(display_url));
+        }
+
+        let agent = match self.proxies.for_url(&parsed) {
+            Some(_) if parsed.scheme() == "https" => self.https_proxy.as_ref(),
+            Some(_) => self.http_proxy.as_ref(),
+            None => None,
+        }
+        .unwrap_or(&self.direct);
+
+        let mut request = agent.request_url("GET", &parsed);
+
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+
+        // Credentials in the URL itself are sent by ureq
+        if parsed.username().is_empty() {
+            let origin = match (parsed.host_str(), parsed.port()) {
+                (Some(host), Some(port)) => format!("{host}:{port}"),
+                (Some(host), None) => host.to_string(),
+                (None, _) => String::new(),
+            };
+            let credentials = self
+                .credentials
+                .get(parsed.scheme(), &origin)
+                .map_err(|error| HttpError::Credentials(display_url.clone(), error))?;
+
+            if let Some(credentials) = credentials {
+                let credentials = match credentials {
+                    Credentials::BitbucketOAuth {
+                        consumer_key,
+                        consumer_secret,
+                    } if matches!(origin.as_str(), "bitbucket.org" | "api.bitbucket.org") => {
+                        Credentials::Bearer(self.bitbucket_token(
+                            agent,
+                            &consumer_key,
+                            &consumer_secret,
+                        )?)
+                    }
+                    credentials => credentials,
+                };
+                let (name, value) = credentials.header();
+                request = request.set(name, &value);
+            }
+        }
+
+        let mut attempt = 0;
+
+        loop {
+            let result = call(request.clone(), &display_url)
+                .and_then(|response| read(response, &display_url));
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err((_, true)) if attempt < self.retries => {
+                    std::thread::sleep(self.retry_delay * 2u32.pow(attempt));
+                    attempt += 1;
+                }
+                Err((error, _)) => return Err(error),
+            }
+        }
+    }
+}
+
+impl HttpDownloader {
+    /// Exchanges a Bitbucket OAuth consumer for an access token, which is kept for the following
+    /// requests
+    fn bitbucket_token(
+        &self,
+        agent: &ureq::Agent,
+        consumer_key: &str,
+        consumer_secret: &Secret,
+    ) -> Result<Secret, HttpError> {
+        #[derive(Deserialize)]
+        struct AccessToken {
+            access_token: Secret,
+        }
+
+        let mut tokens = self.bitbucket_tokens.lock().expect("tokens lock");
+
+        if let Some(token) = tokens.get(consumer_key) {
+            return Ok(token.clone());
+        }
+
+        let (name, value) = Credentials::BitbucketOAuth {
+            consumer_key: consumer_key.to_string(),
+            consumer_secret: consumer_secret.clone(),
+        }
+        .header();
+        let url = BITBUCKET_TOKEN_URL.to_string();
+        let response = agent
+            .post(BITBUCKET_TOKEN_URL)
+            .set(name, &value)
+            .send_form(&[("grant_type", "client_credentials")])
+            .map_err(|error| match error {
+                ureq::Error::Status(status @ (400 | 401 | 403), _) => {
+                    HttpError::Unauthorized(url.clone(), status)
+                }
+                ureq::Error::Status(status, _) => HttpError::Status(url.clone(), status),
+                ureq::Error::Transport(transport) => {
+                    HttpError::Transport(url.clone(), sanitize_urls(&transport.to_string()))
+                }
+            })?;
+        let body = response
+            .into_string()
+            .map_err(|error| HttpError::Io(url.clone(), error))?;
+        let token: AccessToken = serde_json::from_str(&body).map_err(|error| {
+            HttpError::Transport(url.clone(), format!("invalid access token: {error}"))
+        })?;
+
+        tokens.insert(consumer_key.to_string(), token.access_token.clone());
+
+        Ok(token.access_token)
+    }
+}
+
+fn agent(
+    tls: Option<Arc<ureq::rustls::ClientConfig>>,
+    proxy: Option<&str>,
+) -> Result<ureq::Agent, HttpError> {
+    let mut builder = ureq::AgentBuilder::new().user_agent(USER_AGENT);
+
+    if let Some(tls) = tls {
+        builder = builder.tls_config(tls);
+    }
+
+    if let Some(proxy) = proxy {
+        let proxy = ureq::Proxy::new(proxy)
+            .map_err(|error| HttpError::InvalidProxy(sanitize_urls(proxy), error.to_string()))?;
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build())
+}
+
+/// Sends a request
+fn call(request: ureq::Request, url: &str) -> Attempt<ureq::Response> {
+    request.call().map_err(|error| match error {
+        ureq::Error::Status(404 | 410, _) => (HttpError::NotFound(url.to_string()), false),
+        ureq::Error::Status(status @ (401 | 403), _) => {
+            (HttpError::Unauthorized(url.to_string(), status), false)
+        }
+        ureq::Error::Status(status, _) => (
+            HttpError::Status(url.to_string(), status),
+            status == 429 || status >= 500,
+        ),
+        ureq::Error::Transport(transport) => {
+            let transient = matches!(
+                transport.kind(),
+                ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io
+            );
+
+            (
+                HttpError::Transport(url.to_string(), sanitize_urls(&transport.to_string())),
+                transient,
+            )
+        }
+    })
+}
+
+fn read_body(response: ureq::Response, url: &str) -> Attempt<Vec<u8>> {
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|error| (HttpError::Io(url.to_string(), error), true))?;
+
+    Ok(body)
+}
+
+/// The local path of a `file://` URL
+pub(crate) fn file_path(url: &str) -> Option<PathBuf> {
+    url.strip_prefix("file://").map(PathBuf::from)
+}
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("The \"{0}\" file could not be downloaded (HTTP/1.1 404 Not Found)")]
+    NotFound(String),
+
+    #[error("The \"{0}\" file could not be downloaded (HTTP/1.1 {1})")]
+    Status(String, u16),
+
+    #[error("The \"{0}\" URL required authentication (HTTP/1.1 {1}), add credentials for its host to auth.json")]
+    Unauthorized(String, u16),
+
+    #[error("The \"{0}\" file could not be downloaded: {1}")]
+    Transport(String, String),
+
+    #[error("The \"{0}\" file could not be read: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Could not write to \"{0}\": {1}")]
+    Write(String, std::io::Error),
+
+    #[error("Your configuration does not allow connections to {0}. See https://getcomposer.org/doc/06-config.md#secure-http for details.")]
+    Insecure(String),
+
+    #[error("Network disabled, request canceled: {0}")]
+    Offline(String),
+
+    #[error("Invalid URL {0}")]
+    InvalidUrl(String),
+
+    #[error("Invalid proxy {0}: {1}")]
+    InvalidProxy(String, String),
+
+    #[error("Could not load the certificates of {0}: {1}")]
+    Tls(String, String),
+
+    #[error("Could not get the credentials for {0}: {1}")]
+    Credentials(String, CredentialHelperError),
+}
+
+/// A status, headers and body served by [`test_server`]
+#[cfg(test)]
+pub(crate) type TestResponse = (u16, Vec<(&'static str, String)>, Vec<u8>);
+
+/// Serves one canned response per connection on a local port, and returns the heads of the
+/// requests it received once all responses are sent
+#[cfg(test)]
+pub(crate) fn test_server(
+    responses: Vec<TestResponse>,
+) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+
+        for (status, headers, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+
+            while reader.read_line(&mut head).unwrap() > 2 {}
+            requests.push(head);
+
+            let mut response = format!(
+                "HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n",
+                body.len()
+            );
+
+            for (name, value) in headers {
+                response.push_str(&format!("{name}: {value}\r\n"));
+            }
+
+            response.push_str("\r\n");
+            stream.write_all(response.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        }
+
+        requests
+    });
+
+    (url, server)
+}
+
+#[test]
+fn test_get_file_url() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("packages.json"), "{}").unwrap();
+
+    let downloader = HttpDownloader::new();
+    let url = format!("file://{}", dir.path().display());
+
+    assert_eq!(
+        downloader.get(&format!("{url}/packages.json")).unwrap(),
+        b"{}"
+    );
+    assert!(matches!(
+        downloader.get(&format!("{url}/missing.json")),
+        Err(HttpError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_get_http_url() {
+    use crate::config::test_config;
+
+    let (url, server) = test_server(vec![
+        (503, vec![], Vec::new()),
+        (200, vec![], b"{}".to_vec()),
+        (200, vec![], b"proxied".to_vec()),
+        (500, vec![], Vec::new()),
+        (500, vec![], Vec::new()),
+    ]);
+    let origin = url.trim_start_matches("http://");
+
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let composer_json = format!(
+        r#"{{"config": {{
+            "secure-http": false,
+            "http-basic": {{"{origin}": {{"username": "user", "password": "hunter2"}}}},
+            "bearer": {{"packages.example.com": "token"}}
+        }}}}"#
+    );
+    let config = test_config(project.path(), home.path(), &composer_json);
+    let env = |name: &str| match name {
+        "http_proxy" => Some(url.clone()),
+        "no_proxy" => Some("127.0.0.1".to_string()),
+        _ => None,
+    };
+    let downloader = HttpDownloader::load(&config, &env)
+        .unwrap()
+        .with_retries(1, Duration::from_millis(10));
+
+    assert_eq!(
+        downloader.get(&format!("{url}/packages.json")).unwrap(),
+        b"{}"
+    );
+    assert_eq!(
+        downloader
+            .get("http://packages.example.com/p2/acme/lib.json")
+            .unwrap(),
+        b"proxied"
+    );
+    assert!(matches!(
+        downloader.get(&format!("{url}/failing.json")),
+        Err(HttpError::Status(_, 500))
+    ));
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].starts_with("GET /packages.json HTTP/1.1"));
+    assert!(requests[1].contains("Authorization: Basic dXNlcjpodW50ZXIy\r\n"));
+    assert!(requests[2].starts_with("GET http://packages.example.com/p2/acme/lib.json HTTP/1.1"));
+    assert!(requests[2].contains("Authorization: Bearer token\r\n"));
+    assert_eq!(requests.len(), 5);
+
+    assert!(matches!(
+        HttpDownloader::new().get("http://packages.example.com/packages.json"),
+        Err(HttpError::Insecure(_))
+    ));
+}
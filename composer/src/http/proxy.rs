@@ -0,0 +1,120 @@
+use url::Url;
+
+/// The proxies from the `http_proxy`, `https_proxy` and `no_proxy` environment variables
+///
+/// Like Composer, the lowercase variables win over the uppercase ones, except `HTTP_PROXY` which
+/// is ignored when running as CGI, and `https_proxy` falls back to `http_proxy`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Proxies {
+    pub http: Option<String>,
+    pub https: Option<String>,
+    pub no_proxy: Vec<String>,
+}
+
+impl Proxies {
+    pub fn from_env(env: &dyn Fn(&str) -> Option<String>) -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|name| env(name))
+                .find(|value| !value.trim().is_empty())
+                .map(|value| value.trim().to_string())
+        };
+
+        let http = match env("REQUEST_METHOD").is_some() {
+            true => var(&["http_proxy", "CGI_HTTP_PROXY"]),
+            false => var(&["http_proxy", "HTTP_PROXY", "CGI_HTTP_PROXY"]),
+        };
+        let https = var(&["https_proxy", "HTTPS_PROXY"]).or_else(|| http.clone());
+        let no_proxy = var(&["no_proxy", "NO_PROXY"])
+            .map(|value| {
+                value
+                    .split([',', ' '])
+                    .filter(|host| !host.is_empty())
+                    .map(str::to_lowercase)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Proxies {
+            http,
+            https,
+            no_proxy,
+        }
+    }
+
+    /// The proxy to use for a URL, if any
+    pub fn for_url(&self, url: &Url) -> Option<&str> {
+        let proxy = match url.scheme() {
+            "http" => self.http.as_deref(),
+            "https" => self.https.as_deref(),
+            _ => None,
+        }?;
+
+        match self.bypasses(url) {
+            true => None,
+            false => Some(proxy),
+        }
+    }
+
+    /// Whether `no_proxy` excludes the host of a URL, either by name, as subdomain of a name or a
+    /// `.domain`, with an optional port, or with `*` for all hosts
+    fn bypasses(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return true;
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = url.port_or_known_default();
+
+        self.no_proxy.iter().any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+
+            let (domain, entry_port) = match entry.rsplit_once(':') {
+                Some((domain, port)) if !domain.contains(':') => (domain, port.parse().ok()),
+                _ => (entry.as_str(), None),
+            };
+
+            if entry_port.is_some() && entry_port != port {
+                return false;
+            }
+
+            let domain = domain.trim_start_matches("*.").trim_start_matches('.');
+
+            host == domain || host.ends_with(&format!(".{domain}"))
+        })
+    }
+}
+
+#[test]
+fn test_proxies() {
+    let env = |name: &str| match name {
+        "HTTP_PROXY" => Some("http://proxy.example.com:3128".to_string()),
+        "no_proxy" => Some("localhost, .internal.example.com,example.org:8080".to_string()),
+        _ => None,
+    };
+    let proxies = Proxies::from_env(&env);
+    let proxy = |url: &str| proxies.for_url(&Url::parse(url).unwrap());
+
+    assert_eq!(
+        proxy("https://repo.packagist.org/packages.json"),
+        proxy("http://x.com")
+    );
+    assert_eq!(
+        proxy("https://repo.packagist.org"),
+        Some("http://proxy.example.com:3128")
+    );
+    assert_eq!(proxy("http://localhost/packages.json"), None);
+    assert_eq!(proxy("https://repo.internal.example.com"), None);
+    assert_eq!(proxy("http://internal.example.com"), None);
+    assert_eq!(proxy("http://example.org:8080"), None);
+    assert!(proxy("http://example.org").is_some());
+    assert_eq!(proxy("file:///tmp"), None);
+
+    let cgi = |name: &str| match name {
+        "REQUEST_METHOD" => Some("GET".to_string()),
+        _ => env(name),
+    };
+    assert_eq!(Proxies::from_env(&cgi).http, None);
+}
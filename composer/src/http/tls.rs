@@ -0,0 +1,88 @@
+use crate::http::HttpError;
+use std::path::Path;
+use std::sync::Arc;
+use ureq::rustls::pki_types::pem::PemObject;
+use ureq::rustls::pki_types::CertificateDer;
+use ureq::rustls::{crypto, ClientConfig, RootCertStore};
+
+/// The TLS config trusting the certificates of `cafile` and of the files in `capath`, or `None`
+/// to trust the bundled root certificates
+pub(crate) fn tls_config(
+    cafile: Option<&Path>,
+    capath: Option<&Path>,
+) -> Result<Option<Arc<ClientConfig>>, HttpError> {
+    if cafile.is_none() && capath.is_none() {
+        return Ok(None);
+    }
+
+    let source = cafile.or(capath).unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    files.extend(cafile.map(Path::to_path_buf));
+
+    if let Some(capath) = capath {
+        let entries = std::fs::read_dir(capath)
+            .map_err(|error| HttpError::Tls(capath.display().to_string(), error.to_string()))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+    }
+
+    let mut roots = RootCertStore::empty();
+
+    for file in &files {
+        let is_cafile = Some(file.as_path()) == cafile;
+        let certificates = match CertificateDer::pem_file_iter(file)
+            .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        {
+            Ok(certificates) => certificates,
+            // Like OpenSSL, files in capath that aren't certificates are skipped
+            Err(_) if !is_cafile => continue,
+            Err(error) => {
+                return Err(HttpError::Tls(
+                    file.display().to_string(),
+                    error.to_string(),
+                ))
+            }
+        };
+
+        let (added, _) = roots.add_parsable_certificates(certificates);
+
+        if added == 0 && is_cafile {
+            return Err(HttpError::Tls(
+                file.display().to_string(),
+                "no valid certificate found".into(),
+            ));
+        }
+    }
+
+    let config = ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|error| HttpError::Tls(source.display().to_string(), error.to_string()))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(Some(Arc::new(config)))
+}
+
+#[test]
+fn test_capath_skips_invalid_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let invalid = dir.path().join("invalid.pem");
+    std::fs::write(
+        &invalid,
+        "-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----\n",
+    )
+    .unwrap();
+
+    assert!(tls_config(None, Some(dir.path())).unwrap().is_some());
+    assert!(matches!(
+        tls_config(Some(&invalid), None),
+        Err(HttpError::Tls(..))
+    ));
+}
@@ -0,0 +1,138 @@
+use crate::composer_json::to_pretty_json;
+use crate::installer::InstallError;
+use crate::lock_file::Package;
+use serde::ser::{Error, SerializeMap};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// The packages installed in `vendor-dir`, as listed in `vendor/composer/installed.json`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct InstalledRepository {
+    pub packages: Vec<InstalledPackage>,
+    /// Whether the dev requirements were installed
+    #[serde(default)]
+    pub dev: bool,
+    #[serde(rename = "dev-package-names", default)]
+    pub dev_package_names: Vec<String>,
+}
+
+/// A package in `installed.json`, which records how it was installed on top of the lock file data
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct InstalledPackage {
+    #[serde(flatten)]
+    pub package: Package,
+    #[serde(rename = "installation-source")]
+    pub installation_source: Option<InstallMethod>,
+    /// Where the package is installed, relative to `vendor/composer`, which metapackages lack
+    #[serde(rename = "install-path", default)]
+    pub install_path: Option<String>,
+}
+
+/// The keys Composer writes before `installation-source`
+const BEFORE_INSTALLATION_SOURCE: [&str; 17] = [
+    "name",
+    "version",
+    "version_normalized",
+    "target-dir",
+    "source",
+    "dist",
+    "require",
+    "conflict",
+    "provide",
+    "replace",
+    "require-dev",
+    "suggest",
+    "time",
+    "default-branch",
+    "bin",
+    "type",
+    "extra",
+];
+
+impl Serialize for InstalledPackage {
+    /// Puts `installation-source` among the keys of the package and `install-path` last, like
+    /// Composer does
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Value::Object(package) =
+            serde_json::to_value(&self.package).map_err(S::Error::custom)?
+        else {
+            unreachable!("Package always serializes to an object");
+        };
+
+        let position = package
+            .keys()
+            .position(|key| !BEFORE_INSTALLATION_SOURCE.contains(&key.as_str()))
+            .unwrap_or(package.len());
+        let mut map = serializer.serialize_map(Some(package.len() + 2))?;
+
+        for (index, (key, value)) in package.iter().enumerate() {
+            if index == position {
+                if let Some(method) = &self.installation_source {
+                    map.serialize_entry("installation-source", method)?;
+                }
+            }
+
+            map.serialize_entry(key, value)?;
+        }
+
+        if position == package.len() {
+            if let Some(method) = &self.installation_source {
+                map.serialize_entry("installation-source", method)?;
+            }
+        }
+
+        map.serialize_entry("install-path", &self.install_path)?;
+        map.end()
+    }
+}
+
+/// Whether a package is installed from its dist archive or checked out from its source
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallMethod {
+    Dist,
+    Source,
+}
+
+impl InstalledRepository {
+    /// Reads `composer/installed.json` in `vendor_dir`, which is empty when nothing was installed
+    ///
+    /// The plain list of packages that Composer 1 writes is read as well.
+    pub fn load(vendor_dir: &Path) -> Result<Self, InstallError> {
+        let path = Self::path(vendor_dir);
+        let Ok(data) = std::fs::read(&path) else {
+            return Ok(InstalledRepository::default());
+        };
+        let invalid = |error| InstallError::InvalidInstalled(path.display().to_string(), error);
+
+        match serde_json::from_slice(&data).map_err(invalid)? {
+            Value::Array(packages) => Ok(InstalledRepository {
+                packages: serde_json::from_value(Value::Array(packages)).map_err(invalid)?,
+                ..InstalledRepository::default()
+            }),
+            value => serde_json::from_value(value).map_err(invalid),
+        }
+    }
+
+    /// Writes `composer/installed.json` in `vendor_dir`
+    pub fn write(&self, vendor_dir: &Path) -> Result<(), InstallError> {
+        let path = Self::path(vendor_dir);
+        let io_error = |error| InstallError::Io(path.display().to_string(), error);
+        let json = to_pretty_json(self)
+            .map_err(|error| InstallError::InvalidInstalled(path.display().to_string(), error))?;
+
+        std::fs::create_dir_all(path.parent().unwrap_or(vendor_dir)).map_err(io_error)?;
+        std::fs::write(&path, json + "\n").map_err(io_error)
+    }
+
+    pub fn path(vendor_dir: &Path) -> PathBuf {
+        vendor_dir.join("composer").join("installed.json")
+    }
+
+    pub fn find(&self, name: &str) -> Option<&InstalledPackage> {
+        self.packages
+            .iter()
+            .find(|installed| installed.package.name.eq_ignore_ascii_case(name))
+    }
+}
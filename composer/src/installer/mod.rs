@@ -0,0 +1,623 @@
+mod installed;
+mod store;
+mod transaction;
+mod versions;
+
+pub use installed::{InstallMethod, InstalledPackage, InstalledRepository};
+pub use store::{Store, StoreLink, StoreLock};
+pub use transaction::{Operation, Transaction};
+pub use versions::{
+    installed_php, root_package, write_installed_versions, INSTALLED_VERSIONS_CLASS,
+};
+
+use crate::composer_json::{ComposerJson, ComposerJsonError};
+use crate::config::{Config, InstallStrategy, PackageSetting, PreferredInstallMethod};
+use crate::downloader::{extract, install_path, DistDownloader, DownloadError, ProgressReporter};
+use crate::lock_file::{LockFile, Package, PackageSource};
+use crate::repository::{git, matches_package_pattern, RepositoryError};
+use crate::semver::{normalize, Stability};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Installs the packages of a lock file into `vendor-dir`, without resolving anything
+pub struct Installer<'a> {
+    config: &'a Config,
+    downloader: DistDownloader,
+    dev: bool,
+    store: Option<Store>,
+}
+
+#[derive(Error, Debug)]
+pub enum InstallError {
+    #[error("{0}")]
+    Download(DownloadError),
+
+    #[error("{0}")]
+    Repository(RepositoryError),
+
+    #[error("Package {0} is not in the store")]
+    NotInStore(String),
+
+    #[error("Package {0} cannot be installed from a {1} source")]
+    UnsupportedSource(String, String),
+
+    #[error("Could not write to \"{0}\": {1}")]
+    Io(String, std::io::Error),
+
+    #[error("\"{0}\" does not contain valid JSON: {1}")]
+    InvalidInstalled(String, serde_json::Error),
+
+    #[error("Could not read \"{0}\": {1}")]
+    InvalidComposerJson(String, ComposerJsonError),
+}
+
+impl From<DownloadError> for InstallError {
+    fn from(value: DownloadError) -> Self {
+        InstallError::Download(value)
+    }
+}
+
+impl From<RepositoryError> for InstallError {
+    fn from(value: RepositoryError) -> Self {
+        InstallError::Repository(value)
+    }
+}
+
+impl<'a> Installer<'a> {
+    /// An installer of both the packages and the dev packages of lock files, which extracts dists
+    /// into the store when that is the `install-strategy`
+    pub fn new(config: &'a Config, downloader: DistDownloader) -> Self {
+        Installer {
+            config,
+            downloader,
+            dev: true,
+            store: match config.install_strategy {
+                InstallStrategy::Store => Some(Store::from_config(config)),
+                InstallStrategy::Vendor => None,
+            },
+        }
+    }
+
+    /// Whether the dev packages are installed, or removed when they were installed before
+    pub fn with_dev(mut self, dev: bool) -> Self {
+        self.dev = dev;
+        self
+    }
+
+    /// The packages of a lock file which are installed
+    pub fn packages<'l>(&self, lock_file: &'l LockFile) -> Vec<&'l Package> {
+        let mut packages: Vec<_> = lock_file.packages.iter().collect();
+
+        if self.dev {
+            packages.extend(&lock_file.packages_dev);
+        }
+
+        packages
+    }
+
+    /// The operations which bring `vendor-dir` in line with a lock file
+    ///
+    /// Packages whose install path is gone are installed again.
+    pub fn plan(&self, lock_file: &LockFile) -> Result<Transaction, InstallError> {
+        let installed = InstalledRepository::load(&self.config.vendor_dir)?;
+        let present: Vec<&Package> = installed
+            .packages
+            .iter()
+            .map(|installed| &installed.package)
+            .filter(|package| !has_files(package) || self.install_dir(package).exists())
+            .collect();
+
+        Ok(Transaction::new(
+            &present,
+            &self.packages(lock_file),
+            &lock_file.aliases,
+        ))
+    }
+
+    /// Runs the operations planned for a lock file, then writes `installed.json`, `installed.php`
+    /// and the `InstalledVersions` class
+    ///
+    /// All dists are downloaded before anything in `vendor-dir` changes. With the store, only
+    /// dists missing from it are downloaded, and the project is registered as using it.
+    pub fn execute(
+        &self,
+        lock_file: &LockFile,
+        transaction: &Transaction,
+        reporter: &dyn ProgressReporter,
+    ) -> Result<(), InstallError> {
+        let vendor_dir = &self.config.vendor_dir;
+        let previous = InstalledRepository::load(vendor_dir)?;
+        let downloads_dir = vendor_dir.join("composer").join("tmp-downloads");
+
+        let mut methods: HashMap<String, InstallMethod> = previous
+            .packages
+            .iter()
+            .filter_map(|p| Some((p.package.name.clone(), p.installation_source?)))
+            .collect();
+        let mut dists = Vec::new();
+
+        // Locked before looking up the store, so a package found in it can't be pruned before it
+        // is linked
+        let _lock = self.store.as_ref().map(Store::lock).transpose()?;
+
+        for operation in &transaction.operations {
+            let (Operation::Install(package)
+            | Operation::Update { to: package, .. }
+            | Operation::Downgrade { to: package, .. }) = operation
+            else {
+                continue;
+            };
+
+            let method = install_method(&self.config.preferred_install, package);
+
+            let in_store = self
+                .store
+                .as_ref()
+                .is_some_and(|store| store.contains(package));
+
+            if method == Some(InstallMethod::Dist) && !in_store {
+                dists.push(package);
+            }
+
+            match method {
+                Some(method) => methods.insert(package.name.clone(), method),
+                None => methods.remove(&package.name),
+            };
+        }
+
+        let archives: HashMap<String, PathBuf> = self
+            .downloader
+            .download_all(&dists, &downloads_dir, reporter)?
+            .into_iter()
+            .collect();
+
+        let result = transaction
+            .operations
+            .iter()
+            .try_for_each(|operation| match operation {
+                Operation::Uninstall(package) => self.uninstall(package),
+                Operation::MarkAliasInstalled { .. } | Operation::MarkAliasUninstalled { .. } => {
+                    Ok(())
+                }
+                Operation::Install(package)
+                | Operation::Update { to: package, .. }
+                | Operation::Downgrade { to: package, .. } => match methods.get(&package.name) {
+                    Some(InstallMethod::Dist) => {
+                        self.install_dist(package, archives.get(&package.name))
+                    }
+                    Some(InstallMethod::Source) => self.install_source(package),
+                    None => Ok(()),
+                },
+            });
+
+        let _ = std::fs::remove_dir_all(&downloads_dir);
+        result?;
+
+        let mut packages = self.packages(lock_file);
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut dev_package_names: Vec<String> = match self.dev {
+            true => lock_file
+                .packages_dev
+                .iter()
+                .map(|package| package.name.clone())
+                .collect(),
+            false => Vec::new(),
+        };
+        dev_package_names.sort();
+
+        let installed = InstalledRepository {
+            packages: packages
+                .into_iter()
+                .map(|package| {
+                    let mut package = package.clone();
+                    package.version_normalized = package
+                        .version_normalized
+                        .or_else(|| normalize(&package.version).ok());
+
+                    InstalledPackage {
+                        installation_source: methods.get(&package.name).copied(),
+                        install_path: match package.package_type.as_str() {
+                            "metapackage" => None,
+                            _ => Some(format!("../{}", package.name)),
+                        },
+                        package,
+                    }
+                })
+                .collect(),
+            dev: self.dev,
+            dev_package_names,
+        };
+        installed.write(vendor_dir)?;
+
+        let project_dir = self.config.composer_json.parent().unwrap_or(Path::new("."));
+        write_installed_versions(
+            vendor_dir,
+            &installed,
+            &root_package(&self.root()?, project_dir, &|name| std::env::var(name).ok()),
+            &lock_file.aliases,
+            project_dir,
+        )?;
+
+        match &self.store {
+            Some(store) => store.register(vendor_dir),
+            None => Ok(()),
+        }
+    }
+
+    /// The root composer.json, which is empty when there is none
+    fn root(&self) -> Result<ComposerJson, InstallError> {
+        let path = &self.config.composer_json;
+        let invalid = |error| InstallError::InvalidComposerJson(path.display().to_string(), error);
+
+        match std::fs::read_to_string(path) {
+            Ok(data) => ComposerJson::from_json_str(&data).map_err(invalid),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(ComposerJson::default())
+            }
+            Err(error) => Err(invalid(ComposerJsonError::from(error))),
+        }
+    }
+
+    /// Where a package is installed
+    pub fn install_dir(&self, package: &Package) -> PathBuf {
+        self.config.vendor_dir.join(&package.name)
+    }
+
+    fn install_dist(
+        &self,
+        package: &Package,
+        archive: Option<&PathBuf>,
+    ) -> Result<(), InstallError> {
+        let target = self.install_dir(package);
+
+        match (archive, &package.dist, &self.store) {
+            (_, Some(dist), _) if dist.dist_type == "path" => {
+                let project_dir = self.config.composer_json.parent().unwrap_or(Path::new("."));
+                install_path(package, &project_dir.join(&dist.url), &target)?;
+            }
+            (archive, Some(_), Some(store)) => {
+                if let Some(archive) = archive {
+                    store.add(package, archive)?;
+                }
+
+                store.link(package, &target)?;
+            }
+            (Some(archive), _, None) => extract(archive, &target)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Clones the source of a package and checks out its reference, replacing what was installed
+    /// once the checkout succeeded
+    fn install_source(&self, package: &Package) -> Result<(), InstallError> {
+        let Some(PackageSource {
+            source_type,
+            url,
+            reference,
+        }) = &package.source
+        else {
+            return Ok(());
+        };
+
+        if source_type != "git" {
+            return Err(InstallError::UnsupportedSource(
+                package.name.clone(),
+                source_type.clone(),
+            ));
+        }
+
+        let target = self.install_dir(package);
+        let io_error = |error| InstallError::Io(target.display().to_string(), error);
+        std::fs::create_dir_all(target.parent().unwrap_or(&target)).map_err(io_error)?;
+
+        let checkout = crate::cache::temp_path(&target);
+        let clone = || -> Result<(), InstallError> {
+            let dir = checkout.to_string_lossy();
+            git(
+                url,
+                None,
+                &["clone", "--quiet", "--no-checkout", "--", url, &dir],
+            )?;
+            git(
+                url,
+                Some(&checkout),
+                &["checkout", "--quiet", "--detach", reference],
+            )?;
+
+            crate::downloader::replace(&checkout, &target).map_err(io_error)
+        };
+
+        let result = clone();
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&checkout);
+        }
+
+        result
+    }
+
+    fn uninstall(&self, package: &Package) -> Result<(), InstallError> {
+        let target = self.install_dir(package);
+        let io_error = |error| InstallError::Io(target.display().to_string(), error);
+
+        match std::fs::symlink_metadata(&target) {
+            Ok(metadata) if metadata.is_dir() => {
+                std::fs::remove_dir_all(&target).map_err(io_error)?
+            }
+            Ok(_) => std::fs::remove_file(&target).map_err(io_error)?,
+            Err(_) => {}
+        }
+
+        // The vendor directory goes too when it was the last package of its vendor
+        if let Some(parent) = target.parent() {
+            let _ = std::fs::remove_dir(parent);
+        }
+
+        Ok(())
+    }
+}
+
+/// How a package is installed according to `preferred-install`, or `None` when it has neither
+/// dist nor source
+///
+/// `auto`, like a package matching no pattern, installs dev versions from source and others from
+/// dist. When several patterns match, the first one in config order wins, as in Composer. The
+/// other method is used when the preferred one isn't available.
+pub fn install_method(
+    preferred: &PackageSetting<PreferredInstallMethod>,
+    package: &Package,
+) -> Option<InstallMethod> {
+    let preference = match preferred {
+        PackageSetting::Global(method) => Some(method),
+        PackageSetting::Selective(patterns) => patterns
+            .iter()
+            .find(|(pattern, _)| matches_package_pattern(pattern, &package.name))
+            .map(|(_, method)| method),
+    };
+
+    let prefer_source = match preference {
+        Some(PreferredInstallMethod::Source) => true,
+        Some(PreferredInstallMethod::Dist) => false,
+        Some(PreferredInstallMethod::Auto) | None => {
+            Stability::of(&package.version) == Stability::Dev
+        }
+    };
+
+    match (&package.source, &package.dist) {
+        (Some(_), _) if prefer_source => Some(InstallMethod::Source),
+        (_, Some(_)) => Some(InstallMethod::Dist),
+        (Some(_), None) => Some(InstallMethod::Source),
+        (None, None) => None,
+    }
+}
+
+/// Whether a package has files in `vendor-dir`, unlike metapackages
+fn has_files(package: &Package) -> bool {
+    package.source.is_some() || package.dist.is_some()
+}
+
+#[test]
+fn test_install_method() {
+    use crate::lock_file::PackageDist;
+
+    let mut package = Package::new("acme/lib", "1.0.0");
+    let selective = |patterns: &[(&str, PreferredInstallMethod)]| {
+        PackageSetting::Selective(
+            patterns
+                .iter()
+                .map(|(pattern, method)| (pattern.to_string(), method.clone()))
+                .collect(),
+        )
+    };
+
+    assert_eq!(install_method(&PackageSetting::default(), &package), None);
+
+    package.source = Some(PackageSource {
+        source_type: "git".into(),
+        url: "https://github.com/acme/lib.git".into(),
+        reference: "abc".into(),
+    });
+    package.dist = Some(PackageDist {
+        dist_type: "zip".into(),
+        url: "https://example.com/lib.zip".into(),
+        reference: Some("abc".into()),
+        shasum: None,
+    });
+
+    assert_eq!(
+        install_method(&PackageSetting::default(), &package),
+        Some(InstallMethod::Dist)
+    );
+    assert_eq!(
+        install_method(
+            &selective(&[
+                ("acme/*", PreferredInstallMethod::Source),
+                ("acme/lib", PreferredInstallMethod::Dist)
+            ]),
+            &package
+        ),
+        Some(InstallMethod::Source)
+    );
+    assert_eq!(
+        install_method(
+            &selective(&[("ACME/*", PreferredInstallMethod::Source)]),
+            &package
+        ),
+        Some(InstallMethod::Source)
+    );
+
+    package.version = "dev-main".into();
+    assert_eq!(
+        install_method(
+            &selective(&[
+                ("other/*", PreferredInstallMethod::Source),
+                ("*", PreferredInstallMethod::Dist)
+            ]),
+            &package
+        ),
+        Some(InstallMethod::Dist)
+    );
+    assert_eq!(
+        install_method(
+            &PackageSetting::Global(PreferredInstallMethod::Auto),
+            &package
+        ),
+        Some(InstallMethod::Source)
+    );
+
+    package.source = None;
+    assert_eq!(
+        install_method(
+            &PackageSetting::Global(PreferredInstallMethod::Source),
+            &package
+        ),
+        Some(InstallMethod::Dist)
+    );
+}
+
+#[test]
+fn test_installer() {
+    use crate::config::test_config;
+    use crate::downloader::NoProgress;
+    use crate::http::HttpDownloader;
+    use crate::lock_file::PackageDist;
+    use crate::repository::vcs::git::{commit_test_repository, init_test_repository, test_git};
+    use std::io::Write;
+
+    let project = tempfile::tempdir().unwrap();
+    let home = tempfile::tempdir().unwrap();
+    let config = test_config(
+        project.path(),
+        home.path(),
+        r#"{"config": {"preferred-install": {"acme/from-source": "source"}}}"#,
+    );
+
+    let archive = project.path().join("lib.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+    zip.start_file("lib/src/Lib.php", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(b"<?php").unwrap();
+    zip.finish().unwrap();
+
+    let mut lib = Package::new("acme/lib", "1.0.0");
+    lib.dist = Some(PackageDist {
+        dist_type: "zip".into(),
+        url: format!("file://{}", archive.display()),
+        reference: Some("1a2b3c".into()),
+        shasum: None,
+    });
+
+    let repository = project.path().join("source");
+    std::fs::create_dir(&repository).unwrap();
+    init_test_repository(&repository, &[("README.md", "first")]);
+    let first = test_git(Some(&repository), &["rev-parse", "HEAD"]);
+    std::fs::write(repository.join("README.md"), "second").unwrap();
+    commit_test_repository(&repository, "Second commit");
+
+    let mut from_source = Package::new("acme/from-source", "1.0.0");
+    from_source.source = Some(PackageSource {
+        source_type: "git".into(),
+        url: repository.display().to_string(),
+        reference: first.trim().into(),
+    });
+    from_source.dist = lib.dist.clone();
+
+    let mut lock_file = LockFile::new("hash");
+    lock_file.packages = vec![lib.clone(), from_source];
+    lock_file.packages_dev = vec![Package::new("acme/meta", "1.0.0")];
+
+    let http = HttpDownloader::load(&config, &|_| None).unwrap();
+    let installer = Installer::new(&config, DistDownloader::new(http.clone()));
+    let transaction = installer.plan(&lock_file).unwrap();
+
+    assert_eq!(transaction.operations.len(), 3);
+    assert_eq!(
+        transaction.operations[1].to_string(),
+        "Installing acme/lib (1.0.0)"
+    );
+
+    installer
+        .execute(&lock_file, &transaction, &NoProgress)
+        .unwrap();
+
+    let vendor = &config.vendor_dir;
+    assert!(vendor.join("acme/lib/src/Lib.php").is_file());
+    assert_eq!(
+        std::fs::read_to_string(vendor.join("acme/from-source/README.md")).unwrap(),
+        "first"
+    );
+    assert!(!vendor.join("composer/tmp-downloads").exists());
+    assert!(vendor.join("composer/installed.php").is_file());
+
+    let installed = InstalledRepository::load(vendor).unwrap();
+    assert_eq!(installed.dev_package_names, ["acme/meta"]);
+    assert_eq!(
+        installed
+            .find("acme/from-source")
+            .unwrap()
+            .installation_source,
+        Some(InstallMethod::Source)
+    );
+    assert_eq!(
+        installed.find("acme/lib").unwrap().install_path.as_deref(),
+        Some("../acme/lib")
+    );
+    assert!(installer.plan(&lock_file).unwrap().is_empty());
+
+    lock_file.packages[0].version = "1.0.1".into();
+    std::fs::remove_dir_all(vendor.join("acme/from-source")).unwrap();
+
+    let installer = Installer::new(&config, DistDownloader::new(http.clone())).with_dev(false);
+    let transaction = installer.plan(&lock_file).unwrap();
+
+    assert_eq!(
+        transaction
+            .operations
+            .iter()
+            .map(Operation::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "Removing acme/meta (1.0.0)",
+            "Installing acme/from-source (1.0.0)",
+            "Upgrading acme/lib (1.0.0 => 1.0.1)",
+        ]
+    );
+
+    installer
+        .execute(&lock_file, &transaction, &NoProgress)
+        .unwrap();
+
+    let installed = InstalledRepository::load(vendor).unwrap();
+    assert!(!installed.dev);
+    assert_eq!(installed.packages.len(), 2);
+    assert!(vendor.join("acme/from-source/README.md").is_file());
+
+    let other = tempfile::tempdir().unwrap();
+    let config = test_config(
+        other.path(),
+        home.path(),
+        r#"{"config": {"install-strategy": "store"}}"#,
+    );
+    let installer = Installer::new(&config, DistDownloader::new(http.clone()));
+    lock_file.packages = vec![lib.clone()];
+
+    installer
+        .execute(
+            &lock_file,
+            &installer.plan(&lock_file).unwrap(),
+            &NoProgress,
+        )
+        .unwrap();
+
+    assert!(config.vendor_dir.join("acme/lib/src/Lib.php").is_file());
+    assert!(
+        std::fs::symlink_metadata(config.vendor_dir.join("acme/lib"))
+            .unwrap()
+            .is_symlink()
+    );
+    assert!(Store::from_config(&config).contains(&lib));
+    assert_eq!(Store::from_config(&config).prune().unwrap(), 0);
+}
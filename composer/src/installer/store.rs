@@ -0,0 +1,343 @@
+use crate::cache::lock_file;
+use crate::config::Config;
+use crate::downloader::{copy_dir, extract, remove, replace, symlink_dir, DownloadError};
+use crate::installer::{InstallError, InstallMethod, InstalledRepository};
+use crate::lock_file::Package;
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// The extracted dists shared by all projects installed with the `store` install strategy, in
+/// `data-dir/store`
+///
+/// Each dist is extracted once into `packages/<name>/<key>`, where the key is the dist reference,
+/// its shasum or, lacking both, a hash of its URL. The `vendor-dir` of each project using the store
+/// is registered in `projects`, so entries which no project uses anymore can be pruned.
+#[derive(Debug, Clone)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+/// How a package from the store was put into `vendor-dir`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoreLink {
+    Symlink,
+    /// Each file is hard linked, for systems where the symlink can't be created
+    Hardlink,
+    /// The files are copied, when the store is on another file system
+    Copy,
+}
+
+/// A shared lock on a store, released when dropped
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: File,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Store { dir: dir.into() }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Store::new(config.data_dir.join("store"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the dist of a package is extracted, or `None` for packages without dist
+    pub fn path(&self, package: &Package) -> Option<PathBuf> {
+        let dist = package.dist.as_ref()?;
+        let non_empty = |value: &Option<String>| value.clone().filter(|value| !value.is_empty());
+        let key = non_empty(&dist.reference)
+            .or_else(|| non_empty(&dist.shasum))
+            .unwrap_or_else(|| format!("{:x}", Sha1::digest(dist.url.as_bytes())));
+        let key: String = key
+            .chars()
+            .map(
+                |char| match char.is_ascii_alphanumeric() || "._-".contains(char) {
+                    true => char,
+                    false => '-',
+                },
+            )
+            .collect();
+
+        Some(
+            self.dir
+                .join("packages")
+                .join(package.name.to_lowercase())
+                .join(key),
+        )
+    }
+
+    pub fn contains(&self, package: &Package) -> bool {
+        self.path(package).is_some_and(|path| path.is_dir())
+    }
+
+    /// Extracts the archive of a package into the store, unless it is there already
+    pub fn add(&self, package: &Package, archive: &Path) -> Result<(), DownloadError> {
+        match self.path(package) {
+            Some(path) if !path.is_dir() => extract(archive, &path),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces `target` by a symlink to the package in the store, or by hard links to its files
+    /// when symlinking fails, or by a copy of them when hard linking fails too
+    pub fn link(&self, package: &Package, target: &Path) -> Result<StoreLink, InstallError> {
+        let io_error = |error| InstallError::Io(target.display().to_string(), error);
+        let source = self
+            .path(package)
+            .filter(|path| path.is_dir())
+            .ok_or_else(|| InstallError::NotInStore(package.name.clone()))?;
+
+        std::fs::create_dir_all(target.parent().unwrap_or(target)).map_err(io_error)?;
+
+        let temp = crate::cache::temp_path(target);
+        let link = if symlink_dir(&source, &temp).is_ok() {
+            StoreLink::Symlink
+        } else if hardlink_dir(&source, &temp).is_ok() {
+            StoreLink::Hardlink
+        } else {
+            let _ = remove(&temp);
+            copy_dir(&source, &temp).map_err(io_error)?;
+
+            StoreLink::Copy
+        };
+
+        if let Err(error) = replace(&temp, target) {
+            let _ = remove(&temp);
+
+            return Err(io_error(error));
+        }
+
+        Ok(link)
+    }
+
+    /// Registers a project by its `vendor-dir`, so [`prune`](Self::prune) keeps what it uses
+    pub fn register(&self, vendor_dir: &Path) -> Result<(), InstallError> {
+        let projects = self.dir.join("projects");
+        let io_error = |error| InstallError::Io(projects.display().to_string(), error);
+        let vendor_dir = std::path::absolute(vendor_dir).map_err(io_error)?;
+        let name = format!(
+            "{:x}",
+            Sha1::digest(vendor_dir.to_string_lossy().as_bytes())
+        );
+
+        std::fs::create_dir_all(&projects).map_err(io_error)?;
+        std::fs::write(projects.join(name), vendor_dir.to_string_lossy().as_bytes())
+            .map_err(io_error)
+    }
+
+    /// Takes a shared lock on the store until the returned lock is dropped, so that nothing is
+    /// pruned while projects are installed
+    pub fn lock(&self) -> Result<StoreLock, InstallError> {
+        let io_error = |error| InstallError::Io(self.dir.display().to_string(), error);
+        let file = lock_file(&self.dir).map_err(io_error)?;
+        file.lock_shared().map_err(io_error)?;
+
+        Ok(StoreLock { _file: file })
+    }
+
+    /// Removes the packages which no registered project has installed from its dist anymore, and
+    /// returns how many were removed
+    ///
+    /// Projects whose `installed.json` is gone are unregistered. Nothing is pruned while another
+    /// process holds a lock on the store.
+    pub fn prune(&self) -> Result<usize, InstallError> {
+        let packages = self.dir.join("packages");
+
+        if !packages.is_dir() {
+            return Ok(0);
+        }
+
+        let io_error = |path: &Path, error| InstallError::Io(path.display().to_string(), error);
+        let lock = lock_file(&self.dir).map_err(|error| io_error(&self.dir, error))?;
+
+        if lock.try_lock().is_err() {
+            return Ok(0);
+        }
+
+        let mut used = HashSet::new();
+        let projects = self.dir.join("projects");
+
+        for entry in std::fs::read_dir(&projects).into_iter().flatten().flatten() {
+            let vendor_dir =
+                PathBuf::from(std::fs::read_to_string(entry.path()).unwrap_or_default());
+
+            if !InstalledRepository::path(&vendor_dir).is_file() {
+                std::fs::remove_file(entry.path())
+                    .map_err(|error| io_error(&entry.path(), error))?;
+                continue;
+            }
+
+            let installed = InstalledRepository::load(&vendor_dir)?;
+
+            used.extend(
+                installed
+                    .packages
+                    .iter()
+                    .filter(|installed| installed.installation_source == Some(InstallMethod::Dist))
+                    .filter_map(|installed| self.path(&installed.package)),
+            );
+        }
+
+        let mut removed = 0;
+
+        for (vendor, name, key) in entries(&packages).map_err(|error| io_error(&packages, error))? {
+            let path = packages.join(&vendor).join(&name).join(&key);
+
+            if used.contains(&path) {
+                continue;
+            }
+
+            std::fs::remove_dir_all(&path).map_err(|error| io_error(&path, error))?;
+            removed += 1;
+
+            // Directories of packages and vendors go with their last entry
+            let _ = std::fs::remove_dir(packages.join(&vendor).join(&name));
+            let _ = std::fs::remove_dir(packages.join(&vendor));
+        }
+
+        Ok(removed)
+    }
+}
+
+/// The vendor, name and key of each entry in the `packages` directory of a store
+fn entries(packages: &Path) -> std::io::Result<Vec<(String, String, String)>> {
+    let names = |dir: &Path| -> std::io::Result<Vec<String>> {
+        std::fs::read_dir(dir)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect()
+    };
+    let mut entries = Vec::new();
+
+    for vendor in names(packages)? {
+        for name in names(&packages.join(&vendor))? {
+            for key in names(&packages.join(&vendor).join(&name))? {
+                entries.push((vendor.clone(), name.clone(), key));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Recreates a directory with hard links to its files, keeping symlinks
+fn hardlink_dir(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(target)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let destination = target.join(entry.file_name());
+
+        if file_type.is_symlink() {
+            let original = std::fs::read_link(entry.path())?;
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(original, &destination)?;
+            #[cfg(windows)]
+            std::fs::copy(entry.path().join(original), &destination).map(|_| ())?;
+        } else if file_type.is_dir() {
+            hardlink_dir(&entry.path(), &destination)?;
+        } else {
+            std::fs::hard_link(entry.path(), &destination)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_store() {
+    use crate::installer::InstalledPackage;
+    use crate::lock_file::PackageDist;
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::new(dir.path().join("store"));
+
+    let archive = dir.path().join("lib.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+    zip.start_file("lib/Lib.php", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(b"<?php").unwrap();
+    zip.finish().unwrap();
+
+    let package = |name: &str, reference: &str| {
+        let mut package = Package::new(name, "1.0.0");
+        package.dist = Some(PackageDist {
+            dist_type: "zip".into(),
+            url: "https://example.com/lib.zip".into(),
+            reference: Some(reference.into()),
+            shasum: None,
+        });
+        package
+    };
+    let (lib, old, other) = (
+        package("Acme/Lib", "1a2b3c"),
+        package("acme/lib", "0f0f0f"),
+        package("acme/other", "4d5e6f"),
+    );
+
+    assert!(store
+        .path(&lib)
+        .unwrap()
+        .ends_with("packages/acme/lib/1a2b3c"));
+    assert!(matches!(
+        store.link(&lib, &dir.path().join("vendor/acme/lib")),
+        Err(InstallError::NotInStore(_))
+    ));
+
+    for package in [&lib, &old, &other] {
+        store.add(package, &archive).unwrap();
+    }
+
+    let vendor = dir.path().join("vendor");
+    assert_eq!(
+        store.link(&lib, &vendor.join("acme/lib")).unwrap(),
+        StoreLink::Symlink
+    );
+    assert_eq!(
+        std::fs::read(vendor.join("acme/lib/Lib.php")).unwrap(),
+        b"<?php"
+    );
+    assert!(std::fs::symlink_metadata(vendor.join("acme/lib"))
+        .unwrap()
+        .is_symlink());
+
+    hardlink_dir(&store.path(&other).unwrap(), &dir.path().join("hardlinked")).unwrap();
+    assert!(dir.path().join("hardlinked/Lib.php").is_file());
+
+    InstalledRepository {
+        packages: vec![InstalledPackage {
+            package: lib.clone(),
+            installation_source: Some(InstallMethod::Dist),
+            install_path: Some("../acme/lib".into()),
+        }],
+        ..InstalledRepository::default()
+    }
+    .write(&vendor)
+    .unwrap();
+    store.register(&vendor).unwrap();
+    store.register(&dir.path().join("removed/vendor")).unwrap();
+
+    let lock = store.lock().unwrap();
+    assert_eq!(store.prune().unwrap(), 0);
+    drop(lock);
+
+    assert_eq!(store.prune().unwrap(), 2);
+    assert!(store.contains(&lib));
+    assert!(!store.contains(&old));
+    assert!(!store.dir().join("packages/acme/other").exists());
+    assert_eq!(
+        std::fs::read_dir(store.dir().join("projects"))
+            .unwrap()
+            .count(),
+        1
+    );
+}
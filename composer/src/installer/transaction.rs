@@ -0,0 +1,642 @@
+use crate::lock_file::{Alias, Package, DEFAULT_BRANCH_ALIAS};
+use crate::resolver::is_platform_package;
+use crate::semver::{compare_versions, normalize, Stability};
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// A change to `vendor-dir`
+///
+/// Aliases have no files of their own, marking them only changes which versions are considered
+/// installed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Install(Package),
+    Update { from: Box<Package>, to: Package },
+    Downgrade { from: Box<Package>, to: Package },
+    Uninstall(Package),
+    MarkAliasInstalled { package: Package, alias: String },
+    MarkAliasUninstalled { package: Package, alias: String },
+}
+
+/// The operations which turn the installed packages into the packages of a lock file, in the order
+/// Composer runs them
+///
+/// `Display` prints them like `composer install --dry-run` does, and the transaction can be
+/// serialized to JSON for tools.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Transaction {
+    pub operations: Vec<Operation>,
+}
+
+impl Transaction {
+    /// Plans the operations like Composer: removals first, then plugins and their dependencies,
+    /// then everything else with each package after the packages it requires
+    ///
+    /// Packages are updated when their version or one of their references changed. The branch
+    /// aliases of dev versions and the root aliases of the lock file are marked as installed.
+    pub fn new(present: &[&Package], result: &[&Package], root_aliases: &[Alias]) -> Self {
+        let mut remove: Vec<&Package> = present.to_vec();
+        let mut remove_aliases: Vec<(&Package, String)> = present
+            .iter()
+            .flat_map(|package| package.branch_alias().map(|(alias, _)| (*package, alias)))
+            .collect();
+
+        let nodes = nodes(result, root_aliases);
+        let mut operations = Vec::new();
+
+        for index in dependency_order(&nodes) {
+            let (package, alias) = &nodes[index];
+
+            if let Some(alias) = alias {
+                let present = remove_aliases.iter().position(|(present, present_alias)| {
+                    present.name.eq_ignore_ascii_case(&package.name) && present_alias == alias
+                });
+
+                match present {
+                    Some(position) => drop(remove_aliases.remove(position)),
+                    None => operations.push(Operation::MarkAliasInstalled {
+                        package: (*package).clone(),
+                        alias: alias.clone(),
+                    }),
+                }
+
+                continue;
+            }
+
+            let Some(position) = remove
+                .iter()
+                .position(|present| present.name.eq_ignore_ascii_case(&package.name))
+            else {
+                operations.push(Operation::Install((*package).clone()));
+                continue;
+            };
+
+            let from = remove.remove(position);
+            let changed = from.version != package.version
+                || dist_reference(from) != dist_reference(package)
+                || source_reference(from) != source_reference(package);
+
+            if changed {
+                let (from, to) = (Box::new(from.clone()), (*package).clone());
+
+                operations.push(match is_upgrade(&from, &to) {
+                    true => Operation::Update { from, to },
+                    false => Operation::Downgrade { from, to },
+                });
+            }
+        }
+
+        let uninstalls = remove
+            .into_iter()
+            .rev()
+            .map(|package| Operation::Uninstall(package.clone()))
+            .chain(remove_aliases.into_iter().map(|(package, alias)| {
+                Operation::MarkAliasUninstalled {
+                    package: package.clone(),
+                    alias,
+                }
+            }));
+
+        Transaction {
+            operations: uninstalls.chain(plugins_first(operations)).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// The line Composer prints before running the operations, which leaves out aliases
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "Nothing to install, update or remove".into();
+        }
+
+        let count = |noun: &str, matches: fn(&Operation) -> bool| {
+            let count = self.operations.iter().filter(|op| matches(op)).count();
+
+            match count {
+                1 => format!("{count} {noun}"),
+                _ => format!("{count} {noun}s"),
+            }
+        };
+
+        format!(
+            "Package operations: {}, {}, {}",
+            count("install", |op| matches!(op, Operation::Install(_))),
+            count("update", |op| matches!(
+                op,
+                Operation::Update { .. } | Operation::Downgrade { .. }
+            )),
+            count("removal", |op| matches!(op, Operation::Uninstall(_))),
+        )
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Display for Transaction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.summary())?;
+
+        for operation in &self.operations {
+            write!(f, "\n  - {operation}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Operation {
+    /// The versions of an update, with references when the versions alone look the same
+    fn update_versions(from: &Package, to: &Package) -> (String, String) {
+        let versions = |display| {
+            (
+                full_pretty_version(from, &from.version, display),
+                full_pretty_version(to, &to.version, display),
+            )
+        };
+        let (from_version, to_version) = versions(ReferenceDisplay::SourceIfDev);
+
+        if from_version != to_version {
+            (from_version, to_version)
+        } else if source_reference(from) != source_reference(to) {
+            versions(ReferenceDisplay::Source)
+        } else if dist_reference(from) != dist_reference(to) {
+            versions(ReferenceDisplay::Dist)
+        } else {
+            (from_version, to_version)
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let version = |package: &Package| {
+            full_pretty_version(package, &package.version, ReferenceDisplay::SourceIfDev)
+        };
+
+        match self {
+            Operation::Install(package) => {
+                write!(f, "Installing {} ({})", package.name, version(package))
+            }
+            Operation::Update { from, to } | Operation::Downgrade { from, to } => {
+                let action = match self {
+                    Operation::Update { .. } => "Upgrading",
+                    _ => "Downgrading",
+                };
+                let (from_version, to_version) = Operation::update_versions(from, to);
+
+                write!(f, "{action} {} ({from_version} => {to_version})", to.name)
+            }
+            Operation::Uninstall(package) => {
+                write!(f, "Removing {} ({})", package.name, version(package))
+            }
+            Operation::MarkAliasInstalled { package, alias }
+            | Operation::MarkAliasUninstalled { package, alias } => {
+                let state = match self {
+                    Operation::MarkAliasInstalled { .. } => "installed",
+                    _ => "uninstalled",
+                };
+
+                write!(
+                    f,
+                    "Marking {} ({}) as {state}, alias of {} ({})",
+                    package.name,
+                    full_pretty_version(package, alias, ReferenceDisplay::SourceIfDev),
+                    package.name,
+                    version(package)
+                )
+            }
+        }
+    }
+}
+
+/// An operation as written to JSON
+#[derive(Serialize)]
+#[serde(tag = "operation", rename_all = "kebab-case")]
+enum OperationJson<'a> {
+    Install {
+        package: &'a str,
+        version: String,
+    },
+    Update {
+        package: &'a str,
+        from: String,
+        to: String,
+    },
+    Downgrade {
+        package: &'a str,
+        from: String,
+        to: String,
+    },
+    Uninstall {
+        package: &'a str,
+        version: String,
+    },
+    MarkAliasInstalled {
+        package: &'a str,
+        version: String,
+        #[serde(rename = "alias-of")]
+        alias_of: String,
+    },
+    MarkAliasUninstalled {
+        package: &'a str,
+        version: String,
+        #[serde(rename = "alias-of")]
+        alias_of: String,
+    },
+}
+
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let version = |package: &Package, version: &str| {
+            full_pretty_version(package, version, ReferenceDisplay::SourceIfDev)
+        };
+
+        let json = match self {
+            Operation::Install(package) => OperationJson::Install {
+                package: &package.name,
+                version: version(package, &package.version),
+            },
+            Operation::Update { from, to } => {
+                let (from, to_version) = Operation::update_versions(from, to);
+
+                OperationJson::Update {
+                    package: &to.name,
+                    from,
+                    to: to_version,
+                }
+            }
+            Operation::Downgrade { from, to } => {
+                let (from, to_version) = Operation::update_versions(from, to);
+
+                OperationJson::Downgrade {
+                    package: &to.name,
+                    from,
+                    to: to_version,
+                }
+            }
+            Operation::Uninstall(package) => OperationJson::Uninstall {
+                package: &package.name,
+                version: version(package, &package.version),
+            },
+            Operation::MarkAliasInstalled { package, alias } => OperationJson::MarkAliasInstalled {
+                package: &package.name,
+                version: version(package, alias),
+                alias_of: version(package, &package.version),
+            },
+            Operation::MarkAliasUninstalled { package, alias } => {
+                OperationJson::MarkAliasUninstalled {
+                    package: &package.name,
+                    version: version(package, alias),
+                    alias_of: version(package, &package.version),
+                }
+            }
+        };
+
+        json.serialize(serializer)
+    }
+}
+
+/// The packages of the result and their aliases, sorted by name in reverse order with aliases
+/// before the package they alias, as Composer sorts them before walking the dependencies
+fn nodes<'a>(result: &[&'a Package], root_aliases: &[Alias]) -> Vec<(&'a Package, Option<String>)> {
+    let mut nodes = Vec::new();
+
+    for package in result {
+        let normalized = normalized_version(package);
+        let aliases = root_aliases
+            .iter()
+            .filter(|alias| alias.package.eq_ignore_ascii_case(&package.name))
+            .filter(|alias| alias.version == normalized)
+            .map(|alias| alias.alias.clone());
+
+        for alias in package
+            .branch_alias()
+            .map(|(alias, _)| alias)
+            .into_iter()
+            .chain(aliases)
+        {
+            nodes.push((*package, Some(alias)));
+        }
+
+        nodes.push((*package, None));
+    }
+
+    nodes.sort_by(|(a, a_alias), (b, b_alias)| {
+        b.name
+            .to_lowercase()
+            .cmp(&a.name.to_lowercase())
+            .then_with(|| match (a_alias, b_alias) {
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                _ => Ordering::Equal,
+            })
+    });
+
+    nodes
+}
+
+/// The order in which the nodes are processed, each after the packages it requires
+///
+/// Like Composer, the walk starts from the packages no other package requires. Packages only
+/// reachable through a cycle are processed last.
+fn dependency_order(nodes: &[(&Package, Option<String>)]) -> Vec<usize> {
+    let mut providers: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (index, (package, _)) in nodes.iter().enumerate() {
+        let replaced = package.replace.iter().flat_map(|names| names.iter());
+        let provided = package.provide.iter().flat_map(|names| names.iter());
+        let names = replaced
+            .chain(provided)
+            .map(|(name, _)| name)
+            .chain([package.name.clone()]);
+
+        for name in names {
+            providers
+                .entry(name.to_lowercase())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let requires = |index: usize| -> Vec<usize> {
+        nodes[index]
+            .0
+            .require
+            .iter()
+            .filter_map(|(name, _)| providers.get(&name.to_lowercase()))
+            .flatten()
+            .copied()
+            .collect()
+    };
+
+    let mut roots = vec![true; nodes.len()];
+
+    for index in 0..nodes.len() {
+        if !roots[index] {
+            continue;
+        }
+
+        for required in requires(index) {
+            if required != index {
+                roots[required] = false;
+            }
+        }
+    }
+
+    let mut stack: Vec<usize> = (0..nodes.len()).filter(|index| !roots[*index]).collect();
+    stack.extend((0..nodes.len()).filter(|index| roots[*index]));
+
+    let mut visited = HashSet::new();
+    let mut processed = HashSet::new();
+    let mut order = Vec::new();
+
+    while let Some(index) = stack.pop() {
+        if processed.contains(&index) {
+            continue;
+        }
+
+        if visited.insert(index) {
+            stack.push(index);
+
+            match &nodes[index] {
+                (package, Some(_)) => stack.extend(
+                    (0..nodes.len())
+                        .filter(|other| nodes[*other].1.is_none())
+                        .filter(|other| nodes[*other].0.name == package.name),
+                ),
+                (_, None) => stack.extend(requires(index)),
+            }
+        } else {
+            processed.insert(index);
+            order.push(index);
+        }
+    }
+
+    order
+}
+
+/// Moves the installs and updates of plugins, and of the packages they require, in front of the
+/// other operations, so they can act on those
+fn plugins_first(operations: Vec<Operation>) -> Vec<Operation> {
+    let mut plugins = Vec::new();
+    let mut plugin_requires: Vec<String> = Vec::new();
+    let mut others = Vec::new();
+
+    for operation in operations.into_iter().rev() {
+        let package = match &operation {
+            Operation::Install(package) => package,
+            Operation::Update { to, .. } | Operation::Downgrade { to, .. } => to,
+            _ => {
+                others.push(operation);
+                continue;
+            }
+        };
+
+        let is_plugin = matches!(
+            package.package_type.as_str(),
+            "composer-plugin" | "composer-installer"
+        );
+
+        if is_plugin || plugin_requires.contains(&package.name) {
+            plugin_requires.extend(
+                package
+                    .require
+                    .iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| !is_platform_package(name)),
+            );
+            plugins.push(operation);
+        } else {
+            others.push(operation);
+        }
+    }
+
+    plugins.reverse();
+    others.reverse();
+    plugins.extend(others);
+
+    plugins
+}
+
+fn normalized_version(package: &Package) -> String {
+    package
+        .version_normalized
+        .clone()
+        .or_else(|| normalize(&package.version).ok())
+        .unwrap_or_else(|| package.version.clone())
+}
+
+/// Whether going from a version to another is an upgrade, which it is between dev branches
+fn is_upgrade(from: &Package, to: &Package) -> bool {
+    let default_branch = |version: String| match version.as_str() {
+        "dev-master" | "dev-trunk" | "dev-default" => DEFAULT_BRANCH_ALIAS.to_string(),
+        _ => version,
+    };
+    let from = default_branch(normalized_version(from));
+    let to = default_branch(normalized_version(to));
+
+    from == to
+        || from.starts_with("dev-")
+        || to.starts_with("dev-")
+        || compare_versions(&from, &to) != Ordering::Greater
+}
+
+fn source_reference(package: &Package) -> Option<&str> {
+    package
+        .source
+        .as_ref()
+        .map(|source| source.reference.as_str())
+}
+
+fn dist_reference(package: &Package) -> Option<&str> {
+    package.dist.as_ref()?.reference.as_deref()
+}
+
+#[derive(Clone, Copy)]
+enum ReferenceDisplay {
+    /// The source reference of dev versions installed from git or hg
+    SourceIfDev,
+    Source,
+    Dist,
+}
+
+/// A version followed by the reference it is installed from, shortened to 7 characters for
+/// commit hashes, e.g. `dev-main 1a2b3c4`
+fn full_pretty_version(package: &Package, version: &str, display: ReferenceDisplay) -> String {
+    let source_type = package
+        .source
+        .as_ref()
+        .map(|source| source.source_type.as_str());
+
+    let reference = match display {
+        ReferenceDisplay::SourceIfDev
+            if Stability::of(version) != Stability::Dev
+                || !matches!(source_type, Some("git" | "hg")) =>
+        {
+            None
+        }
+        ReferenceDisplay::SourceIfDev | ReferenceDisplay::Source => source_reference(package),
+        ReferenceDisplay::Dist => dist_reference(package),
+    };
+
+    match reference {
+        Some(reference) if reference.len() == 40 && source_type != Some("svn") => {
+            format!("{version} {}", &reference[..7])
+        }
+        Some(reference) => format!("{version} {reference}"),
+        None => version.to_string(),
+    }
+}
+
+#[cfg(test)]
+fn test_package(name: &str, version: &str, require: &[&str]) -> Package {
+    let mut package = Package::new(name, version);
+    package.require = indexmap::IndexMap::from_iter(
+        require
+            .iter()
+            .map(|name| (name.to_string(), "*".to_string())),
+    )
+    .into();
+
+    package
+}
+
+#[test]
+fn test_transaction() {
+    use crate::lock_file::PackageSource;
+    use serde_json::Value;
+
+    let mut main = test_package("acme/framework", "dev-main", &["php", "acme/http"]);
+    main.source = Some(PackageSource {
+        source_type: "git".into(),
+        url: "https://github.com/acme/framework.git".into(),
+        reference: "1a2b3c4d5e6f1a2b3c4d5e6f1a2b3c4d5e6f1a2b".into(),
+    });
+    main.extra = Some(indexmap::IndexMap::from([(
+        "branch-alias".to_string(),
+        serde_json::json!({"dev-main": "2.x-dev"}),
+    )]));
+
+    let mut updated_main = main.clone();
+    updated_main.source.as_mut().unwrap().reference =
+        "9f8e7d6c5b4a9f8e7d6c5b4a9f8e7d6c5b4a9f8e".into();
+
+    let mut plugin = test_package(
+        "acme/plugin",
+        "1.0.0",
+        &["composer-plugin-api", "acme/util"],
+    );
+    plugin.package_type = "composer-plugin".into();
+
+    let present = [
+        main.clone(),
+        test_package("acme/http", "2.0.0", &["psr/log"]),
+        test_package("psr/log", "3.0.0", &[]),
+        test_package("acme/legacy", "1.0.0", &[]),
+        test_package("acme/old", "1.0.0", &[]),
+    ];
+    let result = [
+        updated_main,
+        test_package("acme/http", "1.5.0", &["psr/log", "acme/util"]),
+        test_package("psr/log", "3.0.0", &[]),
+        test_package("acme/util", "1.0.0", &[]),
+        plugin,
+        test_package("acme/app", "1.0.0", &["acme/framework"]),
+    ];
+    let root_aliases = [Alias {
+        package: "acme/http".into(),
+        version: "1.5.0.0".into(),
+        alias: "2.0.0".into(),
+        alias_normalized: "2.0.0.0".into(),
+    }];
+
+    let transaction = Transaction::new(
+        &present.iter().collect::<Vec<_>>(),
+        &result.iter().collect::<Vec<_>>(),
+        &root_aliases,
+    );
+
+    assert_eq!(
+        transaction.to_string(),
+        "Package operations: 3 installs, 2 updates, 2 removals
+  - Removing acme/old (1.0.0)
+  - Removing acme/legacy (1.0.0)
+  - Installing acme/util (1.0.0)
+  - Installing acme/plugin (1.0.0)
+  - Downgrading acme/http (2.0.0 => 1.5.0)
+  - Marking acme/http (2.0.0) as installed, alias of acme/http (1.5.0)
+  - Upgrading acme/framework (dev-main 1a2b3c4 => dev-main 9f8e7d6)
+  - Installing acme/app (1.0.0)"
+    );
+
+    let json: Value = serde_json::from_str(&transaction.to_json().unwrap()).unwrap();
+    assert_eq!(
+        json["operations"][5],
+        serde_json::json!({
+            "operation": "mark-alias-installed",
+            "package": "acme/http",
+            "version": "2.0.0",
+            "alias-of": "1.5.0"
+        })
+    );
+    assert_eq!(json["operations"][6]["from"], "dev-main 1a2b3c4");
+
+    let transaction = Transaction::new(&[&present[0]], &[], &[]);
+    assert_eq!(
+        transaction.to_string(),
+        "Package operations: 0 installs, 0 updates, 1 removal
+  - Removing acme/framework (dev-main 1a2b3c4)
+  - Marking acme/framework (2.x-dev 1a2b3c4) as uninstalled, alias of acme/framework (dev-main 1a2b3c4)"
+    );
+    assert_eq!(
+        Transaction::new(&[&present[2]], &[&present[2]], &[]).to_string(),
+        "Nothing to install, update or remove"
+    );
+}
@@ -0,0 +1,614 @@
+use crate::composer_json::ComposerJson;
+use crate::installer::{InstallError, InstallMethod, InstalledRepository};
+use crate::lock_file::{Alias, Package, PackageSource};
+use crate::php::php_array::PhpArray;
+use crate::repository::{git, guess_git_version};
+use crate::resolver::is_platform_package;
+use crate::semver::normalize;
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// The `Composer\InstalledVersions` class, which reads `installed.php` at runtime
+pub const INSTALLED_VERSIONS_CLASS: &str = include_str!("InstalledVersions.php");
+
+/// The version Composer gives a root package that has none and is not in a git checkout
+const DEFAULT_ROOT_VERSION: &str = "1.0.0+no-version-set";
+
+/// The root package of a project, versioned like Composer does it
+///
+/// The version is the `version` of the composer.json, else `COMPOSER_ROOT_VERSION`, else the
+/// branch or tag of the git checkout the project is in, with the commit as its reference, and
+/// `1.0.0+no-version-set` when all of them are missing.
+pub fn root_package(
+    composer_json: &ComposerJson,
+    project_dir: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Package {
+    let mut commit = None;
+    let version = composer_json
+        .version
+        .clone()
+        .or_else(|| env("COMPOSER_ROOT_VERSION").filter(|version| !version.is_empty()))
+        .or_else(|| {
+            let version = guess_git_version(project_dir)?;
+            commit = git("root", Some(project_dir), &["rev-parse", "HEAD"])
+                .ok()
+                .map(|commit| commit.trim().to_string());
+
+            Some(version)
+        });
+
+    let mut package = Package::new(
+        composer_json.name.as_deref().unwrap_or("__root__"),
+        version.as_deref().unwrap_or(DEFAULT_ROOT_VERSION),
+    );
+    package.version_normalized = match &version {
+        Some(version) => normalize(version).ok(),
+        None => Some("1.0.0.0".into()),
+    };
+    package.package_type = composer_json
+        .package_type
+        .clone()
+        .unwrap_or_else(|| "library".into());
+    package.replace = composer_json.replace.clone().map(PhpArray::from);
+    package.provide = composer_json.provide.clone().map(PhpArray::from);
+    package.extra = composer_json
+        .extra
+        .as_ref()
+        .and_then(Value::as_object)
+        .map(|extra| extra.clone().into_iter().collect());
+    package.source = commit.map(|reference| PackageSource {
+        source_type: String::new(),
+        url: String::new(),
+        reference,
+    });
+
+    package
+}
+
+/// Writes `installed.php` and the `InstalledVersions` class next to `installed.json`, leaving the
+/// files alone when they are up to date
+pub fn write_installed_versions(
+    vendor_dir: &Path,
+    installed: &InstalledRepository,
+    root: &Package,
+    root_aliases: &[Alias],
+    project_dir: &Path,
+) -> Result<(), InstallError> {
+    let dir = vendor_dir.join("composer");
+    let php = installed_php(installed, root, root_aliases, &dir, project_dir);
+
+    for (name, contents) in [
+        ("installed.php", php.as_str()),
+        ("InstalledVersions.php", INSTALLED_VERSIONS_CLASS),
+    ] {
+        let path = dir.join(name);
+
+        if std::fs::read(&path).is_ok_and(|current| current == contents.as_bytes()) {
+            continue;
+        }
+
+        std::fs::write(&path, contents)
+            .map_err(|error| InstallError::Io(path.display().to_string(), error))?;
+    }
+
+    Ok(())
+}
+
+/// The contents of `installed.php` in `dir`: the root package, then every installed, replaced
+/// and provided package by name with its versions, references, install paths and aliases
+pub fn installed_php(
+    installed: &InstalledRepository,
+    root: &Package,
+    root_aliases: &[Alias],
+    dir: &Path,
+    project_dir: &Path,
+) -> String {
+    let real_path = |path: &Path| {
+        path.canonicalize()
+            .or_else(|_| std::path::absolute(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    let root_path = shortest_path(&real_path(dir), &real_path(project_dir));
+    let dev_packages: HashSet<&str> = installed
+        .dev_package_names
+        .iter()
+        .map(String::as_str)
+        .collect();
+
+    let packages: Vec<(&Package, Option<&str>, Option<InstallMethod>)> = installed
+        .packages
+        .iter()
+        .map(|installed| {
+            (
+                &installed.package,
+                installed.install_path.as_deref(),
+                installed.installation_source,
+            )
+        })
+        .chain([(root, Some(root_path.as_str()), None)])
+        .collect();
+
+    let mut versions: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+
+    for (package, install_path, method) in &packages {
+        versions.insert(
+            package.name.clone(),
+            version_data(package, *install_path, *method, &dev_packages),
+        );
+    }
+
+    for (package, ..) in &packages {
+        let dev = dev_packages.contains(package.name.as_str());
+        let links = [
+            ("replaced", &package.replace),
+            ("provided", &package.provide),
+        ];
+
+        for (key, links) in links {
+            for (target, constraint) in links.iter().flat_map(|links| links.iter()) {
+                let target = target.to_lowercase();
+
+                if is_platform_package(&target) {
+                    continue;
+                }
+
+                let data = versions.entry(target).or_default();
+
+                if !data.contains_key("dev_requirement") || !dev {
+                    data.insert("dev_requirement".into(), Value::Bool(dev));
+                }
+
+                let constraint = match constraint.as_str() {
+                    "self.version" => package.version.clone(),
+                    constraint => constraint.to_string(),
+                };
+                let list = data.entry(key).or_insert_with(|| json!([]));
+
+                if let Some(list) = list.as_array_mut() {
+                    if !list.iter().any(|value| value == constraint.as_str()) {
+                        list.push(Value::String(constraint));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut root_data = version_data(root, Some(&root_path), None, &HashSet::new());
+
+    for (package, ..) in &packages {
+        let normalized = package
+            .version_normalized
+            .clone()
+            .or_else(|| normalize(&package.version).ok());
+        let inline_aliases = root_aliases
+            .iter()
+            .filter(|alias| alias.package.eq_ignore_ascii_case(&package.name))
+            .filter(|alias| Some(&alias.version) == normalized.as_ref())
+            .map(|alias| alias.alias.clone());
+
+        for alias in package
+            .branch_alias()
+            .map(|(alias, _)| alias)
+            .into_iter()
+            .chain(inline_aliases)
+        {
+            let aliases = versions
+                .entry(package.name.clone())
+                .or_default()
+                .entry("aliases")
+                .or_insert_with(|| json!([]));
+
+            if let Some(aliases) = aliases.as_array_mut() {
+                aliases.push(Value::String(alias.clone()));
+            }
+
+            if std::ptr::eq(*package, root) {
+                if let Some(Value::Array(aliases)) = root_data.get_mut("aliases") {
+                    aliases.push(Value::String(alias));
+                }
+            }
+        }
+    }
+
+    for data in versions.values_mut() {
+        for key in ["aliases", "replaced", "provided"] {
+            if let Some(Value::Array(list)) = data.get_mut(key) {
+                list.sort_by(|a, b| {
+                    natural_cmp(a.as_str().unwrap_or(""), b.as_str().unwrap_or(""))
+                });
+            }
+        }
+    }
+
+    let mut root_entry = Map::new();
+    root_entry.insert("name".into(), Value::String(root.name.clone()));
+    root_data.remove("dev_requirement");
+    root_entry.append(&mut root_data);
+    root_entry.insert("dev".into(), Value::Bool(installed.dev));
+
+    let data = json!({
+        "root": root_entry,
+        "versions": versions
+            .into_iter()
+            .map(|(name, data)| (name, Value::Object(data)))
+            .collect::<Map<String, Value>>(),
+    });
+
+    format!("<?php return {};\n", to_php(&data, 0))
+}
+
+/// What `installed.php` records of an installed package
+fn version_data(
+    package: &Package,
+    install_path: Option<&str>,
+    method: Option<InstallMethod>,
+    dev_packages: &HashSet<&str>,
+) -> Map<String, Value> {
+    let non_empty = |reference: Option<&String>| reference.filter(|r| !r.is_empty()).cloned();
+    let source_reference = non_empty(package.source.as_ref().map(|source| &source.reference));
+    let dist_reference = non_empty(
+        package
+            .dist
+            .as_ref()
+            .and_then(|dist| dist.reference.as_ref()),
+    );
+    let reference = match method {
+        Some(InstallMethod::Source) => source_reference.clone(),
+        Some(InstallMethod::Dist) => dist_reference.clone(),
+        None => None,
+    }
+    .or(source_reference)
+    .or(dist_reference);
+
+    let version = package
+        .version_normalized
+        .clone()
+        .or_else(|| normalize(&package.version).ok())
+        .unwrap_or_else(|| package.version.clone());
+
+    let Value::Object(data) = json!({
+        "pretty_version": package.version,
+        "version": version,
+        "reference": reference,
+        "type": package.package_type,
+        "install_path": install_path,
+        "aliases": [],
+        "dev_requirement": dev_packages.contains(package.name.as_str()),
+    }) else {
+        unreachable!("json! of an object is an object");
+    };
+
+    data
+}
+
+/// Dumps a value as PHP code like Composer does for `installed.php`, where the relative
+/// `install_path`s are made relative to `__DIR__`
+fn to_php(value: &Value, level: usize) -> String {
+    let entries: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (export_string(key), value))
+            .collect(),
+        Value::Array(list) => list
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut lines = String::from("array(\n");
+
+    for (key, value) in entries {
+        lines.push_str(&"    ".repeat(level + 1));
+        lines.push_str(&key);
+        lines.push_str(" => ");
+
+        match value {
+            Value::Object(_) | Value::Array(_) if is_empty(value) => lines.push_str("array(),\n"),
+            Value::Object(_) | Value::Array(_) => lines.push_str(&to_php(value, level + 1)),
+            Value::String(path) if key == "'install_path'" && !is_absolute(path) => lines.push_str(
+                &format!("__DIR__ . {},\n", export_string(&format!("/{path}"))),
+            ),
+            Value::String(string) => lines.push_str(&format!("{},\n", export_string(string))),
+            Value::Bool(bool) => lines.push_str(&format!("{bool},\n")),
+            _ => lines.push_str("null,\n"),
+        }
+    }
+
+    lines.push_str(&"    ".repeat(level));
+    lines.push(')');
+
+    if level > 0 {
+        lines.push_str(",\n");
+    }
+
+    lines
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.is_empty(),
+        Value::Array(list) => list.is_empty(),
+        _ => false,
+    }
+}
+
+/// A string as PHP's `var_export` writes it
+fn export_string(string: &str) -> String {
+    let escaped = string
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\0', "' . \"\\0\" . '");
+
+    format!("'{escaped}'")
+}
+
+/// Whether a path is absolute, like Composer checks it: rooted, a Windows drive, or a stream URL
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+
+    path.starts_with('/')
+        || bytes.get(1) == Some(&b':')
+        || path.contains("://")
+        || path.starts_with("\\\\")
+}
+
+/// The path from a directory to another, the way Composer's `findShortestPath` finds it for
+/// directories: relative when they share more than the root, and absolute otherwise
+fn shortest_path(from: &Path, to: &Path) -> String {
+    let normalize = |path: &Path| path.to_string_lossy().replace('\\', "/");
+    let from = format!("{}/dummy_file", normalize(from).trim_end_matches('/'));
+    let to = normalize(to).trim_end_matches('/').to_string();
+    let to = if to.is_empty() { "/".to_string() } else { to };
+    let dirname = |path: &str| match path.rfind('/') {
+        Some(0) => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+        None => ".".to_string(),
+    };
+
+    if dirname(&from) == to {
+        return "./".into();
+    }
+
+    let mut common = to.clone();
+
+    while !format!("{from}/").starts_with(&format!("{common}/")) && common != "/" {
+        common = dirname(&common);
+    }
+
+    if !from.starts_with(&common) {
+        return to;
+    }
+
+    let common = format!("{}/", common.trim_end_matches('/'));
+    let depth = from[common.len()..].matches('/').count();
+
+    // Top level directories like /app and /vendor are addressed absolutely, as in Docker setups
+    if common == "/" && depth > 1 {
+        return to;
+    }
+
+    let result = "../".repeat(depth) + to.get(common.len()..).unwrap_or("");
+
+    match result.is_empty() {
+        true => "./".into(),
+        false => result,
+    }
+}
+
+/// Compares strings in natural order like PHP's `SORT_NATURAL`, where `1.10` comes after `1.9`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |s: &[u8]| s.iter().take_while(|c| c.is_ascii_digit()).count();
+                let (x_len, y_len) = (digits(a), digits(b));
+                let trim = |s: &[u8]| {
+                    let zeros = s.iter().take_while(|c| **c == b'0').count();
+                    s[zeros..].to_vec()
+                };
+                let (x_digits, y_digits) = (trim(&a[..x_len]), trim(&b[..y_len]));
+                let ordering = x_digits
+                    .len()
+                    .cmp(&y_digits.len())
+                    .then_with(|| x_digits.cmp(&y_digits));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a = &a[x_len..];
+                b = &b[y_len..];
+            }
+            (Some(x), Some(y)) if x != y => return x.cmp(y),
+            _ => {
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+#[test]
+fn test_shortest_path() {
+    let path = |from: &str, to: &str| shortest_path(Path::new(from), Path::new(to));
+
+    assert_eq!(path("/app/vendor/composer", "/app"), "../../");
+    assert_eq!(
+        path("/app/vendor/composer", "/app/vendor/acme/lib"),
+        "../acme/lib"
+    );
+    assert_eq!(path("/app/vendor/composer", "/app/vendor"), "../");
+    assert_eq!(path("/app/vendor/composer", "/srv/lib"), "/srv/lib");
+    assert_eq!(path("/app/composer", "/lib"), "/lib");
+    assert_eq!(path("/app", "/lib"), "../lib");
+    assert_eq!(natural_cmp("1.10.x-dev", "1.9.x-dev"), Ordering::Greater);
+}
+
+#[test]
+fn test_installed_php() {
+    use crate::installer::InstalledPackage;
+    use crate::lock_file::PackageDist;
+
+    let dir = tempfile::tempdir().unwrap();
+    let vendor = dir.path().join("vendor");
+    std::fs::create_dir_all(vendor.join("composer")).unwrap();
+
+    let composer_json = ComposerJson::from_json_str(
+        r#"{"name": "acme/app", "version": "2.1.0", "replace": {"acme/legacy": "self.version"}}"#,
+    )
+    .unwrap();
+    let root = root_package(&composer_json, dir.path(), &|_| None);
+
+    let unversioned = ComposerJson::from_json_str(r#"{"name": "acme/app"}"#).unwrap();
+    let env = |name: &str| (name == "COMPOSER_ROOT_VERSION").then(|| "2.0.0".to_string());
+    assert_eq!(
+        root_package(&unversioned, dir.path(), &env).version,
+        "2.0.0"
+    );
+
+    let mut log = Package::new("psr/log", "3.0.0");
+    log.version_normalized = Some("3.0.0.0".into());
+    log.dist = Some(PackageDist {
+        dist_type: "zip".into(),
+        url: "https://example.com/log.zip".into(),
+        reference: Some("fe5ea303b0887d5caefd3d431c3e61ad47037001".into()),
+        shasum: Some(String::new()),
+    });
+    log.provide = Some(PhpArray::Associative(
+        [("psr/log-implementation".to_string(), "3.0.0".to_string())].into(),
+    ));
+
+    let mut framework = Package::new("acme/framework", "dev-main");
+    framework.source = Some(PackageSource {
+        source_type: "git".into(),
+        url: "https://example.com/framework.git".into(),
+        reference: "1a2b3c".into(),
+    });
+    framework.default_branch = Some(true);
+    framework.provide = Some(PhpArray::Associative(
+        [
+            ("psr/log-implementation".to_string(), "1.0|2.0".to_string()),
+            ("ext-json".to_string(), "*".to_string()),
+        ]
+        .into(),
+    ));
+
+    let mut meta = Package::new("acme/meta", "1.0.0");
+    meta.package_type = "metapackage".into();
+
+    let installed = InstalledRepository {
+        packages: vec![
+            InstalledPackage {
+                package: framework,
+                installation_source: Some(InstallMethod::Source),
+                install_path: Some("../acme/framework".into()),
+            },
+            InstalledPackage {
+                package: meta,
+                installation_source: None,
+                install_path: None,
+            },
+            InstalledPackage {
+                package: log,
+                installation_source: Some(InstallMethod::Dist),
+                install_path: Some("../psr/log".into()),
+            },
+        ],
+        dev: true,
+        dev_package_names: vec!["acme/meta".into()],
+    };
+    let aliases = [Alias {
+        package: "psr/log".into(),
+        version: "3.0.0.0".into(),
+        alias: "1.1.4".into(),
+        alias_normalized: "1.1.4.0".into(),
+    }];
+
+    write_installed_versions(&vendor, &installed, &root, &aliases, dir.path()).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(vendor.join("composer/installed.php")).unwrap(),
+        r#"<?php return array(
+    'root' => array(
+        'name' => 'acme/app',
+        'pretty_version' => '2.1.0',
+        'version' => '2.1.0.0',
+        'reference' => null,
+        'type' => 'library',
+        'install_path' => __DIR__ . '/../../',
+        'aliases' => array(),
+        'dev' => true,
+    ),
+    'versions' => array(
+        'acme/app' => array(
+            'pretty_version' => '2.1.0',
+            'version' => '2.1.0.0',
+            'reference' => null,
+            'type' => 'library',
+            'install_path' => __DIR__ . '/../../',
+            'aliases' => array(),
+            'dev_requirement' => false,
+        ),
+        'acme/framework' => array(
+            'pretty_version' => 'dev-main',
+            'version' => 'dev-main',
+            'reference' => '1a2b3c',
+            'type' => 'library',
+            'install_path' => __DIR__ . '/../acme/framework',
+            'aliases' => array(
+                0 => '9999999-dev',
+            ),
+            'dev_requirement' => false,
+        ),
+        'acme/legacy' => array(
+            'dev_requirement' => false,
+            'replaced' => array(
+                0 => '2.1.0',
+            ),
+        ),
+        'acme/meta' => array(
+            'pretty_version' => '1.0.0',
+            'version' => '1.0.0.0',
+            'reference' => null,
+            'type' => 'metapackage',
+            'install_path' => null,
+            'aliases' => array(),
+            'dev_requirement' => true,
+        ),
+        'psr/log' => array(
+            'pretty_version' => '3.0.0',
+            'version' => '3.0.0.0',
+            'reference' => 'fe5ea303b0887d5caefd3d431c3e61ad47037001',
+            'type' => 'library',
+            'install_path' => __DIR__ . '/../psr/log',
+            'aliases' => array(
+                0 => '1.1.4',
+            ),
+            'dev_requirement' => false,
+        ),
+        'psr/log-implementation' => array(
+            'dev_requirement' => false,
+            'provided' => array(
+                0 => '1.0|2.0',
+                1 => '3.0.0',
+            ),
+        ),
+    ),
+);
+"#
+    );
+    assert_eq!(
+        std::fs::read_to_string(vendor.join("composer/InstalledVersions.php")).unwrap(),
+        INSTALLED_VERSIONS_CLASS
+    );
+}
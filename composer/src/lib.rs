@@ -1,4 +1,14 @@
+pub mod auth;
+pub mod cache;
+pub mod composer_json;
 pub mod config;
 mod dirs;
+pub mod downloader;
+pub mod http;
+pub mod installer;
 pub mod lock_file;
-mod php;
+pub mod php;
+pub mod repository;
+pub mod resolver;
+pub mod secret;
+pub mod semver;
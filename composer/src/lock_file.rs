@@ -1,7 +1,10 @@
+use crate::composer_json::{to_pretty_json, Ordered};
 use crate::php::php_array::PhpArray;
+use crate::semver::stability::priorities;
+use crate::semver::{normalize_branch, numeric_alias_prefix, Stability};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
+use serde_json::{Map, Value};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
@@ -16,25 +19,70 @@ pub struct LockFile {
     pub packages: Vec<Package>,
     #[serde(rename = "packages-dev")]
     pub packages_dev: Vec<Package>,
-    pub aliases: PhpArray<String>,
+    pub aliases: Vec<Alias>,
     #[serde(rename = "minimum-stability")]
-    pub minimum_stability: String,
-    #[serde(rename = "stability-flags")]
-    #[serde(flatten)]
-    pub stability_flags: PhpArray<u8>,
+    pub minimum_stability: Stability,
+    #[serde(rename = "stability-flags", with = "priorities")]
+    pub stability_flags: PhpArray<Stability>,
     #[serde(rename = "prefer-stable")]
     pub prefer_stable: bool,
     #[serde(rename = "prefer-lowest")]
     pub prefer_lowest: bool,
-    #[serde(flatten)]
     pub platform: PhpArray<String>,
     #[serde(rename = "platform-dev")]
-    #[serde(flatten)]
     pub platform_dev: PhpArray<String>,
     #[serde(rename = "plugin-api-version")]
     pub plugin_api_version: String,
 }
 
+/// The alias of default branches without a branch alias of their own
+pub(crate) const DEFAULT_BRANCH_ALIAS: &str = "9999999-dev";
+
+/// The notice Composer puts at the top of every lock file
+const README: [&str; 3] = [
+    "This file locks the dependencies of your project to a known state",
+    "Read more about it at https://getcomposer.org/doc/01-basic-usage.md#installing-dependencies",
+    "This file is @generated automatically",
+];
+
+impl LockFile {
+    /// An empty lock file with Composer's defaults, to be filled with the resolved packages
+    pub fn new(content_hash: impl Into<String>) -> Self {
+        LockFile {
+            _readme: README.iter().map(|line| line.to_string()).collect(),
+            content_hash: content_hash.into(),
+            packages: Vec::new(),
+            packages_dev: Vec::new(),
+            aliases: Vec::new(),
+            minimum_stability: Stability::Stable,
+            stability_flags: PhpArray::default(),
+            prefer_stable: false,
+            prefer_lowest: false,
+            platform: PhpArray::default(),
+            platform_dev: PhpArray::default(),
+            plugin_api_version: "2.6.0".into(),
+        }
+    }
+
+    /// Serializes the lock file the way Composer writes it, like
+    /// [`ComposerJson::to_json_string`](crate::composer_json::ComposerJson::to_json_string)
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        let mut data = to_pretty_json(self)?;
+        data.push('\n');
+
+        Ok(data)
+    }
+}
+
+/// An inline alias from the root requirements, e.g. `dev-main as 1.0.x-dev`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alias {
+    pub package: String,
+    pub version: String,
+    pub alias: String,
+    pub alias_normalized: String,
+}
+
 impl TryInto<LockFile> for File {
     type Error = LockFileError;
 
@@ -81,36 +129,169 @@ impl Display for LockFileError {
 
 impl Error for LockFileError {}
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A package of a lock file, with its keys in the order Composer writes them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Package {
     pub name: String,
     pub version: String,
-    pub source: PackageSource,
-    pub dist: PackageDist,
+    #[serde(rename = "version_normalized", skip_serializing_if = "Option::is_none")]
+    pub version_normalized: Option<String>,
+    #[serde(rename = "target-dir", skip_serializing_if = "Option::is_none")]
+    pub target_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PackageSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dist: Option<PackageDist>,
+    #[serde(default, skip_serializing_if = "PhpArray::is_empty")]
     pub require: PhpArray<String>,
-    pub suggest: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub conflict: Option<PhpArray<String>>,
-    #[serde(rename = "require-dev")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provide: Option<PhpArray<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace: Option<PhpArray<String>>,
+    #[serde(rename = "require-dev", skip_serializing_if = "Option::is_none")]
     pub require_dev: Option<PhpArray<String>>,
-    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggest: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    /// Whether the version is the default branch of its repository, which is aliased as
+    /// `9999999-dev`
+    #[serde(rename = "default-branch", skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<Vec<String>>,
+    #[serde(rename = "type", default = "default_package_type")]
     pub package_type: String,
-    pub extra: Option<HashMap<String, Value>>,
-    pub autoload: Option<AutoloadConfig>,
-    #[serde(rename = "autoload-dev")]
-    pub autoload_dev: Option<AutoloadConfig>,
-    #[serde(rename = "notification-url")]
-    pub notification_url: String,
-    pub license: Vec<String>,
-    pub authors: Option<Vec<Author>>,
-    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<IndexMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoload: Option<Ordered<AutoloadConfig>>,
+    #[serde(rename = "autoload-dev", skip_serializing_if = "Option::is_none")]
+    pub autoload_dev: Option<Ordered<AutoloadConfig>>,
+    #[serde(rename = "notification-url", skip_serializing_if = "Option::is_none")]
+    pub notification_url: Option<String>,
+    #[serde(rename = "include-path", skip_serializing_if = "Option::is_none")]
+    pub include_path: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<Ordered<Author>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub keywords: Option<Vec<String>>,
-    pub support: Option<HashMap<String, String>>,
-    pub funding: Option<Vec<Funding>>,
-    pub time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support: Option<IndexMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funding: Option<Vec<Ordered<Funding>>>,
+    /// `true`, or the name of the package that replaces it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abandoned: Option<Value>,
+    /// How the package is installed from a path repository, i.e. `symlink` and `relative`
+    #[serde(rename = "transport-options", skip_serializing_if = "Option::is_none")]
+    pub transport_options: Option<Map<String, Value>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Package {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Package {
+            name: name.into(),
+            version: version.into(),
+            version_normalized: None,
+            target_dir: None,
+            source: None,
+            dist: None,
+            require: PhpArray::default(),
+            conflict: None,
+            provide: None,
+            replace: None,
+            require_dev: None,
+            suggest: None,
+            time: None,
+            default_branch: None,
+            bin: None,
+            package_type: default_package_type(),
+            extra: None,
+            autoload: None,
+            autoload_dev: None,
+            notification_url: None,
+            include_path: None,
+            license: None,
+            authors: None,
+            description: None,
+            homepage: None,
+            keywords: None,
+            support: None,
+            funding: None,
+            abandoned: None,
+            transport_options: None,
+        }
+    }
+
+    /// The alias of a dev version from the `branch-alias` of its `extra`, e.g. `2.1.x-dev` for
+    /// `dev-main`, or `9999999-dev` for the default branch when it has no branch alias, as pretty
+    /// and normalized version
+    ///
+    /// Like Composer, only aliases to numeric branches are used, and a numeric branch can only be
+    /// aliased to one of its own versions, e.g. `2.x-dev` to `2.1.x-dev`.
+    pub(crate) fn branch_alias(&self) -> Option<(String, String)> {
+        let version = &self.version;
+
+        if !version.starts_with("dev-") && !version.ends_with("-dev") {
+            return None;
+        }
+
+        let aliases = self
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("branch-alias")?.as_object());
+
+        for (branch, alias) in aliases.into_iter().flatten() {
+            let Some(prefix) = alias.as_str().and_then(|alias| alias.strip_suffix("-dev")) else {
+                continue;
+            };
+            let alias = format!("{prefix}-dev");
+            let normalized = match alias.as_str() {
+                DEFAULT_BRANCH_ALIAS => alias.clone(),
+                _ => normalize_branch(prefix),
+            };
+
+            if !normalized.ends_with("-dev") || !branch.eq_ignore_ascii_case(version) {
+                continue;
+            }
+
+            if let (Some(source), Some(target)) =
+                (numeric_alias_prefix(branch), numeric_alias_prefix(&alias))
+            {
+                if !target.starts_with(&source) {
+                    continue;
+                }
+            }
+
+            return Some((alias, normalized));
+        }
+
+        let numeric = numeric_alias_prefix(version.strip_prefix('v').unwrap_or(version));
+
+        match self.default_branch {
+            Some(true) if numeric.is_none() => Some((
+                DEFAULT_BRANCH_ALIAS.to_string(),
+                DEFAULT_BRANCH_ALIAS.to_string(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn default_package_type() -> String {
+    "library".into()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PackageSource {
     #[serde(rename = "type")]
     pub source_type: String,
@@ -118,47 +299,66 @@ pub struct PackageSource {
     pub reference: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PackageDist {
     #[serde(rename = "type")]
     pub dist_type: String,
     pub url: String,
-    pub reference: String,
-    pub shasum: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shasum: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum AutoloadPath {
     Single(String),
     Multiple(Vec<String>),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct AutoloadConfig {
-    pub files: Option<Vec<String>>,
+    #[serde(rename = "psr-4", skip_serializing_if = "Option::is_none")]
+    pub psr4: Option<IndexMap<String, AutoloadPath>>,
+    #[serde(rename = "psr-0", skip_serializing_if = "Option::is_none")]
+    pub psr0: Option<IndexMap<String, AutoloadPath>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub classmap: Option<Vec<String>>,
-    #[serde(rename = "psr-0")]
-    pub psr0: Option<HashMap<String, AutoloadPath>>,
-    #[serde(rename = "psr-4")]
-    pub psr4: Option<HashMap<String, AutoloadPath>>,
-    #[serde(rename = "exclude-from-classmap")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+    #[serde(
+        rename = "exclude-from-classmap",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub exclude_from_classmap: Option<Vec<String>>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Author {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Funding {
     pub url: String,
-    #[serde(rename = "type")]
-    pub funding_type: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub funding_type: Option<String>,
+    /// Keys that are not part of the schema
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 #[test]
@@ -168,5 +368,86 @@ fn test_simple_lock_file() {
         .try_into()
         .unwrap();
 
+    assert_eq!(lock_file.minimum_stability, Stability::Stable);
+    assert!(matches!(lock_file.stability_flags, PhpArray::Indexed(ref flags) if flags.is_empty()));
+
     dbg!(lock_file);
 }
+
+#[test]
+fn test_package_round_trip() {
+    let data = r#"{
+    "name": "acme/lib",
+    "version": "1.0.0",
+    "type": "library",
+    "autoload": {
+        "files": [
+            "src/functions.php"
+        ],
+        "psr-4": {
+            "Acme\\": "src/"
+        },
+        "custom": true
+    },
+    "authors": [
+        {
+            "email": "jane@example.com",
+            "name": "Jane Doe",
+            "twitter": "@jane"
+        }
+    ],
+    "funding": [
+        {
+            "url": "https://example.com/sponsor"
+        }
+    ]
+}"#;
+    let package: Package = serde_json::from_str(data).unwrap();
+
+    assert!(package.autoload.as_ref().unwrap().psr4.is_some());
+    assert_eq!(package.funding.as_ref().unwrap()[0].funding_type, None);
+    assert_eq!(
+        crate::composer_json::to_pretty_json(&package).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_branch_alias() {
+    let package = |version: &str, alias: Option<(&str, &str)>, default_branch: bool| {
+        let mut package = Package::new("acme/lib", version);
+        package.extra = alias.map(|(branch, alias)| {
+            IndexMap::from([(
+                "branch-alias".to_string(),
+                serde_json::json!({ branch: alias }),
+            )])
+        });
+        package.default_branch = Some(default_branch);
+        package
+    };
+
+    assert_eq!(
+        package("dev-main", Some(("dev-main", "2.1.x-dev")), true).branch_alias(),
+        Some(("2.1.x-dev".into(), "2.1.9999999.9999999-dev".into()))
+    );
+    assert_eq!(
+        package("2.x-dev", Some(("2.x-dev", "2.1.x-dev")), false).branch_alias(),
+        Some(("2.1.x-dev".into(), "2.1.9999999.9999999-dev".into()))
+    );
+    assert_eq!(
+        package("dev-main", None, true).branch_alias(),
+        Some(("9999999-dev".into(), "9999999-dev".into()))
+    );
+    assert_eq!(
+        package("1.x-dev", Some(("1.x-dev", "2.0.x-dev")), true).branch_alias(),
+        None
+    );
+    assert_eq!(
+        package("dev-main", Some(("dev-main", "next-dev")), false).branch_alias(),
+        None
+    );
+    assert_eq!(
+        package("1.0.0", Some(("1.0.0", "1.x-dev")), true).branch_alias(),
+        None
+    );
+}
@@ -1,5 +1,5 @@
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 /// An enum that represents an array in PHP
 /// Due to the way PHP arrays are typed, it can result in an array or object in the JSON
@@ -12,9 +12,54 @@ use std::collections::HashMap;
 /// ```
 ///
 /// Therefore it's not 100% predictable if a JSON key expecting to be have map is actually a map when empty
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PhpArray<TItem> {
     Indexed(Vec<TItem>),
-    Associative(HashMap<String, TItem>),
+    Associative(IndexMap<String, TItem>),
+}
+
+impl<TItem> PhpArray<TItem> {
+    /// Iterates over the keys and values like PHP's `foreach` does, using the position as key for
+    /// indexed arrays
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (String, &TItem)> + '_> {
+        match self {
+            PhpArray::Indexed(items) => Box::new(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| (index.to_string(), item)),
+            ),
+            PhpArray::Associative(items) => {
+                Box::new(items.iter().map(|(key, item)| (key.clone(), item)))
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            PhpArray::Indexed(items) => items.len(),
+            PhpArray::Associative(items) => items.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<TItem> Default for PhpArray<TItem> {
+    fn default() -> Self {
+        PhpArray::Indexed(Vec::new())
+    }
+}
+
+impl<TItem> From<IndexMap<String, TItem>> for PhpArray<TItem> {
+    /// Converts a map, which is written as `[]` when it's empty, just like PHP does
+    fn from(items: IndexMap<String, TItem>) -> Self {
+        match items.is_empty() {
+            true => PhpArray::Indexed(Vec::new()),
+            false => PhpArray::Associative(items),
+        }
+    }
 }
@@ -0,0 +1,253 @@
+use crate::lock_file::{Package, PackageDist};
+use crate::repository::{Repository, RepositoryError};
+use crate::semver::normalize;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A repository of `type: artifact`, which reads packages from a directory of zip and tar archives
+///
+/// Only the composer.json of each archive is read, either at the root of the archive or in its
+/// single top-level directory, so archives don't need to be extracted. Archives without a valid
+/// composer.json are skipped.
+pub struct ArtifactRepository {
+    url: String,
+    project_dir: PathBuf,
+    packages: Mutex<Option<Vec<Package>>>,
+}
+
+impl ArtifactRepository {
+    /// An artifact repository, where a relative URL is relative to the project directory
+    pub fn new(url: impl Into<String>, project_dir: impl Into<PathBuf>) -> Self {
+        ArtifactRepository {
+            url: url.into(),
+            project_dir: project_dir.into(),
+            packages: Mutex::new(None),
+        }
+    }
+
+    /// All packages of the repository, loaded on first use
+    pub fn packages(&self) -> Result<Vec<Package>, RepositoryError> {
+        let mut packages = self.packages.lock().expect("packages lock");
+
+        if let Some(packages) = packages.as_ref() {
+            return Ok(packages.clone());
+        }
+
+        let url = self.url.trim_end_matches('/');
+        let mut files = Vec::new();
+        find_files(&self.project_dir.join(url), Path::new(url), &mut files)
+            .map_err(|error| RepositoryError::InvalidPath(self.url.clone(), error.to_string()))?;
+        files.sort();
+
+        let loaded: Vec<_> = files
+            .iter()
+            .filter_map(|(path, url)| load_package(path, url))
+            .collect();
+
+        *packages = Some(loaded.clone());
+
+        Ok(loaded)
+    }
+}
+
+impl Repository for ArtifactRepository {
+    fn name(&self) -> String {
+        format!("artifact repo ({})", self.url)
+    }
+
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+        Ok(self
+            .packages()?
+            .into_iter()
+            .filter(|package| package.name.eq_ignore_ascii_case(name))
+            .collect())
+    }
+}
+
+/// Collects the files in a directory and its subdirectories, with their path and their URL
+fn find_files(dir: &Path, url: &Path, files: &mut Vec<(PathBuf, String)>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let url = url.join(entry.file_name());
+
+        if path.is_dir() {
+            find_files(&path, &url, files)?;
+        } else if path.is_file() {
+            files.push((path, url.to_string_lossy().replace('\\', "/")));
+        }
+    }
+
+    Ok(())
+}
+
+fn load_package(path: &Path, url: &str) -> Option<Package> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let (dist_type, composer_json) = match extension.as_str() {
+        "zip" => ("zip", zip_composer_json(path)?),
+        "tar" | "gz" | "tgz" => ("tar", tar_composer_json(path, extension != "tar")?),
+        _ => return None,
+    };
+
+    let Value::Object(mut data) = serde_json::from_str(&composer_json).ok()? else {
+        return None;
+    };
+
+    let version = data.get("version")?.as_str()?;
+    let normalized = normalize(version).ok()?;
+    data.insert("version_normalized".into(), Value::String(normalized));
+    data.remove("source");
+
+    let mut package: Package = serde_json::from_value(Value::Object(data)).ok()?;
+    package.dist = Some(PackageDist {
+        dist_type: dist_type.into(),
+        url: url.into(),
+        reference: None,
+        shasum: Some(sha1_file(path).ok()?),
+    });
+
+    Some(package)
+}
+
+/// Picks the composer.json at the root of an archive, or in its only top-level directory
+fn locate_composer_json<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut nested = Vec::new();
+
+    for name in names {
+        let name = name.trim_start_matches("./");
+
+        match name.split('/').collect::<Vec<_>>()[..] {
+            ["composer.json"] => return Some(name.to_string()),
+            [_, "composer.json"] => nested.push(name.to_string()),
+            _ => {}
+        }
+    }
+
+    match nested.len() {
+        1 => nested.pop(),
+        _ => None,
+    }
+}
+
+fn zip_composer_json(path: &Path) -> Option<String> {
+    let mut archive = zip::ZipArchive::new(File::open(path).ok()?).ok()?;
+    let name = locate_composer_json(archive.file_names())?;
+
+    let mut data = String::new();
+    archive
+        .by_name(&name)
+        .ok()?
+        .read_to_string(&mut data)
+        .ok()?;
+
+    Some(data)
+}
+
+fn tar_composer_json(path: &Path, compressed: bool) -> Option<String> {
+    let open = || -> Option<tar::Archive<Box<dyn Read>>> {
+        let file = File::open(path).ok()?;
+        let reader: Box<dyn Read> = match compressed {
+            true => Box::new(GzDecoder::new(file)),
+            false => Box::new(file),
+        };
+
+        Some(tar::Archive::new(reader))
+    };
+
+    // Tar archives can't be read at random, so they are read twice: once to list the entries and
+    // once to read the composer.json, without extracting anything
+    let names: Vec<String> = open()?
+        .entries()
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path().ok()?.to_string_lossy().into_owned()))
+        .collect();
+    let name = locate_composer_json(names.iter().map(String::as_str))?;
+
+    let mut archive = open()?;
+    let mut entry = archive
+        .entries()
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| {
+            entry
+                .path()
+                .is_ok_and(|path| path.to_string_lossy().trim_start_matches("./") == name)
+        })?;
+
+    let mut data = String::new();
+    entry.read_to_string(&mut data).ok()?;
+
+    Some(data)
+}
+
+pub(crate) fn sha1_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[test]
+fn test_artifact_repository() {
+    use std::io::Write;
+
+    let project = tempfile::tempdir().unwrap();
+    let artifacts = project.path().join("artifacts");
+    std::fs::create_dir_all(artifacts.join("nested")).unwrap();
+
+    let mut zip = zip::ZipWriter::new(File::create(artifacts.join("lib-1.0.0.zip")).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("lib-1.0.0/composer.json", options).unwrap();
+    zip.write_all(br#"{"name": "acme/lib", "version": "1.0.0"}"#)
+        .unwrap();
+    zip.start_file("lib-1.0.0/src/vendor/composer.json", options)
+        .unwrap();
+    zip.write_all(b"{}").unwrap();
+    zip.finish().unwrap();
+
+    let file = File::create(artifacts.join("nested/lib-1.1.0.tar.gz")).unwrap();
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+    let data = br#"{"name": "acme/lib", "version": "1.1.0", "require": {"php": "^8.1"}}"#;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    tar.append_data(&mut header, "composer.json", &data[..])
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    std::fs::write(artifacts.join("README.md"), "Not a package").unwrap();
+    std::fs::write(artifacts.join("broken.zip"), "Not a zip").unwrap();
+
+    let repository = ArtifactRepository::new("artifacts", project.path());
+    let packages = repository.find_packages("acme/lib").unwrap();
+
+    assert_eq!(packages.len(), 2);
+    assert_eq!(packages[0].version, "1.0.0");
+    assert_eq!(packages[1].version, "1.1.0");
+    assert_eq!(packages[1].require.len(), 1);
+
+    let dist = packages[0].dist.as_ref().unwrap();
+    assert_eq!(dist.dist_type, "zip");
+    assert_eq!(dist.url, "artifacts/lib-1.0.0.zip");
+    assert_eq!(
+        dist.shasum.as_deref(),
+        Some(
+            sha1_file(&artifacts.join("lib-1.0.0.zip"))
+                .unwrap()
+                .as_str()
+        )
+    );
+
+    let dist = packages[1].dist.as_ref().unwrap();
+    assert_eq!(dist.dist_type, "tar");
+    assert_eq!(dist.url, "artifacts/nested/lib-1.1.0.tar.gz");
+}
@@ -0,0 +1,494 @@
+use crate::cache::Cache;
+use crate::http::{CacheValidators, Conditional, HttpDownloader, HttpError};
+use crate::lock_file::Package;
+use crate::repository::{matches_package_pattern, Repository, RepositoryError};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A repository of `type: composer`, like packagist.org or one generated by Satis
+///
+/// The `packages.json` at the root of the repository either lists the packages inline or points
+/// to the metadata of each package with a `metadata-url`, where `%package%` is replaced by the
+/// package name. Stable versions are loaded from `%package%.json` and dev versions from
+/// `%package%~dev.json`. The legacy `providers-url` of Composer 1 repositories is not supported.
+///
+/// With a cache, downloaded metadata is stored with its `Last-Modified` and `ETag` headers, which
+/// are sent back on the next run so unchanged metadata isn't downloaded again. When the network
+/// is disabled, the cached metadata is used as is.
+pub struct ComposerRepository {
+    url: String,
+    downloader: HttpDownloader,
+    cache: Option<Cache>,
+    root: Mutex<Option<Arc<RootData>>>,
+    packages: Mutex<HashMap<String, Vec<Package>>>,
+}
+
+/// The parts of `packages.json` that are needed to find packages
+#[derive(Debug, Default)]
+struct RootData {
+    metadata_url: Option<String>,
+    available_packages: Option<Vec<String>>,
+    available_package_patterns: Option<Vec<String>>,
+    notify_batch: Option<String>,
+    packages: HashMap<String, Vec<Package>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawRootData {
+    #[serde(default)]
+    packages: Value,
+    metadata_url: Option<String>,
+    available_packages: Option<Vec<String>>,
+    available_package_patterns: Option<Vec<String>>,
+    notify_batch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawMetadata {
+    #[serde(default)]
+    packages: Map<String, Value>,
+    minified: Option<String>,
+}
+
+impl ComposerRepository {
+    pub fn new(url: impl Into<String>, downloader: HttpDownloader) -> Self {
+        let url = url.into();
+        let url = url.trim_end_matches('/');
+
+        ComposerRepository {
+            url: url
+                .strip_suffix("/packages.json")
+                .unwrap_or(url)
+                .to_string(),
+            downloader,
+            cache: None,
+            root: Mutex::new(None),
+            packages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Caches the metadata in a directory of `cache-repo-dir` named after the repository URL
+    pub fn with_cache(mut self, cache_repo_dir: &Path, read_only: bool) -> Self {
+        self.cache = Some(Cache::for_repository(cache_repo_dir, &self.url, read_only));
+        self
+    }
+
+    /// Downloads a metadata file, or reads it from the cache when it didn't change or the network
+    /// is disabled
+    ///
+    /// Like Composer, the validators are stored in the cached JSON itself, as `last-modified` and
+    /// `etag` keys which are ignored when reading the metadata.
+    fn fetch(&self, url: &str, key: &str) -> Result<Vec<u8>, HttpError> {
+        let Some(cache) = &self.cache else {
+            return self.downloader.get(url);
+        };
+
+        let cached = cache.read(key);
+        let cached_json: Option<Map<String, Value>> = cached
+            .as_deref()
+            .and_then(|data| serde_json::from_slice(data).ok());
+        let validator = |key: &str| {
+            cached_json
+                .as_ref()
+                .and_then(|json| json.get(key))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let validators = CacheValidators {
+            last_modified: validator("last-modified"),
+            etag: validator("etag"),
+        };
+
+        match (self.downloader.get_if_modified(url, &validators), cached) {
+            (Ok(Conditional::NotModified), Some(cached)) => Ok(cached),
+            (Ok(Conditional::NotModified), None) => Err(HttpError::Status(url.to_string(), 304)),
+            (Ok(Conditional::Modified(data, validators)), _) => {
+                match serde_json::from_slice(&data) {
+                    Ok(Value::Object(mut json)) => {
+                        let mut insert = |key: &str, value: Option<String>| {
+                            if let Some(value) = value {
+                                json.insert(key.into(), Value::String(value));
+                            }
+                        };
+                        insert("last-modified", validators.last_modified);
+                        insert("etag", validators.etag);
+
+                        cache.write(key, Value::Object(json).to_string().as_bytes());
+                    }
+                    _ => cache.write(key, &data),
+                }
+
+                Ok(data)
+            }
+            (Err(HttpError::Offline(_)), Some(cached)) => Ok(cached),
+            (Err(error), _) => Err(error),
+        }
+    }
+
+    fn root_data(&self) -> Result<Arc<RootData>, RepositoryError> {
+        let mut root = self.root.lock().expect("root data lock");
+
+        if let Some(data) = root.as_ref() {
+            return Ok(data.clone());
+        }
+
+        let url = match self.url.ends_with(".json") {
+            true => self.url.clone(),
+            false => format!("{}/packages.json", self.url),
+        };
+        let raw: RawRootData = serde_json::from_slice(&self.fetch(&url, "packages.json")?)
+            .map_err(|error| RepositoryError::InvalidMetadata(url.clone(), error))?;
+
+        let mut data = RootData {
+            metadata_url: raw.metadata_url.map(|url| self.canonicalize_url(&url)),
+            available_packages: raw.available_packages,
+            available_package_patterns: raw.available_package_patterns,
+            notify_batch: raw.notify_batch.map(|url| self.canonicalize_url(&url)),
+            packages: HashMap::new(),
+        };
+
+        // Inline packages are keyed by name and then by version, or an empty list if there are none
+        if let Value::Object(packages) = raw.packages {
+            for (name, versions) in packages {
+                let versions: Vec<Value> = match versions {
+                    Value::Object(versions) => versions.into_iter().map(|(_, v)| v).collect(),
+                    Value::Array(versions) => versions,
+                    _ => Vec::new(),
+                };
+
+                let packages = self.load_versions(&url, &name, versions, &data)?;
+                data.packages
+                    .entry(name.to_lowercase())
+                    .or_default()
+                    .extend(packages);
+            }
+        }
+
+        let data = Arc::new(data);
+        *root = Some(data.clone());
+
+        Ok(data)
+    }
+
+    /// Resolves URLs that are absolute paths against the host of the repository, like Composer
+    fn canonicalize_url(&self, url: &str) -> String {
+        if !url.starts_with('/') {
+            return url.to_string();
+        }
+
+        match self.url.find("://") {
+            Some(scheme) => {
+                let host_end = self.url[scheme + 3..]
+                    .find('/')
+                    .map(|index| scheme + 3 + index)
+                    .unwrap_or(self.url.len());
+
+                format!("{}{url}", &self.url[..host_end])
+            }
+            None => url.to_string(),
+        }
+    }
+
+    fn load_metadata(
+        &self,
+        url: &str,
+        key: &str,
+        name: &str,
+        root: &RootData,
+    ) -> Result<Vec<Package>, RepositoryError> {
+        let data = match self.fetch(url, key) {
+            Ok(data) => data,
+            Err(HttpError::NotFound(_)) => return Ok(Vec::new()),
+            Err(HttpError::Offline(_)) => {
+                return Err(RepositoryError::NotAvailableOffline(name.to_string()))
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut metadata: RawMetadata = serde_json::from_slice(&data)
+            .map_err(|error| RepositoryError::InvalidMetadata(url.to_string(), error))?;
+
+        let Some(Value::Array(versions)) = metadata.packages.remove(name) else {
+            return Ok(Vec::new());
+        };
+
+        let versions = match metadata.minified.as_deref() {
+            Some("composer/2.0") => expand(versions),
+            _ => versions,
+        };
+
+        self.load_versions(url, name, versions, root)
+    }
+
+    fn load_versions(
+        &self,
+        url: &str,
+        name: &str,
+        versions: Vec<Value>,
+        root: &RootData,
+    ) -> Result<Vec<Package>, RepositoryError> {
+        versions
+            .into_iter()
+            .map(|mut version| {
+                if let Value::Object(version) = &mut version {
+                    version
+                        .entry("name")
+                        .or_insert_with(|| Value::String(name.to_string()));
+                }
+
+                let mut package: Package = serde_json::from_value(version)
+                    .map_err(|error| RepositoryError::InvalidMetadata(url.to_string(), error))?;
+
+                if package.notification_url.is_none() {
+                    package.notification_url = root.notify_batch.clone();
+                }
+
+                Ok(package)
+            })
+            .collect()
+    }
+
+    /// Whether the repository can have the package, if it lists which packages it has
+    fn is_available(root: &RootData, name: &str) -> bool {
+        if root.available_packages.is_none() && root.available_package_patterns.is_none() {
+            return true;
+        }
+
+        let listed = root
+            .available_packages
+            .iter()
+            .flatten()
+            .any(|available| available.eq_ignore_ascii_case(name));
+        let matched = root
+            .available_package_patterns
+            .iter()
+            .flatten()
+            .any(|pattern| matches_package_pattern(pattern, name));
+
+        listed || matched
+    }
+}
+
+impl Repository for ComposerRepository {
+    fn name(&self) -> String {
+        format!("composer repo ({})", self.url)
+    }
+
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+        let name = name.to_lowercase();
+
+        if let Some(packages) = self.packages.lock().expect("packages lock").get(&name) {
+            return Ok(packages.clone());
+        }
+
+        let root = self.root_data()?;
+        let mut packages = root.packages.get(&name).cloned().unwrap_or_default();
+
+        if let Some(metadata_url) = &root.metadata_url {
+            if Self::is_available(&root, &name) {
+                let mut not_cached = Vec::new();
+
+                for file in [name.clone(), format!("{name}~dev")] {
+                    let url = metadata_url.replace("%package%", &file);
+                    let key = format!("provider-{}.json", file.replace('/', "~"));
+
+                    match self.load_metadata(&url, &key, &name, &root) {
+                        Ok(found) => packages.extend(found),
+                        Err(error @ RepositoryError::NotAvailableOffline(_)) => {
+                            not_cached.push(error)
+                        }
+                        Err(error) => return Err(error),
+                    }
+                }
+
+                // One of the files is never cached when it doesn't exist, e.g. for packages
+                // without dev versions
+                if not_cached.len() == 2 {
+                    return Err(not_cached.remove(0));
+                }
+            }
+        }
+
+        self.packages
+            .lock()
+            .expect("packages lock")
+            .insert(name, packages.clone());
+
+        Ok(packages)
+    }
+}
+
+/// Expands metadata minified by Composer's `MetadataMinifier`, where each version only lists the
+/// keys that changed since the previous one and removed keys are set to `__unset`
+fn expand(versions: Vec<Value>) -> Vec<Value> {
+    let mut expanded = Vec::with_capacity(versions.len());
+    let mut current = Map::new();
+
+    for version in versions {
+        let Value::Object(version) = version else {
+            continue;
+        };
+
+        for (key, value) in version {
+            match value {
+                Value::String(unset) if unset == "__unset" => {
+                    current.remove(&key);
+                }
+                value => {
+                    current.insert(key, value);
+                }
+            }
+        }
+
+        expanded.push(Value::Object(current.clone()));
+    }
+
+    expanded
+}
+
+#[test]
+fn test_composer_repository() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("file://{}", dir.path().display());
+
+    std::fs::create_dir_all(dir.path().join("p2/acme")).unwrap();
+    std::fs::write(
+        dir.path().join("packages.json"),
+        serde_json::json!({
+            "packages": {
+                "acme/inline": {
+                    "1.0.0": {"name": "acme/inline", "version": "1.0.0"}
+                }
+            },
+            "metadata-url": format!("{url}/p2/%package%.json"),
+            "available-package-patterns": ["acme/*"],
+            "notify-batch": "https://repo.example.com/downloads/"
+        })
+        .to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("p2/acme/lib.json"),
+        serde_json::json!({
+            "minified": "composer/2.0",
+            "packages": {
+                "acme/lib": [
+                    {
+                        "name": "acme/lib",
+                        "version": "2.0.0",
+                        "version_normalized": "2.0.0.0",
+                        "require": {"php": ">=8.1"},
+                        "description": "A library"
+                    },
+                    {
+                        "version": "1.0.0",
+                        "version_normalized": "1.0.0.0",
+                        "require": "__unset"
+                    }
+                ]
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("p2/acme/lib~dev.json"),
+        serde_json::json!({
+            "minified": "composer/2.0",
+            "packages": {
+                "acme/lib": [{"name": "acme/lib", "version": "dev-main"}]
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let repository = ComposerRepository::new(url, HttpDownloader::new());
+
+    let packages = repository.find_packages("acme/lib").unwrap();
+    let versions: Vec<_> = packages.iter().map(|p| p.version.as_str()).collect();
+    assert_eq!(versions, vec!["2.0.0", "1.0.0", "dev-main"]);
+    assert_eq!(packages[1].description.as_deref(), Some("A library"));
+    assert!(packages[1].require.is_empty());
+    assert_eq!(packages[0].require.len(), 1);
+    assert_eq!(
+        packages[0].notification_url.as_deref(),
+        Some("https://repo.example.com/downloads/")
+    );
+
+    assert_eq!(repository.find_packages("acme/inline").unwrap().len(), 1);
+    assert!(repository.find_packages("acme/missing").unwrap().is_empty());
+    assert!(repository.find_packages("other/lib").unwrap().is_empty());
+}
+
+#[test]
+fn test_metadata_cache() {
+    use crate::config::test_config;
+    use crate::http::test_server;
+
+    let packages_json = br#"{"packages": {}, "metadata-url": "/p2/%package%.json"}"#.to_vec();
+    let metadata = br#"{"packages": {"acme/lib": [{"version": "1.0.0"}]}}"#.to_vec();
+    let last_modified = "Mon, 02 Jan 2023 03:04:05 GMT".to_string();
+    let (url, server) = test_server(vec![
+        (200, vec![], packages_json.clone()),
+        (
+            200,
+            vec![("Last-Modified", last_modified.clone())],
+            metadata,
+        ),
+        (404, vec![], Vec::new()),
+        (200, vec![], packages_json),
+        (304, vec![], Vec::new()),
+        (404, vec![], Vec::new()),
+    ]);
+
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let config = test_config(
+        project.path(),
+        home.path(),
+        r#"{"config": {"secure-http": false}}"#,
+    );
+    let online = HttpDownloader::load(&config, &|_| None).unwrap();
+    let offline = online.clone().with_offline(true);
+
+    let versions = |http: &HttpDownloader| -> Vec<String> {
+        let repository =
+            ComposerRepository::new(&url, http.clone()).with_cache(&config.cache_repo_dir, false);
+        let packages = repository.find_packages("acme/lib").unwrap();
+
+        packages
+            .into_iter()
+            .map(|package| package.version)
+            .collect()
+    };
+
+    let repository = ComposerRepository::new(&url, offline.clone());
+    assert!(matches!(
+        repository.find_packages("acme/lib"),
+        Err(RepositoryError::Http(HttpError::Offline(_)))
+    ));
+
+    assert_eq!(versions(&online), vec!["1.0.0"]);
+    assert_eq!(versions(&online), vec!["1.0.0"]);
+    assert_eq!(versions(&offline), vec!["1.0.0"]);
+    assert!(matches!(
+        ComposerRepository::new(&url, offline.clone())
+            .with_cache(&config.cache_repo_dir, false)
+            .find_packages("acme/other"),
+        Err(RepositoryError::NotAvailableOffline(name)) if name == "acme/other"
+    ));
+
+    let cache = Cache::for_repository(&config.cache_repo_dir, &url, false);
+    let cached = String::from_utf8(cache.read("provider-acme~lib.json").unwrap()).unwrap();
+    assert!(cached.contains(&format!(r#""last-modified":"{last_modified}""#)));
+
+    let requests = server.join().unwrap();
+    assert!(requests[1].starts_with("GET /p2/acme/lib.json"));
+    assert!(!requests[1].contains("If-Modified-Since"));
+    assert!(requests[4].contains(&format!("If-Modified-Since: {last_modified}\r\n")));
+}
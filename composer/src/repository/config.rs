@@ -0,0 +1,273 @@
+use crate::composer_json::Repositories;
+use crate::repository::{matches_package_pattern, PathOptions, RepositoryError};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// The URL of packagist.org, which is used unless it is disabled
+pub const PACKAGIST_URL: &str = "https://repo.packagist.org";
+
+/// A repository as configured in the `repositories` of a composer.json
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryConfig {
+    /// The key of the repository when `repositories` is a map, used to disable it by name
+    pub name: Option<String>,
+    pub kind: RepositoryKind,
+    pub filter: RepositoryFilter,
+}
+
+/// Which packages are taken from a repository, to protect private packages from being shadowed by
+/// public ones with the same name
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RepositoryFilter {
+    /// When a package is found in a canonical repository, repositories with a lower priority are
+    /// not looked up for it
+    pub canonical: bool,
+    /// The package name patterns this repository is limited to, unless empty
+    pub only: Vec<String>,
+    /// The package name patterns ignored in this repository
+    pub exclude: Vec<String>,
+}
+
+impl Default for RepositoryFilter {
+    fn default() -> Self {
+        RepositoryFilter {
+            canonical: true,
+            only: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl RepositoryFilter {
+    /// Whether a package name may be taken from the repository
+    pub fn allows(&self, name: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| matches_package_pattern(pattern, name))
+        };
+
+        (self.only.is_empty() || matches(&self.only)) && !matches(&self.exclude)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RepositoryKind {
+    Composer {
+        url: String,
+    },
+    /// A version control repository, where `git`, `github`, `gitlab` and `bitbucket` are read as
+    /// git repositories too
+    #[serde(alias = "git", alias = "github", alias = "gitlab", alias = "bitbucket")]
+    Vcs {
+        url: String,
+    },
+    Path {
+        url: String,
+        #[serde(default)]
+        options: PathOptions,
+    },
+    Artifact {
+        url: String,
+    },
+    /// Packages defined inline, as one package or a list of packages
+    Package {
+        #[serde(rename = "package")]
+        packages: OneOrMany,
+    },
+    /// A repository type of Composer which isn't supported, such as `svn` or `hg`, so that a
+    /// project using one can still be read and the repository skipped
+    #[serde(skip_deserializing)]
+    Unsupported {
+        repository_type: String,
+        url: String,
+    },
+}
+
+/// The repository types of Composer which [`RepositoryKind`] doesn't support
+const UNSUPPORTED_TYPES: [&str; 4] = ["svn", "hg", "fossil", "perforce"];
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    Many(Vec<Value>),
+    One(Value),
+}
+
+impl OneOrMany {
+    pub fn into_vec(self) -> Vec<Value> {
+        match self {
+            OneOrMany::Many(values) => values,
+            OneOrMany::One(value) => vec![value],
+        }
+    }
+}
+
+impl RepositoryConfig {
+    fn packagist() -> Self {
+        RepositoryConfig {
+            name: Some("packagist.org".into()),
+            kind: RepositoryKind::Composer {
+                url: PACKAGIST_URL.into(),
+            },
+            filter: RepositoryFilter::default(),
+        }
+    }
+
+    pub(crate) fn is_packagist(&self) -> bool {
+        static PACKAGIST: OnceLock<Regex> = OnceLock::new();
+        let packagist = PACKAGIST.get_or_init(|| {
+            Regex::new(r"(?i)^https?://(?:[a-z0-9-.]+\.)?packagist\.org(/|$)").expect("valid regex")
+        });
+
+        matches!(&self.kind, RepositoryKind::Composer { url } if packagist.is_match(url))
+    }
+}
+
+/// Reads the `repositories` of a composer.json in order of priority, the first one being the most
+/// important, followed by packagist.org unless it is disabled
+///
+/// Like Composer, the default packagist.org repository is disabled by setting its name to `false`,
+/// either as key of the map or as `{"packagist.org": false}` in the list, and redefining
+/// packagist.org as a Composer repository replaces the default one.
+pub fn parse_repositories(
+    repositories: Option<&Repositories>,
+) -> Result<Vec<RepositoryConfig>, RepositoryError> {
+    let entries: Vec<(Option<String>, &Value)> = match repositories {
+        None => Vec::new(),
+        Some(Repositories::List(list)) => list.iter().map(|value| (None, value)).collect(),
+        Some(Repositories::Map(map)) => map
+            .iter()
+            .map(|(name, value)| (Some(name.clone()), value))
+            .collect(),
+    };
+
+    let mut configs = Vec::new();
+    let mut default_packagist = true;
+    let is_packagist_name = |name: &str| matches!(name, "packagist" | "packagist.org");
+
+    for (index, (name, value)) in entries.into_iter().enumerate() {
+        if let (Some(name), Value::Bool(false)) = (&name, value) {
+            default_packagist &= !is_packagist_name(name);
+            continue;
+        }
+
+        if let Value::Object(object) = value {
+            if let (1, Some((name, Value::Bool(false)))) = (object.len(), object.iter().next()) {
+                default_packagist &= !is_packagist_name(name);
+                continue;
+            }
+        }
+
+        let invalid = |error| {
+            RepositoryError::InvalidConfig(name.clone().unwrap_or(index.to_string()), error)
+        };
+        let kind = match value.get("type").and_then(Value::as_str) {
+            Some(repository_type) if UNSUPPORTED_TYPES.contains(&repository_type) => {
+                RepositoryKind::Unsupported {
+                    repository_type: repository_type.into(),
+                    url: value
+                        .get("url")
+                        .and_then(Value::as_str)
+                        .unwrap_or("")
+                        .into(),
+                }
+            }
+            _ => RepositoryKind::deserialize(value).map_err(invalid)?,
+        };
+        let filter = RepositoryFilter::deserialize(value).map_err(invalid)?;
+        let config = RepositoryConfig {
+            name: name.map(|name| match name.as_str() {
+                "packagist" => "packagist.org".into(),
+                _ => name,
+            }),
+            kind,
+            filter,
+        };
+
+        if config.is_packagist() {
+            default_packagist = false;
+        }
+
+        configs.push(config);
+    }
+
+    if default_packagist {
+        configs.push(RepositoryConfig::packagist());
+    }
+
+    Ok(configs)
+}
+
+#[test]
+fn test_parse_repositories() {
+    let parse = |json: &str| {
+        let repositories: Repositories = serde_json::from_str(json).unwrap();
+        parse_repositories(Some(&repositories)).unwrap()
+    };
+
+    let configs = parse(
+        r#"[
+            {"type": "vcs", "url": "https://example.com/private.git", "only": ["acme/*"]},
+            {"type": "package", "package": {"name": "acme/inline", "version": "1.0.0"}},
+            {"type": "path", "url": "packages/*", "options": {"symlink": false}},
+            {"packagist.org": false}
+        ]"#,
+    );
+    assert_eq!(configs.len(), 3);
+    assert!(
+        matches!(&configs[0].kind, RepositoryKind::Vcs { url } if url == "https://example.com/private.git")
+    );
+    assert!(configs[0].filter.allows("Acme/Private"));
+    assert!(!configs[0].filter.allows("other/package"));
+    assert!(matches!(&configs[1].kind, RepositoryKind::Package { .. }));
+    assert!(
+        matches!(&configs[2].kind, RepositoryKind::Path { options, .. } if options.symlink == Some(false))
+    );
+
+    let configs = parse(
+        r#"{
+            "private": {"type": "composer", "url": "https://repo.example.com"},
+            "artifacts": {"type": "artifact", "url": "artifacts/"}
+        }"#,
+    );
+    let names: Vec<_> = configs.iter().map(|c| c.name.as_deref().unwrap()).collect();
+    assert_eq!(names, vec!["private", "artifacts", "packagist.org"]);
+
+    let configs = parse(r#"{"packagist": false, "github": {"type": "github", "url": "x"}}"#);
+    assert_eq!(configs.len(), 1);
+
+    let configs = parse(
+        r#"[{"type": "composer", "url": "https://packagist.org", "canonical": false, "exclude": ["acme/*"]}]"#,
+    );
+    assert_eq!(configs.len(), 1);
+    assert!(!configs[0].filter.canonical);
+    assert!(!configs[0].filter.allows("acme/private"));
+
+    for name in ["packagist.org", "packagist"] {
+        let configs = parse(&format!(
+            r#"{{"{name}": {{"type": "composer", "url": "https://repo.packagist.org", "exclude": ["acme/*"]}}}}"#
+        ));
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].name.as_deref(), Some("packagist.org"));
+        assert!(!configs[0].filter.allows("acme/private"));
+        assert!(configs[0].filter.allows("psr/log"));
+    }
+
+    let configs = parse(r#"[{"type": "svn", "url": "https://svn.example.com/lib"}]"#);
+    assert!(matches!(
+        &configs[0].kind,
+        RepositoryKind::Unsupported { repository_type, url } if repository_type == "svn" && url == "https://svn.example.com/lib"
+    ));
+
+    let repositories: Repositories =
+        serde_json::from_str(r#"[{"type": "unknown", "url": "x"}]"#).unwrap();
+    assert!(matches!(
+        parse_repositories(Some(&repositories)),
+        Err(RepositoryError::InvalidConfig(..))
+    ));
+}
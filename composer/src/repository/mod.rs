@@ -0,0 +1,69 @@
+mod artifact;
+mod composer;
+mod config;
+mod package;
+mod path;
+mod set;
+pub(crate) mod vcs;
+
+pub(crate) use artifact::sha1_file;
+pub use artifact::ArtifactRepository;
+pub use composer::ComposerRepository;
+pub use config::{
+    parse_repositories, OneOrMany, RepositoryConfig, RepositoryFilter, RepositoryKind,
+    PACKAGIST_URL,
+};
+pub use package::PackageRepository;
+pub(crate) use path::guess_git_version;
+pub use path::{PathOptions, PathReference, PathRepository};
+pub use set::{DuplicatePackage, RepositorySet};
+pub(crate) use vcs::git::git;
+pub use vcs::{GitDriver, VcsDriver, VcsRepository};
+
+use crate::http::HttpError;
+use crate::lock_file::Package;
+use regex::Regex;
+use thiserror::Error;
+
+/// A source of packages, such as packagist.org or a private Composer repository
+pub trait Repository: Send + Sync {
+    /// How the repository is called in messages, usually its URL
+    fn name(&self) -> String;
+
+    /// All versions of a package that the repository has, none if it doesn't know the name
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError>;
+}
+
+#[derive(Error, Debug)]
+pub enum RepositoryError {
+    #[error("{0}")]
+    Http(HttpError),
+
+    #[error("Invalid repository {0} in composer.json: {1}")]
+    InvalidConfig(String, serde_json::Error),
+
+    #[error("Invalid package metadata in {0}: {1}")]
+    InvalidMetadata(String, serde_json::Error),
+
+    #[error("Failed to read git repository {0}: {1}")]
+    Git(String, String),
+
+    #[error("The url supplied for the path ({0}) repository is invalid: {1}")]
+    InvalidPath(String, String),
+
+    #[error("The metadata of {0} is not available offline, it was never downloaded")]
+    NotAvailableOffline(String),
+}
+
+impl From<HttpError> for RepositoryError {
+    fn from(value: HttpError) -> Self {
+        RepositoryError::Http(value)
+    }
+}
+
+/// Whether a package name matches a pattern like `acme/*`, case-insensitively
+pub(crate) fn matches_package_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = regex::escape(pattern).replace(r"\*", ".*");
+
+    Regex::new(&format!("(?i)^{pattern}$")).is_ok_and(|regex| regex.is_match(name))
+}
@@ -0,0 +1,50 @@
+use crate::lock_file::Package;
+use crate::repository::{Repository, RepositoryError};
+use crate::semver::normalize;
+use serde_json::Value;
+
+/// A repository of `type: package`, with the packages defined inline in composer.json
+pub struct PackageRepository {
+    packages: Vec<Package>,
+}
+
+impl PackageRepository {
+    /// Reads the package definitions, which need at least a name and a version
+    pub fn new(definitions: Vec<Value>) -> Result<Self, RepositoryError> {
+        let packages = definitions
+            .into_iter()
+            .map(|mut definition| {
+                let version = definition.get("version").and_then(Value::as_str);
+                let normalized = version.and_then(|version| normalize(version).ok());
+
+                if let (Value::Object(definition), Some(normalized)) = (&mut definition, normalized)
+                {
+                    definition
+                        .entry("version_normalized")
+                        .or_insert(Value::String(normalized));
+                }
+
+                serde_json::from_value(definition).map_err(|error| {
+                    RepositoryError::InvalidMetadata("inline package repository".into(), error)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(PackageRepository { packages })
+    }
+}
+
+impl Repository for PackageRepository {
+    fn name(&self) -> String {
+        "package repo".into()
+    }
+
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+        Ok(self
+            .packages
+            .iter()
+            .filter(|package| package.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,356 @@
+use crate::lock_file::{Package, PackageDist};
+use crate::repository::vcs::branch_version;
+use crate::repository::vcs::git::git;
+use crate::repository::{Repository, RepositoryError};
+use crate::semver::normalize;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The `options` of a path repository
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PathOptions {
+    /// Whether to symlink the package, copy it, or try to symlink and copy if that fails when
+    /// unset
+    pub symlink: Option<bool>,
+    /// Whether symlinks are relative to the install directory, which is the default
+    pub relative: Option<bool>,
+    pub reference: PathReference,
+    /// Versions to use for packages, by package name
+    pub versions: IndexMap<String, String>,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            symlink: None,
+            relative: None,
+            reference: PathReference::Auto,
+            versions: IndexMap::new(),
+        }
+    }
+}
+
+/// How the dist reference of a path package is computed, which decides when it is reinstalled
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PathReference {
+    /// The last commit when the package is a git checkout, otherwise the same as `config`
+    Auto,
+    /// A hash of the package's composer.json and the repository options
+    Config,
+    /// No reference, the package is never considered changed
+    None,
+}
+
+/// A repository of `type: path`, which reads packages from directories on the local filesystem
+///
+/// The URL can contain glob patterns like `packages/*`, every matching directory with a
+/// composer.json is a package. Packages without a `version` get the version of the git checkout
+/// they are in, the `COMPOSER_ROOT_VERSION` if they are part of the root package's checkout, or
+/// `dev-main`.
+pub struct PathRepository {
+    url: String,
+    options: PathOptions,
+    project_dir: PathBuf,
+    root_version: Option<String>,
+    packages: Mutex<Option<Vec<Package>>>,
+}
+
+impl PathRepository {
+    /// A path repository, where relative URLs are relative to the project directory
+    pub fn new(
+        url: impl Into<String>,
+        options: PathOptions,
+        project_dir: impl Into<PathBuf>,
+    ) -> Self {
+        PathRepository::load(url, options, project_dir, &|name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn load(
+        url: impl Into<String>,
+        options: PathOptions,
+        project_dir: impl Into<PathBuf>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Self {
+        PathRepository {
+            url: url.into(),
+            options,
+            project_dir: project_dir.into(),
+            root_version: env("COMPOSER_ROOT_VERSION").filter(|version| !version.is_empty()),
+            packages: Mutex::new(None),
+        }
+    }
+
+    /// All packages of the repository, loaded on first use
+    pub fn packages(&self) -> Result<Vec<Package>, RepositoryError> {
+        let mut packages = self.packages.lock().expect("packages lock");
+
+        if let Some(packages) = packages.as_ref() {
+            return Ok(packages.clone());
+        }
+
+        let mut loaded = Vec::new();
+
+        for url in self.expand_url()? {
+            let path = self.project_dir.join(&url);
+            let composer_json = path.join("composer.json");
+
+            let Ok(json) = std::fs::read_to_string(&composer_json) else {
+                continue;
+            };
+            let Value::Object(data) = serde_json::from_str(&json).map_err(|error| {
+                RepositoryError::InvalidMetadata(composer_json.display().to_string(), error)
+            })?
+            else {
+                continue;
+            };
+
+            loaded.push(self.load_package(data, &json, &url, &path)?);
+        }
+
+        *packages = Some(loaded.clone());
+
+        Ok(loaded)
+    }
+
+    /// The directories matching the URL, relative to the project directory if the URL is relative
+    fn expand_url(&self) -> Result<Vec<String>, RepositoryError> {
+        let url = self.url.trim_end_matches('/');
+        let pattern = self.project_dir.join(url);
+        let paths = glob::glob(&pattern.to_string_lossy()).map_err(|error| {
+            RepositoryError::InvalidPath(self.url.clone(), error.msg.to_string())
+        })?;
+
+        let mut urls: Vec<String> = paths
+            .filter_map(Result::ok)
+            .filter(|path| path.is_dir())
+            .map(|path| match Path::new(url).is_absolute() {
+                true => path.to_string_lossy().into_owned(),
+                false => path
+                    .strip_prefix(&self.project_dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .into_owned(),
+            })
+            .collect();
+        urls.sort();
+
+        if urls.is_empty() && !url.contains(['*', '?', '[']) {
+            return Err(RepositoryError::InvalidPath(
+                self.url.clone(),
+                "the path does not exist".into(),
+            ));
+        }
+
+        Ok(urls)
+    }
+
+    fn load_package(
+        &self,
+        mut data: Map<String, Value>,
+        json: &str,
+        url: &str,
+        path: &Path,
+    ) -> Result<Package, RepositoryError> {
+        let name = data.get("name").and_then(Value::as_str).map(String::from);
+
+        let version = match name
+            .as_ref()
+            .and_then(|name| self.options.versions.get(name))
+        {
+            Some(version) => Some(version.clone()),
+            None => data
+                .get("version")
+                .and_then(Value::as_str)
+                .map(String::from),
+        };
+        let version = version
+            .or_else(|| self.root_version_for(path))
+            .or_else(|| guess_git_version(path))
+            .unwrap_or_else(|| "dev-main".into());
+
+        if let Ok(normalized) = normalize(&version) {
+            data.insert("version_normalized".into(), Value::String(normalized));
+        }
+
+        data.insert("version".into(), Value::String(version));
+        data.remove("source");
+        data.remove("dist");
+
+        let mut package: Package =
+            serde_json::from_value(Value::Object(data)).map_err(|error| {
+                RepositoryError::InvalidMetadata(format!("{url}/composer.json"), error)
+            })?;
+
+        package.dist = Some(PackageDist {
+            dist_type: "path".into(),
+            url: url.to_string(),
+            reference: match self.options.reference {
+                PathReference::None => None,
+                PathReference::Auto => head_commit(path).or_else(|| Some(self.reference(json))),
+                PathReference::Config => Some(self.reference(json)),
+            },
+            shasum: None,
+        });
+
+        // Only the options that were set are kept, like Composer does
+        let transport_options: Map<String, Value> = [
+            ("symlink", self.options.symlink),
+            ("relative", self.options.relative),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), Value::Bool(value?))))
+        .collect();
+
+        if !transport_options.is_empty() {
+            package.transport_options = Some(transport_options);
+        }
+
+        Ok(package)
+    }
+
+    /// The root version applies to packages in the same checkout as the root package
+    fn root_version_for(&self, path: &Path) -> Option<String> {
+        let version = self.root_version.as_ref()?;
+        let head = |dir: &Path| git("path", Some(dir), &["rev-parse", "HEAD"]).ok();
+
+        match (head(path), head(&self.project_dir)) {
+            (Some(package), Some(root)) if package == root => Some(version.clone()),
+            _ => None,
+        }
+    }
+
+    /// A hash of the composer.json and options, so the package is reinstalled when they change
+    fn reference(&self, json: &str) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(json.as_bytes());
+        hasher.update(format!("{:?}", self.options).as_bytes());
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// The last commit of a package which is a git checkout of its own
+fn head_commit(path: &Path) -> Option<String> {
+    if !path.join(".git").is_dir() {
+        return None;
+    }
+
+    let args = ["log", "-n1", "--pretty=%H", "--no-show-signature"];
+    let commit = git("path", Some(path), &args).ok()?;
+
+    Some(commit.trim().to_string()).filter(|commit| !commit.is_empty())
+}
+
+impl Repository for PathRepository {
+    fn name(&self) -> String {
+        format!("path repo ({})", self.url)
+    }
+
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+        Ok(self
+            .packages()?
+            .into_iter()
+            .filter(|package| package.name.eq_ignore_ascii_case(name))
+            .collect())
+    }
+}
+
+/// The version of the git checkout a directory is in: the branch, or the tag of a detached head
+pub(crate) fn guess_git_version(path: &Path) -> Option<String> {
+    if let Ok(branch) = git(
+        "path",
+        Some(path),
+        &["symbolic-ref", "--short", "-q", "HEAD"],
+    ) {
+        return Some(branch_version(branch.trim()).0);
+    }
+
+    let tag = git(
+        "path",
+        Some(path),
+        &["describe", "--exact-match", "--tags", "HEAD"],
+    )
+    .ok()?;
+    let tag = tag.trim();
+
+    normalize(tag).ok().map(|_| tag.to_string())
+}
+
+#[test]
+fn test_path_repository() {
+    use crate::repository::vcs::git::{init_test_repository, test_git};
+
+    let project = tempfile::tempdir().unwrap();
+    let packages = project.path().join("packages");
+
+    for name in ["versioned", "checkout", "unversioned"] {
+        std::fs::create_dir_all(packages.join(name)).unwrap();
+    }
+    std::fs::create_dir_all(packages.join("no-composer-json")).unwrap();
+
+    std::fs::write(
+        packages.join("versioned/composer.json"),
+        r#"{"name": "acme/versioned", "version": "1.2.0"}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        packages.join("unversioned/composer.json"),
+        r#"{"name": "acme/unversioned"}"#,
+    )
+    .unwrap();
+    init_test_repository(
+        &packages.join("checkout"),
+        &[("composer.json", r#"{"name": "acme/checkout"}"#)],
+    );
+    test_git(
+        Some(&packages.join("checkout")),
+        &["checkout", "--quiet", "-b", "2.x"],
+    );
+    let head = test_git(Some(&packages.join("checkout")), &["rev-parse", "HEAD"]);
+
+    let options = PathOptions {
+        symlink: Some(false),
+        ..PathOptions::default()
+    };
+    let env = |name: &str| (name == "COMPOSER_ROOT_VERSION").then(|| "3.0.0".to_string());
+    let repository = PathRepository::load("packages/*", options, project.path(), &env);
+
+    let packages = repository.packages().unwrap();
+    let versions: Vec<_> = packages
+        .iter()
+        .map(|package| (package.name.as_str(), package.version.as_str()))
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            ("acme/checkout", "2.x-dev"),
+            ("acme/unversioned", "dev-main"),
+            ("acme/versioned", "1.2.0"),
+        ]
+    );
+
+    assert_eq!(
+        packages[0].dist.as_ref().unwrap().reference.as_deref(),
+        Some(head.trim())
+    );
+
+    let dist = packages[2].dist.as_ref().unwrap();
+    assert_eq!(dist.dist_type, "path");
+    assert_eq!(dist.url, "packages/versioned");
+    assert_eq!(dist.reference.as_ref().unwrap().len(), 40);
+    assert_eq!(
+        Value::from(packages[2].transport_options.clone()),
+        serde_json::json!({"symlink": false})
+    );
+
+    assert!(matches!(
+        PathRepository::new("missing", PathOptions::default(), project.path()).packages(),
+        Err(RepositoryError::InvalidPath(..))
+    ));
+}
@@ -0,0 +1,269 @@
+use crate::config::Config;
+use crate::http::HttpDownloader;
+use crate::lock_file::Package;
+use crate::repository::{
+    ArtifactRepository, ComposerRepository, PackageRepository, PathRepository, Repository,
+    RepositoryConfig, RepositoryError, RepositoryFilter, RepositoryKind, VcsRepository,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The repositories of a project, in order of priority
+///
+/// Like Composer, a package found in a canonical repository is never taken from a repository with a
+/// lower priority, so a public package can't shadow a private one with the same name.
+#[derive(Default)]
+pub struct RepositorySet {
+    repositories: Vec<(Box<dyn Repository>, RepositoryFilter)>,
+    duplicates: Mutex<BTreeMap<String, DuplicatePackage>>,
+}
+
+/// A package name found in several repositories, which all provide versions of it because the
+/// ones with a higher priority are not canonical
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicatePackage {
+    pub name: String,
+    /// The repositories the versions of the package are taken from
+    pub used: Vec<String>,
+}
+
+impl Display for DuplicatePackage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Package {} is available in several repositories ({}), versions from all of them are considered because they are not canonical.",
+            self.name,
+            self.used.join(", ")
+        )
+    }
+}
+
+impl RepositorySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a canonical repository with a lower priority than the ones added before
+    pub fn add(&mut self, repository: Box<dyn Repository>) {
+        self.add_filtered(repository, RepositoryFilter::default());
+    }
+
+    /// Adds a repository with a lower priority than the ones added before, which only provides the
+    /// packages allowed by the filter
+    pub fn add_filtered(&mut self, repository: Box<dyn Repository>, filter: RepositoryFilter) {
+        self.repositories.push((repository, filter));
+    }
+
+    /// Creates the repositories of the project from their configs, as returned by
+    /// [`parse_repositories`](crate::repository::parse_repositories), leaving out the unsupported
+    /// ones
+    pub fn from_configs(
+        configs: Vec<RepositoryConfig>,
+        config: &Config,
+        downloader: &HttpDownloader,
+    ) -> Result<Self, RepositoryError> {
+        let project_dir = config.composer_json.parent().unwrap_or(Path::new("."));
+        let mut set = RepositorySet::new();
+
+        for repository in configs {
+            let filter = repository.filter;
+            let repository: Box<dyn Repository> = match repository.kind {
+                RepositoryKind::Composer { url } => Box::new(
+                    ComposerRepository::new(url, downloader.clone())
+                        .with_cache(&config.cache_repo_dir, config.cache_read_only),
+                ),
+                RepositoryKind::Vcs { url } => {
+                    Box::new(VcsRepository::git(url, Some(&config.cache_vcs_dir))?)
+                }
+                RepositoryKind::Path { url, options } => {
+                    Box::new(PathRepository::new(url, options, project_dir))
+                }
+                RepositoryKind::Artifact { url } => {
+                    Box::new(ArtifactRepository::new(url, project_dir))
+                }
+                RepositoryKind::Package { packages } => {
+                    Box::new(PackageRepository::new(packages.into_vec())?)
+                }
+                RepositoryKind::Unsupported { .. } => continue,
+            };
+            set.add_filtered(repository, filter);
+        }
+
+        Ok(set)
+    }
+
+    /// All versions of a package from the repositories allowed to provide it, each with the
+    /// priority of its repository, where 0 is the highest
+    ///
+    /// The repositories after the first canonical repository containing the package are not
+    /// looked up at all, so the names of private packages are never sent to public repositories,
+    /// and a failing repository with a lower priority doesn't break their lookup.
+    pub fn find_packages(&self, name: &str) -> Result<Vec<(Package, usize)>, RepositoryError> {
+        let mut packages = Vec::new();
+        let mut used = Vec::new();
+
+        for (priority, (repository, filter)) in self.repositories.iter().enumerate() {
+            if !filter.allows(name) {
+                continue;
+            }
+
+            let found = repository.find_packages(name)?;
+
+            if found.is_empty() {
+                continue;
+            }
+
+            used.push(repository.name());
+            packages.extend(found.into_iter().map(|package| (package, priority)));
+
+            if filter.canonical {
+                break;
+            }
+        }
+
+        if used.len() > 1 {
+            let duplicate = DuplicatePackage {
+                name: name.to_lowercase(),
+                used,
+            };
+            self.duplicates
+                .lock()
+                .expect("duplicates lock")
+                .insert(duplicate.name.clone(), duplicate);
+        }
+
+        Ok(packages)
+    }
+
+    /// The packages found in several repositories so far, sorted by name
+    ///
+    /// Commands resolving dependencies with this set print these as warnings once the resolution
+    /// is done, like Composer.
+    pub fn duplicates(&self) -> Vec<DuplicatePackage> {
+        let duplicates = self.duplicates.lock().expect("duplicates lock");
+
+        duplicates.values().cloned().collect()
+    }
+}
+
+#[test]
+fn test_inline_packages_resolution() {
+    use crate::composer_json::ComposerJson;
+    use crate::config::test_config;
+    use crate::repository::parse_repositories;
+    use crate::resolver::{platform_packages, resolve, Request};
+
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let composer_json = r#"{
+        "require": {"php": ">=8.1", "acme/lib": "^1.0"},
+        "config": {"platform": {"php": "8.2.0"}},
+        "repositories": [
+            {"type": "package", "package": [
+                {
+                    "name": "acme/lib",
+                    "version": "1.0.0",
+                    "dist": {"type": "zip", "url": "https://example.com/lib-1.0.0.zip"},
+                    "require": {"acme/dep": "*"}
+                }
+            ]},
+            {"type": "package", "package": {"name": "acme/dep", "version": "2.0.0"}},
+            {"type": "package", "package": {
+                "name": "acme/lib",
+                "version": "1.0.0",
+                "dist": {"type": "zip", "url": "https://mirror.example.com/lib-1.0.0.zip"}
+            }},
+            {"packagist.org": false}
+        ]
+    }"#;
+    let config = test_config(project.path(), home.path(), composer_json);
+
+    let composer_json = ComposerJson::from_json_str(composer_json).unwrap();
+    let configs = parse_repositories(composer_json.repositories.as_ref()).unwrap();
+    let set = RepositorySet::from_configs(configs, &config, &HttpDownloader::new()).unwrap();
+
+    let request = Request::from_composer_json(&composer_json, platform_packages(&config)).unwrap();
+    let resolution = resolve(&request, &|name| set.find_packages(name)).unwrap();
+
+    let names: Vec<_> = resolution
+        .packages
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["acme/dep", "acme/lib"]);
+    assert_eq!(
+        resolution.packages[1].dist.as_ref().unwrap().url,
+        "https://example.com/lib-1.0.0.zip"
+    );
+    assert_eq!(resolution.packages[1].version, "1.0.0");
+}
+
+#[test]
+fn test_canonical_repositories() {
+    let repository = |json: &str| {
+        let definitions = serde_json::from_str(json).unwrap();
+        Box::new(PackageRepository::new(definitions).unwrap())
+    };
+    let filter = |json: &str| serde_json::from_str::<RepositoryFilter>(json).unwrap();
+
+    let mut set = RepositorySet::new();
+    set.add_filtered(
+        repository(r#"[{"name": "acme/private", "version": "1.0.0"}]"#),
+        filter(r#"{"only": ["acme/*"]}"#),
+    );
+    set.add_filtered(
+        repository(r#"[{"name": "acme/mirrored", "version": "1.0.0"}]"#),
+        filter(r#"{"canonical": false}"#),
+    );
+    set.add(repository(
+        r#"[
+            {"name": "acme/private", "version": "9.9.9"},
+            {"name": "acme/mirrored", "version": "1.1.0"},
+            {"name": "acme/excluded", "version": "1.0.0"}
+        ]"#,
+    ));
+    set.add_filtered(
+        repository(r#"[{"name": "acme/excluded", "version": "2.0.0"}]"#),
+        filter(r#"{"exclude": ["acme/excluded"]}"#),
+    );
+
+    // Only queried for packages no canonical repository has
+    struct Failing;
+
+    impl Repository for Failing {
+        fn name(&self) -> String {
+            "failing repo".into()
+        }
+
+        fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+            Err(RepositoryError::NotAvailableOffline(name.into()))
+        }
+    }
+
+    set.add(Box::new(Failing));
+
+    let versions = |name: &str| -> Vec<(String, usize)> {
+        let packages = set.find_packages(name).unwrap();
+        packages.into_iter().map(|(p, i)| (p.version, i)).collect()
+    };
+
+    assert_eq!(versions("acme/private"), vec![("1.0.0".into(), 0)]);
+    assert_eq!(
+        versions("acme/mirrored"),
+        vec![("1.0.0".into(), 1), ("1.1.0".into(), 2)]
+    );
+    assert_eq!(versions("acme/excluded"), vec![("1.0.0".into(), 2)]);
+
+    let duplicates = set.duplicates();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].name, "acme/mirrored");
+    assert_eq!(
+        duplicates[0].to_string(),
+        "Package acme/mirrored is available in several repositories (package repo, package repo), versions from all of them are considered because they are not canonical."
+    );
+
+    assert!(set.find_packages("acme/unknown").is_err());
+}
@@ -0,0 +1,261 @@
+use crate::lock_file::PackageSource;
+use crate::repository::vcs::VcsDriver;
+use crate::repository::RepositoryError;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Reads packages from a git repository with the git command line
+///
+/// Local repositories are read in place. Remote ones are mirrored to the VCS cache directory
+/// first, and the mirror is updated each time the driver is created.
+pub struct GitDriver {
+    url: String,
+    repo_dir: PathBuf,
+    cache_dir: Option<PathBuf>,
+}
+
+impl GitDriver {
+    /// Opens the repository, mirroring it into `cache_dir` when it is not local
+    ///
+    /// The composer.json of each commit is cached in `cache_dir` as well, as commits never change.
+    pub fn open(url: &str, cache_dir: Option<&Path>) -> Result<Self, RepositoryError> {
+        let key = cache_key(url);
+        let local = url.strip_prefix("file://").unwrap_or(url);
+
+        let repo_dir = match Path::new(local).is_dir() {
+            true => PathBuf::from(local),
+            false => {
+                let Some(cache_dir) = cache_dir else {
+                    return Err(RepositoryError::Git(
+                        url.to_string(),
+                        "remote repositories can only be read with a cache directory".into(),
+                    ));
+                };
+
+                let mirror = cache_dir.join(&key);
+                mirror_repository(url, &mirror)?;
+
+                mirror
+            }
+        };
+
+        Ok(GitDriver {
+            url: url.to_string(),
+            repo_dir,
+            cache_dir: cache_dir.map(|dir| dir.join(format!("{key}.composer"))),
+        })
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, RepositoryError> {
+        git(&self.url, Some(&self.repo_dir), args)
+    }
+
+    /// The references matching a pattern, by short name
+    fn refs(&self, pattern: &str) -> Result<BTreeMap<String, String>, RepositoryError> {
+        let output = self.git(&[
+            "for-each-ref",
+            "--format=%(refname:short) %(objectname) %(*objectname)",
+            pattern,
+        ])?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split(' ');
+                let name = parts.next()?;
+                let object = parts.next()?;
+                // Annotated tags point to a tag object, the commit is the dereferenced object
+                let commit = parts.next().filter(|commit| !commit.is_empty());
+
+                Some((name.to_string(), commit.unwrap_or(object).to_string()))
+            })
+            .collect())
+    }
+
+    fn read_composer_json(&self, identifier: &str) -> Result<Option<Value>, RepositoryError> {
+        let Ok(data) = self.git(&["show", &format!("{identifier}:composer.json")]) else {
+            return Ok(None);
+        };
+        let Ok(Value::Object(mut composer_json)) = serde_json::from_str(&data) else {
+            return Ok(None);
+        };
+
+        if !composer_json.contains_key("time") {
+            let time = git_with_env(
+                &self.url,
+                Some(&self.repo_dir),
+                &[
+                    "log",
+                    "-1",
+                    "--format=%ad",
+                    "--date=format-local:%Y-%m-%dT%H:%M:%S+00:00",
+                    identifier,
+                ],
+                &[("TZ", "UTC")],
+            )?;
+
+            composer_json.insert("time".into(), Value::String(time.trim().to_string()));
+        }
+
+        Ok(Some(Value::Object(composer_json)))
+    }
+}
+
+impl VcsDriver for GitDriver {
+    fn root_identifier(&self) -> Result<String, RepositoryError> {
+        let head = self.git(&["symbolic-ref", "--short", "HEAD"]);
+
+        Ok(head.map_or("master".into(), |head| head.trim().to_string()))
+    }
+
+    fn tags(&self) -> Result<BTreeMap<String, String>, RepositoryError> {
+        self.refs("refs/tags")
+    }
+
+    fn branches(&self) -> Result<BTreeMap<String, String>, RepositoryError> {
+        self.refs("refs/heads")
+    }
+
+    fn composer_information(&self, identifier: &str) -> Result<Option<Value>, RepositoryError> {
+        let cache_file = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{identifier}.json")));
+
+        if let Some(data) = cache_file
+            .as_ref()
+            .and_then(|file| std::fs::read(file).ok())
+        {
+            if let Ok(composer_json) = serde_json::from_slice(&data) {
+                return Ok(composer_json);
+            }
+        }
+
+        let composer_json = self.read_composer_json(identifier)?;
+
+        // Caching is best effort, a missing cache only makes the next run slower
+        if let Some(file) = cache_file {
+            if let Some(dir) = file.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+
+            let _ = std::fs::write(file, Value::from(composer_json.clone()).to_string());
+        }
+
+        Ok(composer_json)
+    }
+
+    fn source(&self, identifier: &str) -> PackageSource {
+        PackageSource {
+            source_type: "git".into(),
+            url: self.url.clone(),
+            reference: identifier.into(),
+        }
+    }
+}
+
+fn mirror_repository(url: &str, mirror: &Path) -> Result<(), RepositoryError> {
+    if mirror.join("HEAD").is_file() {
+        return git(
+            url,
+            Some(mirror),
+            &["remote", "update", "--prune", "origin"],
+        )
+        .map(|_| ());
+    }
+
+    if let Some(parent) = mirror.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| RepositoryError::Git(url.to_string(), error.to_string()))?;
+    }
+
+    let mirror = mirror.to_string_lossy();
+
+    git(url, None, &["clone", "--mirror", "--", url, &mirror]).map(|_| ())
+}
+
+/// The name of a repository's directory in the cache, like Composer names it
+pub(crate) fn cache_key(url: &str) -> String {
+    url.chars()
+        .map(|char| match char.is_ascii_alphanumeric() || char == '.' {
+            true => char,
+            false => '-',
+        })
+        .collect()
+}
+
+pub(crate) fn git(url: &str, dir: Option<&Path>, args: &[&str]) -> Result<String, RepositoryError> {
+    git_with_env(url, dir, args, &[])
+}
+
+fn git_with_env(
+    url: &str,
+    dir: Option<&Path>,
+    args: &[&str],
+    env: &[(&str, &str)],
+) -> Result<String, RepositoryError> {
+    let mut command = Command::new("git");
+
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+
+    let output = command
+        .args(args)
+        .envs(env.iter().copied())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .map_err(|error| RepositoryError::Git(url.to_string(), error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(RepositoryError::Git(
+            url.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs git in a test repository, with a fixed identity and date so commits can be made
+#[cfg(test)]
+pub(crate) fn test_git(dir: Option<&Path>, args: &[&str]) -> String {
+    let identity = [
+        ("GIT_AUTHOR_NAME", "Test"),
+        ("GIT_AUTHOR_EMAIL", "test@example.com"),
+        ("GIT_AUTHOR_DATE", "2023-01-02T03:04:05+00:00"),
+        ("GIT_COMMITTER_NAME", "Test"),
+        ("GIT_COMMITTER_EMAIL", "test@example.com"),
+        ("GIT_COMMITTER_DATE", "2023-01-02T03:04:05+00:00"),
+    ];
+
+    git_with_env("test", dir, args, &identity).unwrap()
+}
+
+/// Creates a repository in `dir` with a first commit of the given files on `main`
+#[cfg(test)]
+pub(crate) fn init_test_repository(dir: &Path, files: &[(&str, &str)]) {
+    test_git(Some(dir), &["init", "--quiet", "--initial-branch=main"]);
+
+    for (name, content) in files {
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    commit_test_repository(dir, "Initial commit");
+}
+
+#[cfg(test)]
+pub(crate) fn commit_test_repository(dir: &Path, message: &str) {
+    test_git(Some(dir), &["add", "--all"]);
+    test_git(Some(dir), &["commit", "--quiet", "-m", message]);
+}
+
+#[test]
+fn test_cache_key() {
+    assert_eq!(
+        cache_key("https://github.com/acme/lib.git"),
+        "https---github.com-acme-lib.git"
+    );
+}
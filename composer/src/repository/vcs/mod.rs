@@ -0,0 +1,275 @@
+pub(crate) mod git;
+
+pub use git::GitDriver;
+
+use crate::lock_file::{Package, PackageSource};
+use crate::repository::{Repository, RepositoryError};
+use crate::semver::{normalize, normalize_branch};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Access to the references and files of a version control repository
+pub trait VcsDriver {
+    /// The name of the default branch
+    fn root_identifier(&self) -> Result<String, RepositoryError>;
+
+    /// The commits of all tags, by tag name
+    fn tags(&self) -> Result<BTreeMap<String, String>, RepositoryError>;
+
+    /// The commits of all branches, by branch name
+    fn branches(&self) -> Result<BTreeMap<String, String>, RepositoryError>;
+
+    /// The composer.json at a commit, if there is a valid one
+    fn composer_information(&self, identifier: &str) -> Result<Option<Value>, RepositoryError>;
+
+    fn source(&self, identifier: &str) -> PackageSource;
+}
+
+/// A repository of `type: vcs`, which reads a package from each tag and branch of a repository
+///
+/// Tags are versioned by their name, which must be a valid version, or by the `version` in their
+/// composer.json, which must then match the tag. Branches become `dev-*` versions, or `*.x-dev`
+/// for numeric branches, and keep their `extra.branch-alias` so they can be aliased.
+pub struct VcsRepository {
+    url: String,
+    driver: Box<dyn VcsDriver + Send + Sync>,
+    packages: Mutex<Option<Vec<Package>>>,
+}
+
+impl VcsRepository {
+    /// A repository read with git, caching remote repositories in `cache_vcs_dir`
+    pub fn git(
+        url: impl Into<String>,
+        cache_vcs_dir: Option<&Path>,
+    ) -> Result<Self, RepositoryError> {
+        let url = url.into();
+        let driver = GitDriver::open(&url, cache_vcs_dir)?;
+
+        Ok(Self::new(url, Box::new(driver)))
+    }
+
+    pub fn new(url: impl Into<String>, driver: Box<dyn VcsDriver + Send + Sync>) -> Self {
+        VcsRepository {
+            url: url.into(),
+            driver,
+            packages: Mutex::new(None),
+        }
+    }
+
+    /// All packages of the repository, loaded on first use
+    pub fn packages(&self) -> Result<Vec<Package>, RepositoryError> {
+        let mut packages = self.packages.lock().expect("packages lock");
+
+        if let Some(packages) = packages.as_ref() {
+            return Ok(packages.clone());
+        }
+
+        let mut loaded = Vec::new();
+
+        for (tag, identifier) in self.driver.tags()? {
+            loaded.extend(self.load_tag(&tag, &identifier)?);
+        }
+
+        let root = self.driver.root_identifier()?;
+
+        for (branch, identifier) in self.driver.branches()? {
+            loaded.extend(self.load_branch(&branch, &identifier, branch == root)?);
+        }
+
+        *packages = Some(loaded.clone());
+
+        Ok(loaded)
+    }
+
+    fn load_tag(&self, tag: &str, identifier: &str) -> Result<Option<Package>, RepositoryError> {
+        let Ok(tag_version) = normalize(tag) else {
+            return Ok(None);
+        };
+        let Some(Value::Object(mut data)) = self.driver.composer_information(identifier)? else {
+            return Ok(None);
+        };
+
+        let (version, normalized) = match data.get("version").and_then(Value::as_str) {
+            Some(version) => match normalize(version) {
+                Ok(normalized) => (version.to_string(), normalized),
+                Err(_) => return Ok(None),
+            },
+            None => (tag.to_string(), tag_version.clone()),
+        };
+
+        // Tags are releases, even if they are named like a dev version
+        let version = dev_suffix().replace(&version, "").into_owned();
+        let normalized = dev_marker().replace(&normalized, "").into_owned();
+
+        // The tag doesn't match the version in its composer.json
+        if normalized != tag_version {
+            return Ok(None);
+        }
+
+        data.insert("version".into(), Value::String(version));
+        data.insert("version_normalized".into(), Value::String(normalized));
+        data.remove("default-branch");
+
+        self.load_package(data, identifier)
+    }
+
+    fn load_branch(
+        &self,
+        branch: &str,
+        identifier: &str,
+        default: bool,
+    ) -> Result<Option<Package>, RepositoryError> {
+        let Some(Value::Object(mut data)) = self.driver.composer_information(identifier)? else {
+            return Ok(None);
+        };
+
+        let (version, normalized) = branch_version(branch);
+
+        data.insert("version".into(), Value::String(version));
+        data.insert("version_normalized".into(), Value::String(normalized));
+        data.remove("default-branch");
+
+        if default {
+            data.insert("default-branch".into(), Value::Bool(true));
+        }
+
+        self.load_package(data, identifier)
+    }
+
+    fn load_package(
+        &self,
+        mut data: serde_json::Map<String, Value>,
+        identifier: &str,
+    ) -> Result<Option<Package>, RepositoryError> {
+        if !data.get("name").is_some_and(Value::is_string) {
+            return Ok(None);
+        }
+
+        data.remove("source");
+        data.remove("dist");
+
+        let Ok(mut package) = serde_json::from_value::<Package>(Value::Object(data)) else {
+            return Ok(None);
+        };
+
+        package.source = Some(self.driver.source(identifier));
+
+        Ok(Some(package))
+    }
+}
+
+impl Repository for VcsRepository {
+    fn name(&self) -> String {
+        format!("vcs repo (git {})", self.url)
+    }
+
+    fn find_packages(&self, name: &str) -> Result<Vec<Package>, RepositoryError> {
+        Ok(self
+            .packages()?
+            .into_iter()
+            .filter(|package| package.name.eq_ignore_ascii_case(name))
+            .collect())
+    }
+}
+
+/// The pretty and normalized version of a branch, e.g. `dev-main` or `1.x-dev`
+pub(crate) fn branch_version(branch: &str) -> (String, String) {
+    let normalized = normalize_branch(branch);
+
+    match normalized.starts_with("dev-") {
+        true => (
+            format!("dev-{}", branch.replace('#', "+")),
+            normalized.replace('#', "+"),
+        ),
+        false => {
+            let prefix = if branch.starts_with('v') { "v" } else { "" };
+
+            (
+                format!("{prefix}{}", wildcards().replace(&normalized, ".x")),
+                normalized,
+            )
+        }
+    }
+}
+
+/// The `.9999999` parts that numeric branches are normalized to
+fn wildcards() -> &'static Regex {
+    static WILDCARDS: OnceLock<Regex> = OnceLock::new();
+
+    WILDCARDS.get_or_init(|| Regex::new(r"(\.9{7})+").expect("valid regex"))
+}
+
+fn dev_suffix() -> &'static Regex {
+    static DEV_SUFFIX: OnceLock<Regex> = OnceLock::new();
+
+    DEV_SUFFIX.get_or_init(|| Regex::new(r"(?i)[.-]?dev$").expect("valid regex"))
+}
+
+fn dev_marker() -> &'static Regex {
+    static DEV_MARKER: OnceLock<Regex> = OnceLock::new();
+
+    DEV_MARKER.get_or_init(|| Regex::new(r"(?i)(^dev-|[.-]?dev$)").expect("valid regex"))
+}
+
+#[test]
+fn test_git_repository() {
+    use crate::repository::vcs::git::{
+        cache_key, commit_test_repository, init_test_repository, test_git,
+    };
+
+    let work = tempfile::tempdir().unwrap();
+    let bare = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let dir = work.path();
+
+    init_test_repository(dir, &[("composer.json", r#"{"name": "acme/lib"}"#)]);
+    test_git(Some(dir), &["tag", "v1.0.0"]);
+    test_git(Some(dir), &["tag", "not-a-version"]);
+    test_git(Some(dir), &["branch", "1.x"]);
+    std::fs::write(
+        dir.join("composer.json"),
+        r#"{"name": "acme/lib", "version": "1.1.0", "extra": {"branch-alias": {"dev-main": "2.x-dev"}}}"#,
+    )
+    .unwrap();
+    commit_test_repository(dir, "Mismatching version");
+    test_git(Some(dir), &["tag", "-a", "v1.2.0", "-m", "Release"]);
+
+    let bare = bare.path().join("lib.git");
+    let url = bare.to_string_lossy().to_string();
+    test_git(
+        None,
+        &["clone", "--quiet", "--bare", &dir.to_string_lossy(), &url],
+    );
+
+    let repository = VcsRepository::git(&url, Some(cache.path())).unwrap();
+    let packages = repository.find_packages("acme/lib").unwrap();
+
+    let versions: Vec<_> = packages
+        .iter()
+        .map(|package| {
+            (
+                package.version.as_str(),
+                package.version_normalized.as_deref().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            ("v1.0.0", "1.0.0.0"),
+            ("1.x-dev", "1.9999999.9999999.9999999-dev"),
+            ("dev-main", "dev-main"),
+        ]
+    );
+
+    let main = &packages[2];
+    assert_eq!(main.time.as_deref(), Some("2023-01-02T03:04:05+00:00"));
+    assert_eq!(main.source.as_ref().unwrap().url, url);
+    assert!(main.extra.as_ref().unwrap().contains_key("branch-alias"));
+
+    let cached = cache.path().join(format!("{}.composer", cache_key(&url)));
+    assert_eq!(std::fs::read_dir(cached).unwrap().count(), 2);
+}
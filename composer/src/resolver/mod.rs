@@ -0,0 +1,551 @@
+mod platform;
+mod policy;
+mod pool;
+mod report;
+mod rules;
+mod selector;
+mod solver;
+
+pub use platform::platform_packages;
+pub use report::{ProblemReport, Report};
+pub use rules::{PackageRef, Rule, RuleLiteral, RuleReason, Unmatched};
+pub use selector::{find_best_candidate, recommended_constraint};
+
+use crate::composer_json::ComposerJson;
+use crate::lock_file::{Alias, LockFile, Package};
+use crate::repository::{matches_package_pattern, RepositoryError};
+use crate::resolver::policy::select_preferred;
+use crate::resolver::pool::{PackageId, PackageKind, Pool};
+use crate::resolver::rules::RuleSet;
+use crate::resolver::solver::Solver;
+use crate::semver::{split_constraints, split_inline_alias, SemverError, Stability};
+use indexmap::IndexMap;
+use regex::Regex;
+use std::collections::{HashSet, VecDeque};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Everything the resolver needs to know about the root package
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub name: String,
+    pub version: String,
+    pub require: IndexMap<String, String>,
+    pub require_dev: IndexMap<String, String>,
+    pub conflict: IndexMap<String, String>,
+    pub replace: IndexMap<String, String>,
+    pub provide: IndexMap<String, String>,
+    pub minimum_stability: Stability,
+    /// Per package overrides of the minimum stability, e.g. from `"vendor/package": "^1.0@beta"`
+    pub stability_flags: IndexMap<String, Stability>,
+    pub prefer_stable: bool,
+    pub prefer_lowest: bool,
+    /// The installed platform packages such as `php` or `ext-json`, with their version
+    pub platform: IndexMap<String, String>,
+    pub ignore_platform_reqs: IgnorePlatformReqs,
+    /// Packages that must stay at their currently locked version, when only some packages are
+    /// updated
+    pub locked: IndexMap<String, String>,
+}
+
+impl Default for Request {
+    fn default() -> Self {
+        Request {
+            name: "__root__".into(),
+            version: "1.0.0+no-version-set".into(),
+            require: IndexMap::new(),
+            require_dev: IndexMap::new(),
+            conflict: IndexMap::new(),
+            replace: IndexMap::new(),
+            provide: IndexMap::new(),
+            minimum_stability: Stability::Stable,
+            stability_flags: IndexMap::new(),
+            prefer_stable: false,
+            prefer_lowest: false,
+            platform: IndexMap::new(),
+            ignore_platform_reqs: IgnorePlatformReqs::None,
+            locked: IndexMap::new(),
+        }
+    }
+}
+
+impl Request {
+    /// The request of a root composer.json, resolved against the given platform packages, see
+    /// [`platform_packages`]
+    pub fn from_composer_json(
+        composer_json: &ComposerJson,
+        platform: IndexMap<String, String>,
+    ) -> Result<Self, ResolverError> {
+        let defaults = Request::default();
+        let minimum_stability = match &composer_json.minimum_stability {
+            Some(stability) => stability.parse().map_err(|error| {
+                ResolverError::InvalidRootPackage("minimum-stability".into(), error)
+            })?,
+            None => Stability::Stable,
+        };
+        let require = composer_json.require.clone().unwrap_or_default();
+        let require_dev = composer_json.require_dev.clone().unwrap_or_default();
+
+        Ok(Request {
+            name: composer_json.name.clone().unwrap_or(defaults.name),
+            version: composer_json.version.clone().unwrap_or(defaults.version),
+            stability_flags: extract_stability_flags(
+                require.iter().chain(&require_dev),
+                minimum_stability,
+            ),
+            require,
+            require_dev,
+            conflict: composer_json.conflict.clone().unwrap_or_default(),
+            replace: composer_json.replace.clone().unwrap_or_default(),
+            provide: composer_json.provide.clone().unwrap_or_default(),
+            minimum_stability,
+            prefer_stable: composer_json.prefer_stable.unwrap_or(false),
+            platform,
+            ..defaults
+        })
+    }
+
+    /// Whether a package of the given stability may be installed
+    fn is_acceptable(&self, name: &str, stability: Stability) -> bool {
+        match self.stability_flags.get(name) {
+            Some(flag) => stability.satisfies(*flag),
+            None => stability.satisfies(self.minimum_stability),
+        }
+    }
+}
+
+/// Finds the stability flags in root requirements, like Composer does: explicit `@stability`
+/// flags, and otherwise the stability of exact versions such as `1.0.0-beta2` or `dev-main` when
+/// they are less stable than the minimum stability
+fn extract_stability_flags<'a>(
+    requires: impl Iterator<Item = (&'a String, &'a String)>,
+    minimum_stability: Stability,
+) -> IndexMap<String, Stability> {
+    static FLAG: OnceLock<Regex> = OnceLock::new();
+    let flag = FLAG.get_or_init(|| {
+        Regex::new(r"(?i)^[^@]*?@(stable|RC|beta|alpha|dev)$").expect("valid regex")
+    });
+
+    let mut flags: IndexMap<String, Stability> = IndexMap::new();
+
+    for (name, constraint) in requires {
+        let name = name.to_lowercase();
+        let constraints = split_constraints(constraint);
+        let mut matched = false;
+
+        for constraint in &constraints {
+            let Some(stability) = flag
+                .captures(constraint)
+                .and_then(|captures| captures[1].parse::<Stability>().ok())
+            else {
+                continue;
+            };
+
+            // Multiple flags for the same package resolve to the least stable one
+            if flags
+                .get(&name)
+                .is_some_and(|existing| *existing > stability)
+            {
+                continue;
+            }
+
+            flags.insert(name.clone(), stability);
+            matched = true;
+        }
+
+        if matched {
+            continue;
+        }
+
+        for constraint in &constraints {
+            let version = split_inline_alias(constraint).0;
+
+            if version.contains([',', ' ', '@']) {
+                continue;
+            }
+
+            let stability = Stability::of(version);
+
+            if stability == Stability::Stable
+                || flags
+                    .get(&name)
+                    .is_some_and(|existing| *existing > stability)
+                || minimum_stability > stability
+            {
+                continue;
+            }
+
+            flags.insert(name.clone(), stability);
+        }
+    }
+
+    flags
+}
+
+/// Which platform requirements to leave out of the resolution
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum IgnorePlatformReqs {
+    #[default]
+    None,
+    All,
+    /// Names to ignore, where `*` is a wildcard, e.g. `ext-*`
+    Only(Vec<String>),
+}
+
+impl IgnorePlatformReqs {
+    pub fn ignores(&self, name: &str) -> bool {
+        if !is_platform_package(name) {
+            return false;
+        }
+
+        match self {
+            IgnorePlatformReqs::None => false,
+            IgnorePlatformReqs::All => true,
+            IgnorePlatformReqs::Only(patterns) => patterns
+                .iter()
+                .any(|pattern| matches_package_pattern(pattern, name)),
+        }
+    }
+}
+
+/// Whether a name refers to PHP itself, an extension, a library or Composer, rather than a package
+/// from a repository
+pub fn is_platform_package(name: &str) -> bool {
+    static PLATFORM: OnceLock<Regex> = OnceLock::new();
+
+    PLATFORM
+        .get_or_init(|| {
+            Regex::new(
+                r"(?i)^(?:php(?:-64bit|-ipv6|-zts|-debug)?|hhvm|(?:ext|lib)-[a-z0-9](?:[_.-]?[a-z0-9]+)*|composer(?:-(?:plugin|runtime)-api)?)$",
+            )
+            .expect("valid regex")
+        })
+        .is_match(name)
+}
+
+/// The packages to install, split like the `packages` and `packages-dev` of a lock file
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub packages: Vec<Package>,
+    pub packages_dev: Vec<Package>,
+    pub aliases: Vec<Alias>,
+}
+
+impl Resolution {
+    fn new(pool: &Pool, request: &Request, installed: &[bool]) -> Self {
+        // Packages are only dev packages when nothing outside require-dev needs them
+        let mut production = HashSet::new();
+        let mut queue: VecDeque<_> = pool.packages[Pool::ROOT]
+            .requires
+            .iter()
+            .filter(|link| {
+                request
+                    .require
+                    .keys()
+                    .any(|name| name.to_lowercase() == link.target)
+            })
+            .collect();
+
+        while let Some(link) = queue.pop_front() {
+            for id in pool.what_provides(&link.target, &link.constraint) {
+                let id = match pool.packages[id].kind {
+                    PackageKind::Alias { of, .. } => of,
+                    _ => id,
+                };
+
+                if installed[id] && production.insert(id) {
+                    queue.extend(&pool.packages[id].requires);
+                }
+            }
+        }
+
+        let mut packages = Vec::new();
+        let mut packages_dev = Vec::new();
+
+        for (id, package) in pool.packages.iter().enumerate() {
+            let PackageKind::Candidate(package) = &package.kind else {
+                continue;
+            };
+
+            if !installed[id] {
+                continue;
+            }
+
+            let mut package = package.as_ref().clone();
+            package.version_normalized = None;
+
+            match production.contains(&id) {
+                true => packages.push(package),
+                false => packages_dev.push(package),
+            }
+        }
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        packages_dev.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let aliases = pool
+            .root_aliases
+            .iter()
+            .filter(|alias| {
+                pool.packages.iter().enumerate().any(|(id, package)| {
+                    installed[id]
+                        && package.name == alias.package
+                        && package.version == alias.alias_normalized
+                })
+            })
+            .map(|alias| Alias {
+                package: alias.package.clone(),
+                version: alias.version.clone(),
+                alias: alias.alias.clone(),
+                alias_normalized: alias.alias_normalized.clone(),
+            })
+            .collect();
+
+        Resolution {
+            packages,
+            packages_dev,
+            aliases,
+        }
+    }
+
+    pub fn into_lock_file(self, request: &Request, content_hash: impl Into<String>) -> LockFile {
+        let platform = |requires: &IndexMap<String, String>| {
+            requires
+                .iter()
+                .filter(|(name, _)| is_platform_package(name))
+                .map(|(name, constraint)| (name.clone(), constraint.clone()))
+                .collect::<IndexMap<_, _>>()
+                .into()
+        };
+
+        let mut lock_file = LockFile::new(content_hash);
+        lock_file.packages = self.packages;
+        lock_file.packages_dev = self.packages_dev;
+        lock_file.aliases = self.aliases;
+        lock_file.minimum_stability = request.minimum_stability;
+        lock_file.stability_flags = request.stability_flags.clone().into();
+        lock_file.prefer_stable = request.prefer_stable;
+        lock_file.prefer_lowest = request.prefer_lowest;
+        lock_file.platform = platform(&request.require);
+        lock_file.platform_dev = platform(&request.require_dev);
+
+        lock_file
+    }
+}
+
+/// A set of rules that cannot all be fulfilled at the same time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Error, Debug)]
+pub enum ResolverError {
+    #[error("Invalid {0} in the root composer.json: {1}")]
+    InvalidRootPackage(String, SemverError),
+
+    #[error("{}", Report::new(.0))]
+    Unsatisfiable(Vec<Problem>),
+
+    #[error("{0}")]
+    Repository(RepositoryError),
+}
+
+impl From<RepositoryError> for ResolverError {
+    fn from(value: RepositoryError) -> Self {
+        ResolverError::Repository(value)
+    }
+}
+
+/// Returns all versions of a package name from the repositories, each with the priority of its
+/// repository
+pub type PackageProvider<'a> = dyn Fn(&str) -> Result<Vec<(Package, usize)>, RepositoryError> + 'a;
+
+/// Resolves the root requirements to the set of packages to install
+///
+/// The provider returns all versions of a package name from the repositories, each with the
+/// priority of its repository, where 0 is the highest, see
+/// [`RepositorySet::find_packages`](crate::repository::RepositorySet::find_packages). When the
+/// requirements cannot be fulfilled, the problems are collected by disabling one root requirement
+/// per problem and solving again, so every conflicting requirement is reported at once.
+pub fn resolve(request: &Request, provider: &PackageProvider) -> Result<Resolution, ResolverError> {
+    let pool = Pool::build(request, provider)?;
+    let mut disabled = Vec::new();
+    let mut problems = Vec::new();
+
+    loop {
+        let rules = RuleSet::generate(&pool, request, &disabled);
+        let mut solver = Solver::new(pool.packages.len());
+
+        for (index, (literals, _)) in rules.rules.iter().enumerate() {
+            solver.add_rule(literals.clone(), index);
+        }
+
+        let choose = |rule: usize, candidates: &[PackageId]| {
+            let target = match rules.rules.get(rule) {
+                Some((_, RuleReason::Requires { target, .. })) => Some(target.as_str()),
+                _ => None,
+            };
+
+            select_preferred(&pool, request, target, candidates)
+        };
+
+        match solver.solve(&choose) {
+            Ok(installed) if problems.is_empty() => {
+                return Ok(Resolution::new(&pool, request, &installed))
+            }
+            Ok(_) => break,
+            Err(indices) => {
+                let rules: Vec<_> = indices
+                    .into_iter()
+                    .map(|index| rules.rule(&pool, index))
+                    .collect();
+                let root_requirement = rules.iter().find_map(|rule| match &rule.reason {
+                    RuleReason::Requires {
+                        root: true, target, ..
+                    } if !disabled.contains(target) => Some(target.clone()),
+                    _ => None,
+                });
+
+                problems.push(Problem { rules });
+
+                match root_requirement {
+                    Some(target) => disabled.push(target),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    Err(ResolverError::Unsatisfiable(problems))
+}
+
+#[cfg(test)]
+fn repository(
+    packages: serde_json::Value,
+) -> impl Fn(&str) -> Result<Vec<(Package, usize)>, RepositoryError> {
+    let packages: Vec<Package> = serde_json::from_value(packages).unwrap();
+
+    move |name| {
+        Ok(packages
+            .iter()
+            .filter(|package| package.name == name)
+            .map(|package| (package.clone(), 0))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+fn resolved(packages: &[Package]) -> Vec<String> {
+    packages
+        .iter()
+        .map(|package| format!("{} {}", package.name, package.version))
+        .collect()
+}
+
+#[test]
+fn test_resolve() {
+    let provider = repository(serde_json::json!([
+        {"name": "a/a", "version": "1.0.0"},
+        {"name": "a/a", "version": "1.1.0", "require": {"b/b": "^2.0"}},
+        {"name": "a/a", "version": "1.2.0", "require": {"b/b": "^3.0"}},
+        {"name": "a/a", "version": "1.3.0-beta1"},
+        {"name": "a/a", "version": "2.0.0"},
+        {"name": "b/b", "version": "2.0.0"},
+        {"name": "b/b", "version": "2.1.0", "conflict": {"c/c": "<1.1"}},
+        {"name": "c/c", "version": "1.0.0"},
+        {"name": "c/c", "version": "1.1.0", "require": {"php": ">=8.1"}},
+        {"name": "d/d", "version": "dev-main", "extra": {"branch-alias": {"dev-main": "1.0.x-dev"}}},
+        {"name": "e/e", "version": "1.0.0", "replace": {"b/b": "self.version"}}
+    ]));
+
+    let mut request = Request::default();
+    request.require.insert("a/a".into(), "^1.0".into());
+    request.require.insert("php".into(), "^8.0".into());
+    request.require_dev.insert("c/c".into(), "^1.0".into());
+    request.platform.insert("php".into(), "8.0.30".into());
+
+    // a 1.2 needs b 3 which doesn't exist, c 1.1 needs a newer PHP so b 2.1 conflicts with c 1.0
+    let resolution = resolve(&request, &provider).unwrap();
+    assert_eq!(
+        resolved(&resolution.packages),
+        vec!["a/a 1.1.0", "b/b 2.0.0"]
+    );
+    assert_eq!(resolved(&resolution.packages_dev), vec!["c/c 1.0.0"]);
+
+    request.prefer_lowest = true;
+    let resolution = resolve(&request, &provider).unwrap();
+    assert_eq!(resolved(&resolution.packages), vec!["a/a 1.0.0"]);
+
+    request.prefer_lowest = false;
+    request.require.insert("a/a".into(), "^1.3@beta".into());
+    request
+        .stability_flags
+        .insert("a/a".into(), Stability::Beta);
+    let resolution = resolve(&request, &provider).unwrap();
+    assert_eq!(resolved(&resolution.packages), vec!["a/a 1.3.0-beta1"]);
+
+    request.require.insert("d/d".into(), "^1.0@dev".into());
+    request.stability_flags.insert("d/d".into(), Stability::Dev);
+    let lock_file = resolve(&request, &provider)
+        .unwrap()
+        .into_lock_file(&request, "hash");
+    assert_eq!(
+        resolved(&lock_file.packages),
+        vec!["a/a 1.3.0-beta1", "d/d dev-main"]
+    );
+    assert_eq!(lock_file.platform.len(), 1);
+}
+
+#[test]
+fn test_resolve_replace_and_aliases() {
+    let provider = repository(serde_json::json!([
+        {"name": "a/a", "version": "1.0.0", "require": {"b/b": "^1.0"}},
+        {"name": "b/b", "version": "1.0.0"},
+        {"name": "e/e", "version": "dev-main", "replace": {"b/b": "self.version"}}
+    ]));
+
+    // The inline alias makes the branch of e/e replace b/b 1.0.0
+    let composer_json =
+        ComposerJson::from_json_str(r#"{"require": {"a/a": "^1.0", "e/e": "dev-main as 1.0.0"}}"#)
+            .unwrap();
+    let request = Request::from_composer_json(&composer_json, IndexMap::new()).unwrap();
+    assert_eq!(request.stability_flags.get("e/e"), Some(&Stability::Dev));
+
+    let resolution = resolve(&request, &provider).unwrap();
+    assert_eq!(
+        resolved(&resolution.packages),
+        vec!["a/a 1.0.0", "e/e dev-main"]
+    );
+    assert_eq!(resolution.aliases[0].alias_normalized, "1.0.0.0");
+}
+
+#[test]
+fn test_unsatisfiable_requirements() {
+    let provider = repository(serde_json::json!([
+        {"name": "a/a", "version": "1.0.0", "require": {"ext-missing": "*"}},
+        {"name": "b/b", "version": "1.0.0"}
+    ]));
+
+    let mut request = Request::default();
+    request.require.insert("a/a".into(), "^1.0".into());
+    request.require.insert("b/b".into(), "^2.0".into());
+
+    let Err(ResolverError::Unsatisfiable(problems)) = resolve(&request, &provider) else {
+        panic!("requirements should not be satisfiable");
+    };
+
+    assert_eq!(problems.len(), 2);
+    assert!(problems.iter().all(|problem| problem
+        .rules
+        .iter()
+        .any(|rule| matches!(&rule.reason, RuleReason::Requires { root: true, .. }))));
+
+    request.ignore_platform_reqs = IgnorePlatformReqs::Only(vec!["ext-*".into()]);
+    request.require.insert("b/b".into(), "^1.0".into());
+    let resolution = resolve(&request, &provider).unwrap();
+    assert_eq!(
+        resolved(&resolution.packages),
+        vec!["a/a 1.0.0", "b/b 1.0.0"]
+    );
+}
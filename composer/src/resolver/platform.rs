@@ -0,0 +1,86 @@
+use crate::config::Config;
+use crate::semver::normalize;
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// The versions of the plugin and runtime APIs of the Composer release composer-rs mirrors
+const COMPOSER_PLUGIN_API: &str = "2.6.0";
+const COMPOSER_RUNTIME_API: &str = "2.2.2";
+
+/// Prints the PHP version and the loaded extensions as `name=version` lines
+const DETECT_PLATFORM: &str = r#"
+echo 'php=', PHP_VERSION, PHP_EOL;
+if (PHP_INT_SIZE === 8) {
+    echo 'php-64bit=', PHP_VERSION, PHP_EOL;
+}
+foreach (get_loaded_extensions() as $name) {
+    echo 'ext-', strtolower(str_replace(' ', '-', $name)), '=', phpversion($name) ?: '0', PHP_EOL;
+}
+"#;
+
+/// The platform packages requirements are resolved against: the `php` on the `PATH` with its
+/// extensions, the Composer APIs, and the `platform` config, which overrides or adds packages
+///
+/// Without a `php` binary, requirements on `php` or extensions can only be fulfilled through the
+/// `platform` config.
+pub fn platform_packages(config: &Config) -> IndexMap<String, String> {
+    let detected = Command::new("php")
+        .args(["-r", DETECT_PLATFORM])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+
+    with_overrides(&detected, config.platform.as_ref())
+}
+
+/// Reads the output of [`DETECT_PLATFORM`] and applies the `platform` config on top
+fn with_overrides(
+    detected: &str,
+    overrides: Option<&HashMap<String, String>>,
+) -> IndexMap<String, String> {
+    let mut packages = IndexMap::new();
+    packages.insert("composer-plugin-api".into(), COMPOSER_PLUGIN_API.into());
+    packages.insert("composer-runtime-api".into(), COMPOSER_RUNTIME_API.into());
+
+    for (name, version) in detected.lines().filter_map(|line| line.split_once('=')) {
+        // Like Composer, suffixes such as `-1ubuntu2` are dropped and versions it can't read are 0
+        let version = version.split(['~', '+', '-']).next().unwrap_or_default();
+        let version = match normalize(version) {
+            Ok(_) => version.to_string(),
+            Err(_) => "0".into(),
+        };
+
+        packages.insert(name.to_lowercase(), version);
+    }
+
+    let mut overrides: Vec<_> = overrides.into_iter().flatten().collect();
+    overrides.sort();
+
+    for (name, version) in overrides {
+        packages.insert(name.to_lowercase(), version.clone());
+    }
+
+    packages
+}
+
+#[test]
+fn test_with_overrides() {
+    let detected =
+        "php=8.1.2-1ubuntu2.14\nphp-64bit=8.1.2-1ubuntu2.14\next-json=8.1.2\next-weird=abc\n";
+    let overrides = HashMap::from([
+        ("php".to_string(), "8.2.0".to_string()),
+        ("ext-missing".to_string(), "1.0.0".to_string()),
+    ]);
+
+    let packages = with_overrides(detected, Some(&overrides));
+
+    assert_eq!(packages["php"], "8.2.0");
+    assert_eq!(packages["php-64bit"], "8.1.2");
+    assert_eq!(packages["ext-json"], "8.1.2");
+    assert_eq!(packages["ext-weird"], "0");
+    assert_eq!(packages["ext-missing"], "1.0.0");
+    assert_eq!(packages["composer-runtime-api"], "2.2.2");
+}
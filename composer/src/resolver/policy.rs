@@ -0,0 +1,65 @@
+use crate::resolver::pool::{PackageId, Pool, PoolPackage};
+use crate::resolver::Request;
+use crate::semver::compare_versions;
+use std::cmp::Ordering;
+
+/// Picks the package to install when a requirement can be fulfilled by several candidates
+///
+/// Like Composer, the best version of every name is chosen first: the most stable one when
+/// prefer-stable is set, then the highest version, or the lowest with prefer-lowest. Between
+/// names, the package that is actually named by the requirement wins over packages replacing or
+/// providing it, and then the one from the repository with the highest priority.
+pub(crate) fn select_preferred(
+    pool: &Pool,
+    request: &Request,
+    target: Option<&str>,
+    candidates: &[PackageId],
+) -> PackageId {
+    let mut best: Vec<PackageId> = Vec::new();
+
+    for candidate in candidates {
+        let package = &pool.packages[*candidate];
+
+        match best
+            .iter_mut()
+            .find(|id| pool.packages[**id].name == package.name)
+        {
+            Some(id) => {
+                if compare_candidates(request, package, &pool.packages[*id]) == Ordering::Less {
+                    *id = *candidate;
+                }
+            }
+            None => best.push(*candidate),
+        }
+    }
+
+    best.into_iter()
+        .min_by_key(|id| {
+            let package = &pool.packages[*id];
+
+            (
+                target.is_some_and(|target| target != package.name),
+                package.priority,
+                package.name.clone(),
+            )
+        })
+        .unwrap_or(candidates[0])
+}
+
+/// Orders two versions of the same package, the preferred one first
+fn compare_candidates(request: &Request, a: &PoolPackage, b: &PoolPackage) -> Ordering {
+    let stability = match request.prefer_stable {
+        true => a.stability.cmp(&b.stability),
+        false => Ordering::Equal,
+    };
+
+    let version = match request.prefer_lowest {
+        true => compare_versions(&a.version, &b.version),
+        false => compare_versions(&b.version, &a.version),
+    };
+
+    stability
+        .then(version)
+        .then(a.priority.cmp(&b.priority))
+        .then(b.is_alias().cmp(&a.is_alias()))
+}
@@ -0,0 +1,414 @@
+use crate::lock_file::Package;
+use crate::php::php_array::PhpArray;
+use crate::resolver::{is_platform_package, PackageProvider, Request, ResolverError};
+use crate::semver::{normalize, split_inline_alias, Constraint, SemverError, Stability};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub(crate) type PackageId = usize;
+
+/// A requirement, conflict, replacement or provision of a package
+#[derive(Debug, Clone)]
+pub(crate) struct Link {
+    pub target: String,
+    pub constraint: Constraint,
+    pub pretty_constraint: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum PackageKind {
+    Root,
+    Platform,
+    Candidate(Box<Package>),
+    /// A branch alias, or an inline alias from the root requirements when `root` is set
+    Alias {
+        of: PackageId,
+        root: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PoolPackage {
+    pub name: String,
+    pub version: String,
+    pub pretty_version: String,
+    pub stability: Stability,
+    pub requires: Vec<Link>,
+    pub conflicts: Vec<Link>,
+    pub replaces: Vec<Link>,
+    pub provides: Vec<Link>,
+    pub kind: PackageKind,
+    /// The position of the repository the package came from, lower is preferred
+    pub priority: usize,
+}
+
+impl PoolPackage {
+    pub fn is_alias(&self) -> bool {
+        matches!(self.kind, PackageKind::Alias { .. })
+    }
+}
+
+/// Why a version of a package was not added to the pool
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Filtered {
+    /// It is less stable than the minimum stability or the stability flag of the package allows
+    Stability,
+    /// The package is locked to the given version, because it is not part of a partial update
+    Locked(String),
+}
+
+/// An inline alias such as `"vendor/package": "dev-main as 1.0.x-dev"` in the root requirements
+#[derive(Debug, Clone)]
+pub(crate) struct RootAlias {
+    pub package: String,
+    pub version: String,
+    pub alias: String,
+    pub alias_normalized: String,
+}
+
+/// All packages that may be part of the solution
+///
+/// The root package always has id 0, followed by the platform packages and then the candidates
+/// loaded from the repositories, starting with the names the root package requires.
+pub(crate) struct Pool {
+    pub packages: Vec<PoolPackage>,
+    pub root_aliases: Vec<RootAlias>,
+    /// Versions that were left out of the pool, by name
+    pub filtered: HashMap<String, Vec<(String, Filtered)>>,
+    by_name: HashMap<String, Vec<PackageId>>,
+    providers: HashMap<String, Vec<(PackageId, usize, bool)>>,
+}
+
+impl Pool {
+    pub const ROOT: PackageId = 0;
+
+    pub fn build(request: &Request, provider: &PackageProvider) -> Result<Self, ResolverError> {
+        let mut pool = Pool {
+            packages: Vec::new(),
+            root_aliases: root_aliases(request)?,
+            filtered: HashMap::new(),
+            by_name: HashMap::new(),
+            providers: HashMap::new(),
+        };
+
+        pool.add(root_package(request)?);
+
+        for (name, version) in &request.platform {
+            let normalized = normalize(version).map_err(|error| {
+                ResolverError::InvalidRootPackage(format!("config.platform.{name}"), error)
+            })?;
+
+            pool.add(PoolPackage {
+                name: name.to_lowercase(),
+                version: normalized,
+                pretty_version: version.clone(),
+                stability: Stability::Stable,
+                requires: Vec::new(),
+                conflicts: Vec::new(),
+                replaces: Vec::new(),
+                provides: Vec::new(),
+                kind: PackageKind::Platform,
+                priority: 0,
+            });
+        }
+
+        let mut queue: VecDeque<String> = pool.packages[Pool::ROOT]
+            .requires
+            .iter()
+            .map(|link| link.target.clone())
+            .collect();
+        let mut loaded = HashSet::new();
+
+        while let Some(name) = queue.pop_front() {
+            if is_platform_package(&name) || !loaded.insert(name.clone()) {
+                continue;
+            }
+
+            for (package, priority) in provider(&name)? {
+                let Some(id) = pool.add_candidate(request, package, priority) else {
+                    continue;
+                };
+
+                queue.extend(
+                    pool.packages[id]
+                        .requires
+                        .iter()
+                        .map(|link| link.target.clone()),
+                );
+            }
+        }
+
+        Ok(pool)
+    }
+
+    /// The packages named `name` whose version matches the constraint, followed by the packages
+    /// that replace or provide a matching version of it
+    pub fn what_provides(&self, name: &str, constraint: &Constraint) -> Vec<PackageId> {
+        let mut ids: Vec<PackageId> = self
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|id| constraint.matches_version(&self.packages[*id].version))
+            .collect();
+
+        for (id, link, replace) in self.providers.get(name).into_iter().flatten() {
+            let package = &self.packages[*id];
+            let links = match replace {
+                true => &package.replaces,
+                false => &package.provides,
+            };
+
+            if links[*link].constraint.matches(constraint) && !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+
+        ids
+    }
+
+    /// The names a package occupies, which no other package may share: its own name and the
+    /// names it replaces
+    pub fn names(&self, id: PackageId) -> impl Iterator<Item = &str> {
+        let package = &self.packages[id];
+
+        std::iter::once(package.name.as_str())
+            .chain(package.replaces.iter().map(|link| link.target.as_str()))
+    }
+
+    fn add(&mut self, package: PoolPackage) -> PackageId {
+        let id = self.packages.len();
+
+        self.by_name
+            .entry(package.name.clone())
+            .or_default()
+            .push(id);
+
+        for (index, link) in package.replaces.iter().enumerate() {
+            self.providers
+                .entry(link.target.clone())
+                .or_default()
+                .push((id, index, true));
+        }
+
+        for (index, link) in package.provides.iter().enumerate() {
+            self.providers
+                .entry(link.target.clone())
+                .or_default()
+                .push((id, index, false));
+        }
+
+        self.packages.push(package);
+
+        id
+    }
+
+    /// The versions of a name in the pool, whether they match any constraint or not
+    pub fn versions(&self, name: &str) -> Vec<&PoolPackage> {
+        self.by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|id| &self.packages[*id])
+            .filter(|package| !package.is_alias())
+            .collect()
+    }
+
+    fn filter(&mut self, name: String, version: String, reason: Filtered) {
+        self.filtered
+            .entry(name)
+            .or_default()
+            .push((version, reason));
+    }
+
+    fn add_candidate(
+        &mut self,
+        request: &Request,
+        package: Package,
+        priority: usize,
+    ) -> Option<PackageId> {
+        let name = package.name.to_lowercase();
+        let version = match &package.version_normalized {
+            Some(version) => version.clone(),
+            None => normalize(&package.version).ok()?,
+        };
+
+        if let Some(locked) = request.locked.get(&name) {
+            if normalize(locked).ok()? != version {
+                self.filter(name, package.version, Filtered::Locked(locked.clone()));
+                return None;
+            }
+        }
+
+        let already_loaded = self
+            .by_name
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .any(|id| !self.packages[*id].is_alias() && self.packages[*id].version == version);
+        let stability = Stability::of(&version);
+
+        if already_loaded {
+            return None;
+        }
+
+        if !request.is_acceptable(&name, stability) {
+            self.filter(name, package.version, Filtered::Stability);
+            return None;
+        }
+
+        let branch_alias = package.branch_alias();
+        let root_alias = self
+            .root_aliases
+            .iter()
+            .find(|alias| alias.package == name && alias.version == version)
+            .map(|alias| (alias.alias.clone(), alias.alias_normalized.clone()));
+
+        let id = self.add(PoolPackage {
+            requires: links(&package.require, &package.version).ok()?,
+            conflicts: links(package.conflict.as_ref(), &package.version).ok()?,
+            replaces: links(package.replace.as_ref(), &package.version).ok()?,
+            provides: links(package.provide.as_ref(), &package.version).ok()?,
+            name,
+            version,
+            pretty_version: package.version.clone(),
+            stability,
+            kind: PackageKind::Candidate(Box::new(package)),
+            priority,
+        });
+
+        for (alias, root) in branch_alias
+            .into_iter()
+            .map(|alias| (alias, false))
+            .chain(root_alias.map(|alias| (alias, true)))
+        {
+            self.add_alias(request, id, alias, root);
+        }
+
+        Some(id)
+    }
+
+    fn add_alias(
+        &mut self,
+        request: &Request,
+        of: PackageId,
+        (pretty_version, version): (String, String),
+        root: bool,
+    ) {
+        let base = &self.packages[of];
+        let PackageKind::Candidate(package) = &base.kind else {
+            return;
+        };
+        let stability = Stability::of(&version);
+
+        if !request.is_acceptable(&base.name, stability) {
+            return;
+        }
+
+        // Links to self.version refer to the alias version when coming from the alias
+        let (Ok(requires), Ok(conflicts), Ok(replaces), Ok(provides)) = (
+            links(&package.require, &pretty_version),
+            links(package.conflict.as_ref(), &pretty_version),
+            links(package.replace.as_ref(), &pretty_version),
+            links(package.provide.as_ref(), &pretty_version),
+        ) else {
+            return;
+        };
+
+        let alias = PoolPackage {
+            name: base.name.clone(),
+            version,
+            pretty_version,
+            stability,
+            requires,
+            conflicts,
+            replaces,
+            provides,
+            kind: PackageKind::Alias { of, root },
+            priority: base.priority,
+        };
+
+        self.add(alias);
+    }
+}
+
+fn root_package(request: &Request) -> Result<PoolPackage, ResolverError> {
+    let root_links = |key: &str, map: &indexmap::IndexMap<String, String>| {
+        map.iter()
+            .map(|(target, constraint)| {
+                let constraint = constraint.replace("self.version", &request.version);
+
+                Ok(Link {
+                    target: target.to_lowercase(),
+                    constraint: Constraint::parse(&constraint).map_err(|error| {
+                        ResolverError::InvalidRootPackage(format!("{key}.{target}"), error)
+                    })?,
+                    pretty_constraint: constraint,
+                })
+            })
+            .collect::<Result<Vec<_>, ResolverError>>()
+    };
+
+    let mut requires = root_links("require", &request.require)?;
+    requires.extend(root_links("require-dev", &request.require_dev)?);
+    requires.retain(|link| !request.ignore_platform_reqs.ignores(&link.target));
+
+    let version = normalize(&request.version)
+        .map_err(|error| ResolverError::InvalidRootPackage("version".into(), error))?;
+
+    Ok(PoolPackage {
+        name: request.name.to_lowercase(),
+        stability: Stability::of(&version),
+        version,
+        pretty_version: request.version.clone(),
+        requires,
+        conflicts: root_links("conflict", &request.conflict)?,
+        replaces: root_links("replace", &request.replace)?,
+        provides: root_links("provide", &request.provide)?,
+        kind: PackageKind::Root,
+        priority: 0,
+    })
+}
+
+fn root_aliases(request: &Request) -> Result<Vec<RootAlias>, ResolverError> {
+    let mut aliases = Vec::new();
+
+    for (name, constraint) in request.require.iter().chain(&request.require_dev) {
+        let (version, Some(alias)) = split_inline_alias(constraint) else {
+            continue;
+        };
+        let version = version.split('#').next().unwrap_or(version);
+        let invalid = |error: SemverError| {
+            ResolverError::InvalidRootPackage(format!("require.{name}"), error)
+        };
+
+        aliases.push(RootAlias {
+            package: name.to_lowercase(),
+            version: normalize(version).map_err(invalid)?,
+            alias: alias.to_string(),
+            alias_normalized: normalize(alias).map_err(invalid)?,
+        });
+    }
+
+    Ok(aliases)
+}
+
+fn links<'a>(
+    links: impl Into<Option<&'a PhpArray<String>>>,
+    self_version: &str,
+) -> Result<Vec<Link>, SemverError> {
+    links
+        .into()
+        .into_iter()
+        .flat_map(|links| links.iter())
+        .map(|(target, constraint)| {
+            let constraint = constraint.replace("self.version", self_version);
+
+            Ok(Link {
+                target: target.to_lowercase(),
+                constraint: Constraint::parse(&constraint)?,
+                pretty_constraint: constraint,
+            })
+        })
+        .collect()
+}
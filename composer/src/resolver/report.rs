@@ -0,0 +1,432 @@
+use crate::resolver::{is_platform_package, PackageRef, Problem, Rule, RuleReason, Unmatched};
+use crate::semver::{compare_versions, normalize};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+const MESSAGE: &str = "Your requirements could not be resolved to an installable set of packages.";
+
+/// An explanation of why the requirements can't be resolved, worded like Composer does
+///
+/// `Display` prints the numbered problems and the hints for the console, and the report can be
+/// serialized to JSON for tools.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub message: String,
+    pub problems: Vec<ProblemReport>,
+    pub hints: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemReport {
+    /// The explanations, one per line
+    pub lines: Vec<String>,
+    pub rules: Vec<Rule>,
+}
+
+impl Report {
+    pub fn new(problems: &[Problem]) -> Self {
+        let mut hints = Hints::default();
+
+        let problems = problems
+            .iter()
+            .map(|problem| {
+                let mut rules: Vec<&Rule> = problem.rules.iter().collect();
+                rules.sort_by_key(|rule| order(&rule.reason));
+
+                let mut lines: Vec<String> = Vec::new();
+
+                for rule in &rules {
+                    let line = match &rule.reason {
+                        RuleReason::SameName { name } => same_name(name, &problem.rules),
+                        _ => describe(rule, &mut hints),
+                    };
+
+                    if let Some(line) = line.filter(|line| !lines.contains(line)) {
+                        lines.push(line);
+                    }
+                }
+
+                ProblemReport {
+                    lines,
+                    rules: problem.rules.clone(),
+                }
+            })
+            .collect();
+
+        Report {
+            message: MESSAGE.into(),
+            problems,
+            hints: hints.into_lines(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.message)?;
+
+        for (index, problem) in self.problems.iter().enumerate() {
+            writeln!(f)?;
+            write!(f, "  Problem {}", index + 1)?;
+
+            for line in &problem.lines {
+                write!(f, "\n    - {line}")?;
+            }
+        }
+
+        for hint in &self.hints {
+            write!(f, "\n\n{hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Remedies that apply to at least one of the problems
+#[derive(Default)]
+struct Hints {
+    not_found: bool,
+    unstable: bool,
+    locked: bool,
+    platform: Vec<String>,
+}
+
+impl Hints {
+    fn into_lines(self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if self.not_found || self.unstable {
+            lines.push(
+                [
+                    "Potential causes:",
+                    " - A typo in the package name",
+                    " - The package is not available in a stable-enough version according to your minimum-stability setting",
+                    "   see <https://getcomposer.org/doc/04-schema.md#minimum-stability> for more details.",
+                    " - It's a private package and you forgot to add a custom repository to find it",
+                    "",
+                    "Read <https://getcomposer.org/doc/articles/troubleshooting.md> for further common problems.",
+                ]
+                .join("\n"),
+            );
+        }
+
+        if !self.platform.is_empty() {
+            let mut hint = String::new();
+
+            if self.platform.iter().any(|name| name.starts_with("ext-")) {
+                hint.push_str(
+                    "To enable extensions, verify that they are enabled in your .ini files.\n",
+                );
+            }
+
+            hint.push_str(&format!(
+                "Alternatively, you can run Composer with `{}` to temporarily ignore these required platform packages.",
+                self.platform
+                    .iter()
+                    .map(|name| format!("--ignore-platform-req={name}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+
+            lines.push(hint);
+        }
+
+        if self.locked {
+            lines.push("Use the option --with-all-dependencies (-W) to allow upgrades, downgrades and removals for packages currently locked to specific versions.".into());
+        }
+
+        lines
+    }
+}
+
+/// Root requirements come first, then the other requirements, conflicts and the rest
+fn order(reason: &RuleReason) -> u8 {
+    match reason {
+        RuleReason::Requires { root: true, .. } => 0,
+        RuleReason::Requires { .. } => 1,
+        RuleReason::Conflicts { .. } => 2,
+        RuleReason::SameName { .. } => 3,
+        RuleReason::Alias { .. } => 4,
+        RuleReason::Fixed(_) => 5,
+    }
+}
+
+fn describe(rule: &Rule, hints: &mut Hints) -> Option<String> {
+    match &rule.reason {
+        // The root package and the platform are a given, they need no explanation
+        RuleReason::Fixed(_) | RuleReason::SameName { .. } => None,
+        RuleReason::Requires {
+            package,
+            root,
+            target,
+            constraint,
+        } => {
+            let subject = match root {
+                true => "Root composer.json".to_string(),
+                false => package.to_string(),
+            };
+            let candidates = installable(rule);
+
+            if !candidates.is_empty() {
+                return Some(format!(
+                    "{subject} requires {target} {constraint} -> satisfiable by {}.",
+                    package_list(&candidates)
+                ));
+            }
+
+            let unmatched = rule.unmatched.clone().unwrap_or_default();
+
+            if is_platform_package(target) {
+                if !hints.platform.contains(target) {
+                    hints.platform.push(target.clone());
+                }
+
+                let separator = if *root { " but" } else { " ->" };
+
+                return Some(format!(
+                    "{subject} requires {target} {constraint}{separator} {}",
+                    missing_platform_package(target, &unmatched)
+                ));
+            }
+
+            let separator = if *root { "," } else { " ->" };
+
+            Some(format!(
+                "{subject} requires {target} {constraint}{separator} {}",
+                missing_package(target, &unmatched, hints)
+            ))
+        }
+        RuleReason::Conflicts {
+            package,
+            root,
+            target,
+            constraint,
+        } => {
+            let subject = match root {
+                true => "Root composer.json".to_string(),
+                false => package.to_string(),
+            };
+            let conflicting: Vec<_> = rule
+                .literals
+                .iter()
+                .skip(1)
+                .map(|literal| literal.package.to_string())
+                .collect();
+
+            Some(match conflicting.is_empty() {
+                true => format!("{subject} conflicts with {target} {constraint}."),
+                false => format!("{subject} conflicts with {}.", conflicting.join(", ")),
+            })
+        }
+        RuleReason::Alias { alias, package } => Some(format!(
+            "{alias} is an alias of {package} and thus requires it to be installed too."
+        )),
+    }
+}
+
+fn missing_platform_package(target: &str, unmatched: &Unmatched) -> String {
+    if let (true, Some(version)) = (unmatched.platform, unmatched.versions.first()) {
+        return format!("your {target} version ({version}) does not satisfy that requirement.");
+    }
+
+    match target.strip_prefix("ext-") {
+        Some(extension) => format!(
+            "it is missing from your system. Install or enable PHP's {extension} extension."
+        ),
+        None => "it is missing from your system.".into(),
+    }
+}
+
+fn missing_package(target: &str, unmatched: &Unmatched, hints: &mut Hints) -> String {
+    let list = |versions: &[String]| {
+        let packages: Vec<_> = versions
+            .iter()
+            .map(|version| PackageRef {
+                name: target.to_string(),
+                version: version.clone(),
+            })
+            .collect();
+
+        package_list(&packages.iter().collect::<Vec<_>>())
+    };
+
+    if let Some(locked) = &unmatched.locked {
+        hints.locked = true;
+
+        return format!(
+            "found {} but the package is fixed to {locked} (lock file version) by a partial update and that version does not match. Make sure you list it as an argument for the update command.",
+            list(&unmatched.not_locked)
+        );
+    }
+
+    if !unmatched.versions.is_empty() {
+        return format!(
+            "found {} but it does not match the constraint.",
+            list(&unmatched.versions)
+        );
+    }
+
+    if !unmatched.unstable.is_empty() {
+        hints.unstable = true;
+
+        return format!(
+            "found {} but it does not match your minimum-stability.",
+            list(&unmatched.unstable)
+        );
+    }
+
+    hints.not_found = true;
+
+    "it could not be found in any version, there may be a typo in the package name.".into()
+}
+
+fn same_name(name: &str, rules: &[Rule]) -> Option<String> {
+    let mut packages: Vec<&PackageRef> = Vec::new();
+
+    for rule in rules {
+        if !matches!(&rule.reason, RuleReason::SameName { name: other } if other == name) {
+            continue;
+        }
+
+        for literal in &rule.literals {
+            if !packages.contains(&&literal.package) {
+                packages.push(&literal.package);
+            }
+        }
+    }
+
+    let mut line = format!(
+        "Only one of these can be installed: {}.",
+        package_list(&packages)
+    );
+
+    let mut replacers: Vec<&str> = packages
+        .iter()
+        .map(|package| package.name.as_str())
+        .filter(|package| !package.eq_ignore_ascii_case(name))
+        .collect();
+    replacers.dedup();
+
+    if !replacers.is_empty() {
+        line.push_str(&format!(
+            " {} replace{} {name} and thus cannot coexist with it.",
+            replacers.join(", "),
+            if replacers.len() == 1 { "s" } else { "" }
+        ));
+    }
+
+    Some(line)
+}
+
+/// The packages a requirement could be fulfilled with
+fn installable(rule: &Rule) -> Vec<&PackageRef> {
+    rule.literals
+        .iter()
+        .filter(|literal| literal.installed)
+        .map(|literal| &literal.package)
+        .collect()
+}
+
+/// Lists packages grouped by name, e.g. `vendor/a[1.0.0, 1.1.0], vendor/b[2.0.0]`, leaving out the
+/// middle versions when there are many
+fn package_list(packages: &[&PackageRef]) -> String {
+    let mut by_name: IndexMap<&str, Vec<&str>> = IndexMap::new();
+
+    for package in packages {
+        let versions = by_name.entry(package.name.as_str()).or_default();
+
+        if !versions.contains(&package.version.as_str()) {
+            versions.push(&package.version);
+        }
+    }
+
+    by_name
+        .into_iter()
+        .map(|(name, mut versions)| {
+            versions.sort_by(|a, b| {
+                let normalized = |version: &str| normalize(version).unwrap_or(version.to_string());
+
+                compare_versions(&normalized(a), &normalized(b))
+            });
+
+            if versions.len() > 4 {
+                versions = vec![
+                    versions[0],
+                    versions[1],
+                    "...",
+                    versions[versions.len() - 1],
+                ];
+            }
+
+            format!("{name}[{}]", versions.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[test]
+fn test_package_list() {
+    let package = |name: &str, version: &str| PackageRef {
+        name: name.into(),
+        version: version.into(),
+    };
+    let packages = [
+        package("a/a", "1.10.0"),
+        package("a/a", "1.2.0"),
+        package("b/b", "dev-main"),
+        package("a/a", "1.9.0"),
+        package("a/a", "v1.0.0"),
+        package("a/a", "2.0.0-beta1"),
+    ];
+
+    assert_eq!(
+        package_list(&packages.iter().collect::<Vec<_>>()),
+        "a/a[v1.0.0, 1.2.0, ..., 2.0.0-beta1], b/b[dev-main]"
+    );
+}
+
+#[test]
+fn test_report() {
+    use crate::resolver::{repository, resolve, Request, ResolverError};
+
+    let provider = repository(serde_json::json!([
+        {"name": "a/a", "version": "1.0.0", "require": {"ext-missing": "*"}},
+        {"name": "b/b", "version": "1.0.0"},
+        {"name": "b/b", "version": "1.1.0"},
+        {"name": "c/c", "version": "1.0.0-beta1"}
+    ]));
+
+    let mut request = Request::default();
+    request.require.insert("a/a".into(), "^1.0".into());
+    request.require.insert("b/b".into(), "^2.0".into());
+    request.require.insert("c/c".into(), "^1.0".into());
+    request.require.insert("d/d".into(), "^1.0".into());
+
+    let Err(ResolverError::Unsatisfiable(problems)) = resolve(&request, &provider) else {
+        panic!("requirements should not be satisfiable");
+    };
+
+    let report = Report::new(&problems);
+    let lines: Vec<_> = report
+        .problems
+        .iter()
+        .flat_map(|problem| problem.lines.iter().map(String::as_str))
+        .collect();
+
+    assert!(lines.contains(&"Root composer.json requires a/a ^1.0 -> satisfiable by a/a[1.0.0]."));
+    assert!(lines.contains(&"a/a 1.0.0 requires ext-missing * -> it is missing from your system. Install or enable PHP's missing extension."));
+    assert!(lines.contains(&"Root composer.json requires b/b ^2.0, found b/b[1.0.0, 1.1.0] but it does not match the constraint."));
+    assert!(lines.contains(&"Root composer.json requires c/c ^1.0, found c/c[1.0.0-beta1] but it does not match your minimum-stability."));
+    assert!(lines.contains(&"Root composer.json requires d/d ^1.0, it could not be found in any version, there may be a typo in the package name."));
+    assert!(report.hints[1].contains("--ignore-platform-req=ext-missing"));
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["problems"].as_array().unwrap().len(), 4);
+    assert_eq!(json["problems"][0]["lines"][0], lines[0]);
+    assert_eq!(json["hints"].as_array().unwrap().len(), 2);
+}
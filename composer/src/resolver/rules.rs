@@ -0,0 +1,267 @@
+use crate::resolver::pool::{Filtered, PackageId, PackageKind, Pool};
+use crate::resolver::solver::Literal;
+use crate::resolver::Request;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A package as it is named in problem reports
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct PackageRef {
+    pub name: String,
+    pub version: String,
+}
+
+impl Display for PackageRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// Why a rule was created
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum RuleReason {
+    /// The root package, or a platform package, which are always installed
+    Fixed(PackageRef),
+    /// A package, or the root composer.json when `root` is set, requires a target
+    Requires {
+        package: PackageRef,
+        root: bool,
+        target: String,
+        constraint: String,
+    },
+    /// A package, or the root composer.json when `root` is set, conflicts with a target
+    Conflicts {
+        package: PackageRef,
+        root: bool,
+        target: String,
+        constraint: String,
+    },
+    /// Only one package with the same name, or replacing the same name, can be installed
+    SameName { name: String },
+    /// An alias can only be installed together with the package it aliases
+    Alias {
+        alias: PackageRef,
+        package: PackageRef,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleLiteral {
+    pub installed: bool,
+    pub package: PackageRef,
+}
+
+/// A clause of the dependency problem: at least one of its literals must hold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rule {
+    pub reason: RuleReason,
+    pub literals: Vec<RuleLiteral>,
+    /// For requirements that no package can fulfill, the versions that exist but can't be used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unmatched: Option<Unmatched>,
+}
+
+/// The versions of a required name that were found but don't fulfill the requirement
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Unmatched {
+    /// Versions that don't match the constraint
+    pub versions: Vec<String>,
+    /// Versions that are less stable than the minimum stability allows
+    pub unstable: Vec<String>,
+    /// Versions that are not the locked version, when the package is not being updated
+    pub not_locked: Vec<String>,
+    /// The version the package is locked to
+    pub locked: Option<String>,
+    /// Whether the name is a platform package that is installed, with the versions holding its
+    /// installed version
+    pub platform: bool,
+}
+
+/// The rules as generated from the pool, using package ids as variables
+pub(crate) struct RuleSet {
+    pub rules: Vec<(Vec<Literal>, RuleReason)>,
+}
+
+impl RuleSet {
+    /// Generates the rules for all packages in the pool, leaving out the root requirements of
+    /// the disabled names
+    pub fn generate(pool: &Pool, request: &Request, disabled: &[String]) -> Self {
+        let mut rules = Vec::new();
+        let reference = |id: PackageId| package_ref(pool, id);
+
+        for (id, package) in pool.packages.iter().enumerate() {
+            let root = id == Pool::ROOT;
+
+            match package.kind {
+                PackageKind::Root | PackageKind::Platform => {
+                    rules.push((vec![Literal::install(id)], RuleReason::Fixed(reference(id))));
+                }
+                PackageKind::Alias { of, root } => {
+                    let reason = RuleReason::Alias {
+                        alias: reference(id),
+                        package: reference(of),
+                    };
+
+                    rules.push((
+                        vec![Literal::dont_install(id), Literal::install(of)],
+                        reason.clone(),
+                    ));
+
+                    // Inline aliases replace the aliased version, so they are installed together
+                    if root {
+                        rules.push((
+                            vec![Literal::dont_install(of), Literal::install(id)],
+                            reason,
+                        ));
+                    }
+
+                    continue;
+                }
+                PackageKind::Candidate(_) => {}
+            }
+
+            for link in &package.requires {
+                if request.ignore_platform_reqs.ignores(&link.target)
+                    || (root && disabled.contains(&link.target))
+                {
+                    continue;
+                }
+
+                let mut literals = vec![Literal::dont_install(id)];
+                literals.extend(
+                    pool.what_provides(&link.target, &link.constraint)
+                        .into_iter()
+                        .map(Literal::install),
+                );
+
+                rules.push((
+                    literals,
+                    RuleReason::Requires {
+                        package: reference(id),
+                        root,
+                        target: link.target.clone(),
+                        constraint: link.pretty_constraint.clone(),
+                    },
+                ));
+            }
+
+            for link in &package.conflicts {
+                for conflicting in pool.what_provides(&link.target, &link.constraint) {
+                    let is_self = match pool.packages[conflicting].kind {
+                        PackageKind::Alias { of, .. } => of == id,
+                        _ => conflicting == id,
+                    };
+
+                    if is_self {
+                        continue;
+                    }
+
+                    rules.push((
+                        vec![
+                            Literal::dont_install(id),
+                            Literal::dont_install(conflicting),
+                        ],
+                        RuleReason::Conflicts {
+                            package: reference(id),
+                            root,
+                            target: link.target.clone(),
+                            constraint: link.pretty_constraint.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        let mut by_name: HashMap<&str, Vec<PackageId>> = HashMap::new();
+
+        for (id, package) in pool.packages.iter().enumerate() {
+            if matches!(package.kind, PackageKind::Candidate(_) | PackageKind::Root) {
+                for name in pool.names(id) {
+                    by_name.entry(name).or_default().push(id);
+                }
+            }
+        }
+
+        let mut names: Vec<_> = by_name
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .collect();
+        names.sort();
+
+        for (name, ids) in names {
+            for (index, a) in ids.iter().enumerate() {
+                for b in &ids[index + 1..] {
+                    rules.push((
+                        vec![Literal::dont_install(*a), Literal::dont_install(*b)],
+                        RuleReason::SameName {
+                            name: name.to_string(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        RuleSet { rules }
+    }
+
+    /// The public form of a rule, naming the packages instead of their ids
+    pub fn rule(&self, pool: &Pool, index: usize) -> Rule {
+        let (literals, reason) = &self.rules[index];
+        let unmatched = match reason {
+            RuleReason::Requires { target, .. }
+                if literals.iter().all(|literal| !literal.is_positive()) =>
+            {
+                Some(unmatched(pool, target))
+            }
+            _ => None,
+        };
+
+        Rule {
+            reason: reason.clone(),
+            unmatched,
+            literals: literals
+                .iter()
+                .map(|literal| RuleLiteral {
+                    installed: literal.is_positive(),
+                    package: package_ref(pool, literal.variable()),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn unmatched(pool: &Pool, target: &str) -> Unmatched {
+    let mut unmatched = Unmatched::default();
+
+    for package in pool.versions(target) {
+        unmatched.platform |= matches!(package.kind, PackageKind::Platform);
+        unmatched.versions.push(package.pretty_version.clone());
+    }
+
+    for (version, reason) in pool.filtered.get(target).into_iter().flatten() {
+        match reason {
+            Filtered::Stability => unmatched.unstable.push(version.clone()),
+            Filtered::Locked(locked) => {
+                unmatched.not_locked.push(version.clone());
+                unmatched.locked = Some(locked.clone());
+            }
+        }
+    }
+
+    unmatched
+}
+
+pub(crate) fn package_ref(pool: &Pool, id: PackageId) -> PackageRef {
+    let package = &pool.packages[id];
+
+    PackageRef {
+        name: match &package.kind {
+            // Keep the name as the package author spelled it
+            PackageKind::Candidate(candidate) => candidate.name.clone(),
+            _ => package.name.clone(),
+        },
+        version: package.pretty_version.clone(),
+    }
+}
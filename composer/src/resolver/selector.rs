@@ -0,0 +1,130 @@
+use crate::lock_file::Package;
+use crate::resolver::{is_platform_package, Request};
+use crate::semver::{compare_versions, normalize, Constraint, Stability};
+
+/// The version `require` adds a package with when no constraint is given, like Composer's
+/// `VersionSelector`: the highest version allowed by the minimum stability whose platform
+/// requirements are met
+///
+/// Stable versions are preferred over less stable ones with `prefer-stable`, and branches are only
+/// selected when there is no other version.
+pub fn find_best_candidate<'a>(
+    request: &Request,
+    candidates: &'a [Package],
+) -> Option<&'a Package> {
+    candidates
+        .iter()
+        .filter_map(|package| {
+            let normalized = match &package.version_normalized {
+                Some(version) => version.clone(),
+                None => normalize(&package.version).ok()?,
+            };
+            let stability = Stability::of(&normalized);
+
+            (request.is_acceptable(&package.name, stability) && meets_platform(request, package))
+                .then_some((package, normalized, stability))
+        })
+        .max_by(|(_, a, a_stability), (_, b, b_stability)| {
+            let rank = |version: &str, stability: &Stability| {
+                (
+                    request.prefer_stable && *stability == Stability::Stable,
+                    !version.starts_with("dev-"),
+                )
+            };
+
+            rank(a, a_stability)
+                .cmp(&rank(b, b_stability))
+                .then_with(|| compare_versions(a, b))
+        })
+        .map(|(package, _, _)| package)
+}
+
+/// The constraint to require a package with, which allows later minor versions, e.g. `^2.1` for
+/// `2.1.3`, `^0.3.1` for `0.3.1` and `^1.0@beta` for `1.0.0-beta2`
+///
+/// Branches are required as they are, and extensions versioned like PHP itself with `*`.
+pub fn recommended_constraint(request: &Request, package: &Package) -> String {
+    if package.name.starts_with("ext-") && request.platform.get("php") == Some(&package.version) {
+        return "*".into();
+    }
+
+    let normalized = match &package.version_normalized {
+        Some(version) => version.clone(),
+        None => normalize(&package.version).unwrap_or_default(),
+    };
+    let stability = Stability::of(&normalized);
+    let parts: Vec<&str> = normalized.split('.').collect();
+
+    let [major, minor, patch, extra] = parts[..] else {
+        return package.version.clone();
+    };
+
+    if stability == Stability::Dev || !extra.starts_with(|c: char| c.is_ascii_digit()) {
+        return package.version.clone();
+    }
+
+    let mut version = match major {
+        "0" => format!("{major}.{minor}.{patch}"),
+        _ => format!("{major}.{minor}"),
+    };
+
+    if stability != Stability::Stable {
+        version.push_str(&format!("@{stability}"));
+    }
+
+    format!("^{version}")
+}
+
+/// Whether the platform has the PHP version and extensions a package requires
+fn meets_platform(request: &Request, package: &Package) -> bool {
+    package
+        .require
+        .iter()
+        .filter(|(name, _)| {
+            is_platform_package(name) && !request.ignore_platform_reqs.ignores(name)
+        })
+        .all(|(name, constraint)| {
+            let version = request
+                .platform
+                .get(&name)
+                .map(|version| normalize(version));
+
+            match (Constraint::parse(constraint), version) {
+                (Ok(constraint), Some(Ok(version))) => constraint.matches_version(&version),
+                _ => false,
+            }
+        })
+}
+
+#[test]
+fn test_find_best_candidate() {
+    let request = Request {
+        platform: [("php".to_string(), "8.1.0".to_string())].into(),
+        ..Request::default()
+    };
+    let candidates: Vec<Package> = serde_json::from_value(serde_json::json!([
+        {"name": "acme/lib", "version": "dev-main"},
+        {"name": "acme/lib", "version": "1.2.3"},
+        {"name": "acme/lib", "version": "0.9.1"},
+        {"name": "acme/lib", "version": "2.0.0-beta1"},
+        {"name": "acme/lib", "version": "1.5.0", "require": {"php": ">=8.2"}},
+    ]))
+    .unwrap();
+
+    let best = find_best_candidate(&request, &candidates).unwrap();
+    assert_eq!(best.version, "1.2.3");
+    assert_eq!(recommended_constraint(&request, best), "^1.2");
+    assert_eq!(recommended_constraint(&request, &candidates[2]), "^0.9.1");
+    assert_eq!(
+        recommended_constraint(&request, &candidates[3]),
+        "^2.0@beta"
+    );
+    assert_eq!(recommended_constraint(&request, &candidates[0]), "dev-main");
+
+    let request = Request {
+        minimum_stability: Stability::Beta,
+        ..request
+    };
+    let best = find_best_candidate(&request, &candidates).unwrap();
+    assert_eq!(best.version, "2.0.0-beta1");
+}
@@ -0,0 +1,427 @@
+use std::collections::BTreeSet;
+
+/// A package that is either installed (positive) or not installed (negative)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Literal(usize);
+
+impl Literal {
+    pub fn install(variable: usize) -> Self {
+        Literal(variable << 1)
+    }
+
+    pub fn dont_install(variable: usize) -> Self {
+        Literal((variable << 1) | 1)
+    }
+
+    pub fn variable(self) -> usize {
+        self.0 >> 1
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    fn negate(self) -> Self {
+        Literal(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug)]
+enum Origin {
+    /// The index of the rule this clause was generated from
+    Rule(usize),
+    /// A clause learned from a conflict, with the clauses it was derived from and the clauses
+    /// that fixed the literals it omits because they were decided at level 0
+    Learned {
+        sources: Vec<usize>,
+        fixed: Vec<usize>,
+    },
+}
+
+#[derive(Debug)]
+struct Clause {
+    literals: Vec<Literal>,
+    origin: Origin,
+}
+
+/// A CDCL SAT solver: unit propagation with two watched literals, and learning from conflicts by
+/// resolving up to the first unique implication point
+///
+/// Decisions are delegated to a policy, which is only asked to pick a package when a clause has
+/// all its negative literals fulfilled, i.e. when an installed package still needs one of its
+/// requirements. Variables that are never decided end up not installed.
+pub(crate) struct Solver {
+    clauses: Vec<Clause>,
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Literal>,
+    trail_limits: Vec<usize>,
+    head: usize,
+    units: Vec<usize>,
+    empty: Option<usize>,
+}
+
+impl Solver {
+    pub fn new(variables: usize) -> Self {
+        Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); variables * 2],
+            values: vec![None; variables],
+            levels: vec![0; variables],
+            reasons: vec![None; variables],
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            head: 0,
+            units: Vec::new(),
+            empty: None,
+        }
+    }
+
+    pub fn add_rule(&mut self, mut literals: Vec<Literal>, rule: usize) {
+        let mut unique = Vec::with_capacity(literals.len());
+
+        for literal in literals.drain(..) {
+            if unique.contains(&literal.negate()) {
+                // A tautology like "A or not A" never restricts anything
+                return;
+            }
+
+            if !unique.contains(&literal) {
+                unique.push(literal);
+            }
+        }
+
+        self.push_clause(unique, Origin::Rule(rule));
+    }
+
+    /// Solves all clauses, returning whether each variable is installed, or the indices of the
+    /// rules that together make the problem unsatisfiable
+    pub fn solve(
+        &mut self,
+        choose: &dyn Fn(usize, &[usize]) -> usize,
+    ) -> Result<Vec<bool>, BTreeSet<usize>> {
+        if let Some(empty) = self.empty {
+            return Err(self.explain(empty));
+        }
+
+        for unit in self.units.clone() {
+            let literal = self.clauses[unit].literals[0];
+
+            match self.value(literal) {
+                Some(true) => {}
+                Some(false) => return Err(self.explain(unit)),
+                None => self.assign(literal, Some(unit)),
+            }
+        }
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_limits.is_empty() {
+                    return Err(self.explain(conflict));
+                }
+
+                let (literals, level, origin) = self.analyze(conflict);
+                self.backtrack(level);
+
+                let asserting = literals[0];
+                let clause = self.push_clause(literals, origin);
+                self.assign(asserting, Some(clause));
+
+                continue;
+            }
+
+            match self.next_decision(choose) {
+                Some(literal) => {
+                    self.trail_limits.push(self.trail.len());
+                    self.assign(literal, None);
+                }
+                None => break,
+            }
+        }
+
+        Ok(self
+            .values
+            .iter()
+            .map(|value| *value == Some(true))
+            .collect())
+    }
+
+    fn push_clause(&mut self, literals: Vec<Literal>, origin: Origin) -> usize {
+        let id = self.clauses.len();
+
+        match literals.len() {
+            0 => {
+                self.empty.get_or_insert(id);
+            }
+            1 => self.units.push(id),
+            _ => {
+                self.watches[literals[0].0].push(id);
+                self.watches[literals[1].0].push(id);
+            }
+        }
+
+        self.clauses.push(Clause { literals, origin });
+
+        id
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        self.values[literal.variable()].map(|value| value == literal.is_positive())
+    }
+
+    fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        let variable = literal.variable();
+
+        self.values[variable] = Some(literal.is_positive());
+        self.levels[variable] = self.trail_limits.len();
+        self.reasons[variable] = reason;
+        self.trail.push(literal);
+    }
+
+    /// Assigns all literals implied by the current assignment, returning a clause that became
+    /// false if there is one
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = self.trail[self.head].negate();
+            self.head += 1;
+
+            let mut watchers = std::mem::take(&mut self.watches[falsified.0]);
+            let mut index = 0;
+
+            while index < watchers.len() {
+                let id = watchers[index];
+                let values = &self.values;
+                let value = |literal: Literal| {
+                    values[literal.variable()].map(|value| value == literal.is_positive())
+                };
+                let literals = &mut self.clauses[id].literals;
+
+                if literals[0] == falsified {
+                    literals.swap(0, 1);
+                }
+
+                if value(literals[0]) == Some(true) {
+                    index += 1;
+                    continue;
+                }
+
+                if let Some(position) =
+                    (2..literals.len()).find(|position| value(literals[*position]) != Some(false))
+                {
+                    literals.swap(1, position);
+                    self.watches[literals[1].0].push(id);
+                    watchers.swap_remove(index);
+                    continue;
+                }
+
+                let first = literals[0];
+
+                if value(first) == Some(false) {
+                    self.watches[falsified.0] = watchers;
+                    return Some(id);
+                }
+
+                self.assign(first, Some(id));
+                index += 1;
+            }
+
+            self.watches[falsified.0] = watchers;
+        }
+
+        None
+    }
+
+    /// Derives a clause from a conflict that forces a different assignment after backtracking,
+    /// returning it with the level to backtrack to
+    fn analyze(&self, conflict: usize) -> (Vec<Literal>, usize, Origin) {
+        let level = self.trail_limits.len();
+        let mut seen = vec![false; self.values.len()];
+        let mut literals = vec![Literal(0)];
+        let mut sources = Vec::new();
+        let mut fixed = Vec::new();
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+
+        let implied = loop {
+            sources.push(clause);
+
+            for literal in &self.clauses[clause].literals {
+                let variable = literal.variable();
+
+                if seen[variable] || self.value(*literal) == Some(true) {
+                    continue;
+                }
+
+                seen[variable] = true;
+
+                match self.levels[variable] {
+                    0 => fixed.extend(self.reasons[variable]),
+                    assigned if assigned == level => pending += 1,
+                    _ => literals.push(*literal),
+                }
+            }
+
+            index -= 1;
+            while !seen[self.trail[index].variable()] {
+                index -= 1;
+            }
+
+            let literal = self.trail[index];
+            pending -= 1;
+
+            if pending == 0 {
+                break literal;
+            }
+
+            clause = self.reasons[literal.variable()].expect("implied literals have a reason");
+        };
+
+        literals[0] = implied.negate();
+
+        let mut backtrack_level = 0;
+
+        if let Some((position, highest)) = literals
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(position, literal)| (position, self.levels[literal.variable()]))
+            .max_by_key(|(_, level)| *level)
+        {
+            literals.swap(1, position);
+            backtrack_level = highest;
+        }
+
+        (
+            literals,
+            backtrack_level,
+            Origin::Learned { sources, fixed },
+        )
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        let Some(&start) = self.trail_limits.get(level) else {
+            return;
+        };
+
+        for literal in self.trail.drain(start..) {
+            let variable = literal.variable();
+
+            self.values[variable] = None;
+            self.reasons[variable] = None;
+        }
+
+        self.trail_limits.truncate(level);
+        self.head = self.trail.len();
+    }
+
+    /// Picks the next package to install from the first clause that requires one
+    fn next_decision(&self, choose: &dyn Fn(usize, &[usize]) -> usize) -> Option<Literal> {
+        for clause in &self.clauses {
+            let mut candidates = Vec::new();
+            let mut applies = true;
+
+            for literal in &clause.literals {
+                match (self.value(*literal), literal.is_positive()) {
+                    (Some(true), _) | (None, false) => {
+                        applies = false;
+                        break;
+                    }
+                    (None, true) => candidates.push(literal.variable()),
+                    (Some(false), _) => {}
+                }
+            }
+
+            if !applies || candidates.is_empty() {
+                continue;
+            }
+
+            let rule = match clause.origin {
+                Origin::Rule(rule) => rule,
+                Origin::Learned { .. } => usize::MAX,
+            };
+
+            return Some(Literal::install(choose(rule, &candidates)));
+        }
+
+        None
+    }
+
+    /// Collects the rules that led to a conflict at level 0
+    fn explain(&self, conflict: usize) -> BTreeSet<usize> {
+        let mut rules = BTreeSet::new();
+        let mut visited = vec![false; self.clauses.len()];
+        // Clauses that hold at level 0 also depend on the reasons of their false literals
+        let mut stack = vec![(conflict, true)];
+
+        while let Some((id, follow)) = stack.pop() {
+            if visited[id] {
+                continue;
+            }
+
+            visited[id] = true;
+            let clause = &self.clauses[id];
+
+            match &clause.origin {
+                Origin::Rule(rule) => {
+                    rules.insert(*rule);
+                }
+                Origin::Learned { sources, fixed } => {
+                    stack.extend(sources.iter().map(|source| (*source, false)));
+                    stack.extend(fixed.iter().map(|source| (*source, true)));
+                }
+            }
+
+            if follow {
+                stack.extend(
+                    clause
+                        .literals
+                        .iter()
+                        .filter(|literal| self.value(**literal) == Some(false))
+                        .filter_map(|literal| self.reasons[literal.variable()])
+                        .map(|reason| (reason, true)),
+                );
+            }
+        }
+
+        rules
+    }
+}
+
+#[test]
+fn test_solver() {
+    // A requires B or C, B conflicts with A, A is fixed
+    let mut solver = Solver::new(3);
+    solver.add_rule(vec![Literal::install(0)], 0);
+    solver.add_rule(
+        vec![
+            Literal::dont_install(0),
+            Literal::install(1),
+            Literal::install(2),
+        ],
+        1,
+    );
+    solver.add_rule(vec![Literal::dont_install(1), Literal::dont_install(0)], 2);
+
+    let solution = solver.solve(&|_, candidates| candidates[0]).unwrap();
+    assert_eq!(solution, vec![true, false, true]);
+
+    // Both candidates conflict with A
+    let mut solver = Solver::new(3);
+    solver.add_rule(vec![Literal::install(0)], 0);
+    solver.add_rule(
+        vec![
+            Literal::dont_install(0),
+            Literal::install(1),
+            Literal::install(2),
+        ],
+        1,
+    );
+    solver.add_rule(vec![Literal::dont_install(1), Literal::dont_install(0)], 2);
+    solver.add_rule(vec![Literal::dont_install(2), Literal::dont_install(0)], 3);
+
+    let problem = solver.solve(&|_, candidates| candidates[0]).unwrap_err();
+    assert_eq!(problem.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+}
@@ -0,0 +1,627 @@
+use crate::semver::parser::parse_constraints;
+use crate::semver::version::compare_versions;
+use crate::semver::SemverError;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The lowest possible version, which every numeric version is greater than or equal to
+const ZERO: &str = "0.0.0.0-dev";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+                Operator::LessThan => "<",
+                Operator::LessThanOrEqual => "<=",
+                Operator::GreaterThan => ">",
+                Operator::GreaterThanOrEqual => ">=",
+            }
+        )
+    }
+}
+
+/// A parsed version constraint such as `^1.2 || ~2.0@beta`, on normalized versions
+///
+/// `Display` prints the canonical form the same way Composer does, e.g. `[>= 1.2.0.0-dev < 2.0.0.0-dev]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constraint {
+    /// Matches every version, including branches
+    Any,
+    /// Matches nothing
+    None,
+    Single {
+        operator: Operator,
+        version: String,
+    },
+    And(Vec<Constraint>),
+    Or(Vec<Constraint>),
+}
+
+impl Constraint {
+    pub fn parse(constraints: &str) -> Result<Self, SemverError> {
+        parse_constraints(constraints)
+    }
+
+    pub fn single(operator: Operator, version: impl Into<String>) -> Self {
+        Constraint::Single {
+            operator,
+            version: version.into(),
+        }
+    }
+
+    /// Whether this constraint and another one have at least one version in common
+    pub fn matches(&self, other: &Constraint) -> bool {
+        !self.to_set().intersect(&other.to_set()).is_empty()
+    }
+
+    /// Whether a normalized version, such as `1.2.0.0` or `dev-main`, satisfies this constraint
+    pub fn matches_version(&self, version: &str) -> bool {
+        self.matches(&Constraint::single(Operator::Equal, version))
+    }
+
+    /// The versions that satisfy both constraints, in their most compact form
+    pub fn intersect(&self, other: &Constraint) -> Constraint {
+        let set = self.to_set().intersect(&other.to_set());
+
+        set.to_constraint()
+            .unwrap_or_else(|| Constraint::And(vec![self.clone(), other.clone()]))
+    }
+
+    /// The versions that satisfy either constraint, in their most compact form
+    pub fn union(&self, other: &Constraint) -> Constraint {
+        let set = self.to_set().union(&other.to_set());
+
+        set.to_constraint()
+            .unwrap_or_else(|| Constraint::Or(vec![self.clone(), other.clone()]))
+    }
+
+    /// Whether no version can satisfy this constraint
+    pub fn is_empty(&self) -> bool {
+        self.to_set().is_empty()
+    }
+
+    /// Whether every version satisfying this constraint also satisfies the other one
+    pub fn is_subset_of(&self, other: &Constraint) -> bool {
+        let set = self.to_set();
+
+        set.intersect(&other.to_set()) == set
+    }
+
+    /// Simplifies the constraint, e.g. `>=1.0 <2.0 >=1.5` becomes `[>= 1.5.0.0-dev < 2.0.0.0-dev]`
+    pub fn compact(&self) -> Constraint {
+        self.to_set()
+            .to_constraint()
+            .unwrap_or_else(|| self.clone())
+    }
+
+    fn to_set(&self) -> VersionSet {
+        match self {
+            Constraint::Any => VersionSet::any(),
+            Constraint::None => VersionSet::none(),
+            Constraint::Single { operator, version } => VersionSet::single(*operator, version),
+            Constraint::And(constraints) => constraints
+                .iter()
+                .fold(VersionSet::any(), |set, constraint| {
+                    set.intersect(&constraint.to_set())
+                }),
+            Constraint::Or(constraints) => constraints
+                .iter()
+                .fold(VersionSet::none(), |set, constraint| {
+                    set.union(&constraint.to_set())
+                }),
+        }
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = SemverError;
+
+    fn from_str(constraints: &str) -> Result<Self, Self::Err> {
+        Constraint::parse(constraints)
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Any => write!(f, "*"),
+            Constraint::None => write!(f, "[]"),
+            Constraint::Single { operator, version } => write!(f, "{operator} {version}"),
+            Constraint::And(constraints) | Constraint::Or(constraints) => {
+                let separator = match self {
+                    Constraint::And(_) => " ",
+                    _ => " || ",
+                };
+
+                write!(f, "[")?;
+
+                for (index, constraint) in constraints.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "{separator}")?;
+                    }
+
+                    write!(f, "{constraint}")?;
+                }
+
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// One end of an interval of numeric versions
+#[derive(Debug, Clone, PartialEq)]
+struct Bound {
+    version: String,
+    inclusive: bool,
+}
+
+impl Bound {
+    fn zero() -> Self {
+        Bound {
+            version: ZERO.into(),
+            inclusive: true,
+        }
+    }
+}
+
+/// A range of numeric versions, where a missing end means there is no upper limit
+#[derive(Debug, Clone, PartialEq)]
+struct Interval {
+    start: Bound,
+    end: Option<Bound>,
+}
+
+impl Interval {
+    fn is_empty(&self) -> bool {
+        match &self.end {
+            None => false,
+            Some(end) => match compare_versions(&self.start.version, &end.version) {
+                Ordering::Less => false,
+                Ordering::Equal => !(self.start.inclusive && end.inclusive),
+                Ordering::Greater => true,
+            },
+        }
+    }
+
+    fn intersect(&self, other: &Interval) -> Interval {
+        let start = match compare_starts(&self.start, &other.start) {
+            Ordering::Less => other.start.clone(),
+            _ => self.start.clone(),
+        };
+
+        let end = match (&self.end, &other.end) {
+            (None, end) | (end, None) => end.clone(),
+            (Some(a), Some(b)) => match compare_ends(a, b) {
+                Ordering::Greater => Some(b.clone()),
+                _ => Some(a.clone()),
+            },
+        };
+
+        Interval { start, end }
+    }
+
+    /// Whether the other interval, which starts at or after this one, overlaps or touches it
+    fn connects_to(&self, other: &Interval) -> bool {
+        match &self.end {
+            None => true,
+            Some(end) => match compare_versions(&end.version, &other.start.version) {
+                Ordering::Greater => true,
+                Ordering::Equal => end.inclusive || other.start.inclusive,
+                Ordering::Less => false,
+            },
+        }
+    }
+}
+
+fn compare_starts(a: &Bound, b: &Bound) -> Ordering {
+    compare_versions(&a.version, &b.version).then(match (a.inclusive, b.inclusive) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => Ordering::Equal,
+    })
+}
+
+fn compare_ends(a: &Bound, b: &Bound) -> Ordering {
+    compare_versions(&a.version, &b.version).then(match (a.inclusive, b.inclusive) {
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => Ordering::Equal,
+    })
+}
+
+/// Which `dev-*` branches are part of a set: either exactly the listed names, or every branch
+/// except the listed names
+#[derive(Debug, Clone, PartialEq)]
+struct Branches {
+    names: Vec<String>,
+    exclude: bool,
+}
+
+impl Branches {
+    fn none() -> Self {
+        Branches {
+            names: Vec::new(),
+            exclude: false,
+        }
+    }
+
+    fn any() -> Self {
+        Branches {
+            names: Vec::new(),
+            exclude: true,
+        }
+    }
+
+    fn intersect(&self, other: &Branches) -> Branches {
+        match (self.exclude, other.exclude) {
+            (false, false) => Branches {
+                names: filter_names(&self.names, |name| other.names.contains(name)),
+                exclude: false,
+            },
+            (false, true) => Branches {
+                names: filter_names(&self.names, |name| !other.names.contains(name)),
+                exclude: false,
+            },
+            (true, false) => other.intersect(self),
+            (true, true) => Branches {
+                names: merge_names(&self.names, &other.names),
+                exclude: true,
+            },
+        }
+    }
+
+    fn union(&self, other: &Branches) -> Branches {
+        match (self.exclude, other.exclude) {
+            (false, false) => Branches {
+                names: merge_names(&self.names, &other.names),
+                exclude: false,
+            },
+            (false, true) => Branches {
+                names: filter_names(&other.names, |name| !self.names.contains(name)),
+                exclude: true,
+            },
+            (true, false) => other.union(self),
+            (true, true) => Branches {
+                names: filter_names(&self.names, |name| other.names.contains(name)),
+                exclude: true,
+            },
+        }
+    }
+}
+
+fn filter_names(names: &[String], keep: impl Fn(&String) -> bool) -> Vec<String> {
+    names.iter().filter(|name| keep(name)).cloned().collect()
+}
+
+fn merge_names(a: &[String], b: &[String]) -> Vec<String> {
+    let mut names = a.to_vec();
+    names.extend(b.iter().filter(|name| !a.contains(name)).cloned());
+    names.sort();
+
+    names
+}
+
+/// The versions matched by a constraint as sorted, non-overlapping intervals plus a set of branches
+#[derive(Debug, Clone, PartialEq)]
+struct VersionSet {
+    intervals: Vec<Interval>,
+    branches: Branches,
+}
+
+impl VersionSet {
+    fn any() -> Self {
+        VersionSet {
+            intervals: vec![Interval {
+                start: Bound::zero(),
+                end: None,
+            }],
+            branches: Branches::any(),
+        }
+    }
+
+    fn none() -> Self {
+        VersionSet {
+            intervals: Vec::new(),
+            branches: Branches::none(),
+        }
+    }
+
+    fn single(operator: Operator, version: &str) -> Self {
+        let bound = |inclusive| Bound {
+            version: version.to_string(),
+            inclusive,
+        };
+
+        if version.starts_with("dev-") {
+            // Dev branches only match equality checks, never ranges
+            return match operator {
+                Operator::Equal => VersionSet {
+                    intervals: Vec::new(),
+                    branches: Branches {
+                        names: vec![version.to_string()],
+                        exclude: false,
+                    },
+                },
+                Operator::NotEqual => VersionSet {
+                    intervals: VersionSet::any().intervals,
+                    branches: Branches {
+                        names: vec![version.to_string()],
+                        exclude: true,
+                    },
+                },
+                _ => VersionSet::none(),
+            };
+        }
+
+        let intervals = match operator {
+            Operator::Equal => vec![Interval {
+                start: bound(true),
+                end: Some(bound(true)),
+            }],
+            Operator::NotEqual => vec![
+                Interval {
+                    start: Bound::zero(),
+                    end: Some(bound(false)),
+                },
+                Interval {
+                    start: bound(false),
+                    end: None,
+                },
+            ],
+            Operator::LessThan | Operator::LessThanOrEqual => vec![Interval {
+                start: Bound::zero(),
+                end: Some(bound(operator == Operator::LessThanOrEqual)),
+            }],
+            Operator::GreaterThan | Operator::GreaterThanOrEqual => vec![Interval {
+                start: bound(operator == Operator::GreaterThanOrEqual),
+                end: None,
+            }],
+        };
+
+        VersionSet {
+            intervals: intervals
+                .into_iter()
+                .filter(|interval| !interval.is_empty())
+                .collect(),
+            branches: match operator {
+                Operator::NotEqual => Branches::any(),
+                _ => Branches::none(),
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.intervals.is_empty() && !self.branches.exclude && self.branches.names.is_empty()
+    }
+
+    fn intersect(&self, other: &VersionSet) -> VersionSet {
+        let mut intervals = Vec::new();
+
+        for a in &self.intervals {
+            for b in &other.intervals {
+                let interval = a.intersect(b);
+
+                if !interval.is_empty() {
+                    intervals.push(interval);
+                }
+            }
+        }
+
+        VersionSet {
+            intervals: merge_intervals(intervals),
+            branches: self.branches.intersect(&other.branches),
+        }
+    }
+
+    fn union(&self, other: &VersionSet) -> VersionSet {
+        let mut intervals = self.intervals.clone();
+        intervals.extend(other.intervals.iter().cloned());
+
+        VersionSet {
+            intervals: merge_intervals(intervals),
+            branches: self.branches.union(&other.branches),
+        }
+    }
+
+    /// Converts the set back to the simplest equivalent constraint, if it can be expressed as one
+    fn to_constraint(&self) -> Option<Constraint> {
+        if self.is_empty() {
+            return Some(Constraint::None);
+        }
+
+        let all_numeric = self.intervals.len() == 1
+            && self.intervals[0].start == Bound::zero()
+            && self.intervals[0].end.is_none();
+
+        if self.branches.exclude {
+            // `!=` constraints are the only ones that match branches without listing them
+            let holes = self.holes()?;
+            let mut constraints: Vec<_> = holes
+                .into_iter()
+                .chain(self.branches.names.iter().cloned())
+                .map(|version| Constraint::single(Operator::NotEqual, version))
+                .collect();
+
+            return Some(match constraints.len() {
+                0 if all_numeric => Constraint::Any,
+                1 => constraints.remove(0),
+                _ => Constraint::And(constraints),
+            });
+        }
+
+        let mut alternatives: Vec<_> = self.intervals.iter().map(interval_constraint).collect();
+
+        alternatives.extend(
+            self.branches
+                .names
+                .iter()
+                .map(|name| Constraint::single(Operator::Equal, name)),
+        );
+
+        Some(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Constraint::Or(alternatives),
+        })
+    }
+
+    /// The excluded versions when the intervals cover everything except single versions
+    fn holes(&self) -> Option<Vec<String>> {
+        let first = self.intervals.first()?;
+        let last = self.intervals.last()?;
+
+        if first.start != Bound::zero() || last.end.is_some() {
+            return None;
+        }
+
+        self.intervals
+            .windows(2)
+            .map(|pair| match (&pair[0].end, &pair[1].start) {
+                (Some(end), start)
+                    if !end.inclusive && !start.inclusive && end.version == start.version =>
+                {
+                    Some(end.version.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn merge_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort_by(|a, b| compare_starts(&a.start, &b.start));
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if last.connects_to(&interval) => {
+                last.end = match (&last.end, &interval.end) {
+                    (None, _) | (_, None) => None,
+                    (Some(a), Some(b)) => match compare_ends(a, b) {
+                        Ordering::Less => Some(b.clone()),
+                        _ => Some(a.clone()),
+                    },
+                };
+            }
+            _ => merged.push(interval),
+        }
+    }
+
+    merged
+}
+
+fn interval_constraint(interval: &Interval) -> Constraint {
+    let start = &interval.start;
+
+    match &interval.end {
+        None => Constraint::single(
+            match start.inclusive {
+                true => Operator::GreaterThanOrEqual,
+                false => Operator::GreaterThan,
+            },
+            &start.version,
+        ),
+        Some(end) if end.inclusive && start.inclusive && end.version == start.version => {
+            Constraint::single(Operator::Equal, &start.version)
+        }
+        Some(end) => {
+            let end = Constraint::single(
+                match end.inclusive {
+                    true => Operator::LessThanOrEqual,
+                    false => Operator::LessThan,
+                },
+                &end.version,
+            );
+
+            match *start == Bound::zero() {
+                true => end,
+                false => Constraint::And(vec![
+                    Constraint::single(
+                        match start.inclusive {
+                            true => Operator::GreaterThanOrEqual,
+                            false => Operator::GreaterThan,
+                        },
+                        &start.version,
+                    ),
+                    end,
+                ]),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_matches_version() {
+    for (constraint, version, expected) in [
+        ("^1.2", "1.2.0.0", true),
+        ("^1.2", "1.9.9.0", true),
+        ("^1.2", "2.0.0.0", false),
+        ("^1.2", "1.1.0.0", false),
+        ("^1.2@beta", "1.2.0.0-beta1", true),
+        ("~1.2.3", "1.2.9.0", true),
+        ("~1.2.3", "1.3.0.0", false),
+        ("1.0.*", "1.0.5.0", true),
+        ("1.0.*", "1.1.0.0", false),
+        ("1.0 - 2.0", "2.0.5.0", true),
+        ("1.0 - 2.0.0", "2.0.5.0", false),
+        (">=1.0 <1.1 || >=1.2", "1.1.0.0", false),
+        (">=1.0 <1.1 || >=1.2", "1.3.0.0", true),
+        ("!=1.5", "1.5.0.0", false),
+        ("!=1.5", "dev-main", true),
+        ("*", "dev-main", true),
+        (">=1.0", "dev-main", false),
+        ("dev-main", "dev-main", true),
+        ("dev-main", "dev-feature", false),
+        ("1.x-dev", "1.9999999.9999999.9999999-dev", true),
+    ] {
+        let parsed = Constraint::parse(constraint).unwrap();
+
+        assert_eq!(
+            parsed.matches_version(version),
+            expected,
+            "{constraint} ({parsed}) matching {version}"
+        );
+    }
+}
+
+#[test]
+fn test_intersect() {
+    for (a, b, expected) in [
+        ("^1.0", "^1.5", "[>= 1.5.0.0-dev < 2.0.0.0-dev]"),
+        ("^1.0", "^2.0", "[]"),
+        ("1.0", ">=1.0", "== 1.0.0.0"),
+        ("*", "!=1.0", "!= 1.0.0.0"),
+        (
+            "^1.0 || ^3.0",
+            ">=1.5",
+            "[[>= 1.5.0.0-dev < 2.0.0.0-dev] || [>= 3.0.0.0-dev < 4.0.0.0-dev]]",
+        ),
+        ("dev-main || ^1.0", "dev-main", "== dev-main"),
+    ] {
+        let a = Constraint::parse(a).unwrap();
+        let b = Constraint::parse(b).unwrap();
+
+        assert_eq!(a.intersect(&b).to_string(), expected, "{a} & {b}");
+    }
+
+    let caret = Constraint::parse("^1.2").unwrap();
+    assert!(caret.is_subset_of(&Constraint::parse("^1.0").unwrap()));
+    assert!(!caret.is_subset_of(&Constraint::parse("~1.2.0").unwrap()));
+    assert!(caret.matches(&Constraint::parse("<1.3").unwrap()));
+}
@@ -0,0 +1,24 @@
+mod constraint;
+mod parser;
+pub(crate) mod stability;
+mod version;
+
+pub use constraint::{Constraint, Operator};
+pub use parser::{split_constraints, split_inline_alias};
+pub use stability::Stability;
+pub(crate) use version::numeric_alias_prefix;
+pub use version::{compare_versions, normalize, normalize_branch};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SemverError {
+    #[error("Invalid version string \"{0}\"")]
+    InvalidVersion(String),
+
+    #[error("Could not parse version constraint {0}: {1}")]
+    InvalidConstraint(String, String),
+
+    #[error("Invalid stability \"{0}\", expected one of stable, RC, beta, alpha or dev")]
+    InvalidStability(String),
+}
@@ -0,0 +1,370 @@
+use crate::semver::constraint::{Constraint, Operator};
+use crate::semver::version::{normalize, regex, MODIFIER, STABILITIES};
+use crate::semver::{SemverError, Stability};
+use regex::{Captures, Regex};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// A version with up to four numeric parts, capturing the parts in groups 1 to 4 and the modifier
+/// in groups 5 to 7
+fn version_pattern() -> String {
+    format!(r"v?(\d+)(?:\.(\d+))?(?:\.(\d+))?(?:\.(\d+))?{MODIFIER}(?:\+[^\s]+)?")
+}
+
+/// Splits an inline alias such as `dev-main as 1.0.x-dev` into the actual version and its alias
+pub fn split_inline_alias(version: &str) -> (&str, Option<&str>) {
+    match regex!(r"^([^,\s]+) +as +([^,\s]+)$").captures(version.trim()) {
+        Some(captures) => (
+            captures.get(1).map_or(version, |group| group.as_str()),
+            captures.get(2).map(|group| group.as_str()),
+        ),
+        None => (version, None),
+    }
+}
+
+/// Parses a full constraint string, where `||` separates alternatives and commas or spaces
+/// separate constraints that must all match
+pub(crate) fn parse_constraints(constraints: &str) -> Result<Constraint, SemverError> {
+    let mut alternatives = Vec::new();
+
+    for alternative in regex!(r"\s*\|\|?\s*").split(constraints.trim()) {
+        let mut parsed = Vec::new();
+
+        for constraint in split_and(alternative) {
+            parsed.extend(parse_constraint(&constraint)?);
+        }
+
+        if parsed.is_empty() {
+            return Err(SemverError::InvalidConstraint(
+                constraints.to_string(),
+                "Empty constraint".into(),
+            ));
+        }
+
+        alternatives.push(match parsed.len() {
+            1 => parsed.remove(0),
+            _ => Constraint::And(parsed),
+        });
+    }
+
+    Ok(match alternatives.len() {
+        1 => alternatives.remove(0),
+        _ => Constraint::Or(alternatives),
+    })
+}
+
+/// Splits a constraint into its single constraints, whether they are alternatives or must all
+/// match, e.g. `^1.0 || >=2.0 <2.5` becomes `^1.0`, `>=2.0` and `<2.5`
+pub fn split_constraints(constraints: &str) -> Vec<String> {
+    regex!(r"\s*\|\|?\s*")
+        .split(constraints.trim())
+        .flat_map(split_and)
+        .collect()
+}
+
+/// Splits on commas and spaces, but keeps operators, hyphen ranges and inline aliases together
+/// with the versions they belong to, e.g. `>= 1.0 <2.0` becomes `>= 1.0` and `<2.0`
+fn split_and(constraints: &str) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut join_next = false;
+
+    for token in constraints
+        .split([',', ' '])
+        .filter(|token| !token.is_empty())
+    {
+        let joins_previous = join_next || token == "-" || token == "as";
+
+        match parts.last_mut() {
+            Some(last) if joins_previous => {
+                last.push(' ');
+                last.push_str(token);
+            }
+            _ => parts.push(token.to_string()),
+        }
+
+        join_next = token == "-"
+            || token == "as"
+            || token
+                .chars()
+                .all(|character| matches!(character, '<' | '>' | '=' | '!'));
+    }
+
+    parts
+}
+
+fn parse_constraint(constraint: &str) -> Result<Vec<Constraint>, SemverError> {
+    let original = constraint;
+    let mut constraint = split_inline_alias(constraint).0;
+    let mut stability_modifier = None;
+
+    let flag = regex!(format!(r"(?i)^([^,\s]*?)@({STABILITIES})$"));
+    if let Some(captures) = flag.captures(constraint) {
+        let stability = Stability::from_str(captures.get(2).map_or("", |group| group.as_str()))?;
+
+        constraint = match captures.get(1).map_or("", |group| group.as_str()) {
+            "" => "*",
+            version => version,
+        };
+
+        if stability != Stability::Stable {
+            stability_modifier = Some(stability);
+        }
+    }
+
+    // References are only used to lock a branch to a commit, they don't affect matching
+    if let Some(captures) =
+        regex!(r"(?i)^(dev-[^,\s@]+?|[^,\s@]+?\.x-dev)#.+$").captures(constraint)
+    {
+        constraint = captures.get(1).map_or(constraint, |group| group.as_str());
+    }
+
+    if let Some(captures) = regex!(r"(?i)^(v)?[x*](\.[x*])*$").captures(constraint) {
+        if captures.get(1).is_some() || captures.get(2).is_some() {
+            return Ok(vec![Constraint::single(
+                Operator::GreaterThanOrEqual,
+                "0.0.0.0-dev",
+            )]);
+        }
+
+        return Ok(vec![Constraint::Any]);
+    }
+
+    let invalid = |reason: &str| SemverError::InvalidConstraint(original.into(), reason.into());
+    let version = version_pattern();
+
+    if regex!(format!(r"(?i)^~>{version}$")).is_match(constraint) {
+        return Err(invalid(
+            "Invalid operator \"~>\", you probably meant to use the \"~\" operator",
+        ));
+    }
+
+    if let Some(captures) = regex!(format!(r"(?i)^~{version}$")).captures(constraint) {
+        let position = (2..=4)
+            .rev()
+            .find(|index| captures.get(*index).is_some())
+            .unwrap_or(1);
+        let low = normalize(&format!(
+            "{}{}",
+            &constraint[1..],
+            stability_suffix(&captures, 5)
+        ))
+        .map_err(|error| invalid(&error.to_string()))?;
+        let high = manipulate_version(&captures, 1, position.saturating_sub(1).max(1), 1);
+
+        return Ok(vec![
+            Constraint::single(Operator::GreaterThanOrEqual, low),
+            Constraint::single(Operator::LessThan, format!("{high}-dev")),
+        ]);
+    }
+
+    if let Some(captures) = regex!(format!(r"(?i)^\^{version}$")).captures(constraint) {
+        let part = |index: usize| captures.get(index).map(|group| group.as_str());
+        let position = match (part(1), part(2), part(3)) {
+            (Some(major), minor, _) if major != "0" || minor.is_none() => 1,
+            (_, Some(minor), patch) if minor != "0" || patch.is_none() => 2,
+            _ => 3,
+        };
+        let low = normalize(&format!(
+            "{}{}",
+            &constraint[1..],
+            stability_suffix(&captures, 5)
+        ))
+        .map_err(|error| invalid(&error.to_string()))?;
+        let high = manipulate_version(&captures, 1, position, 1);
+
+        return Ok(vec![
+            Constraint::single(Operator::GreaterThanOrEqual, low),
+            Constraint::single(Operator::LessThan, format!("{high}-dev")),
+        ]);
+    }
+
+    let x_range = regex!(r"^v?(\d+)(?:\.(\d+))?(?:\.(\d+))?(?:\.[xX*])+$");
+    if let Some(captures) = x_range.captures(constraint) {
+        let position = (2..=3)
+            .rev()
+            .find(|index| captures.get(*index).is_some())
+            .unwrap_or(1);
+        let low = format!("{}-dev", manipulate_version(&captures, 1, position, 0));
+        let high = format!("{}-dev", manipulate_version(&captures, 1, position, 1));
+
+        if low == "0.0.0.0-dev" {
+            return Ok(vec![Constraint::single(Operator::LessThan, high)]);
+        }
+
+        return Ok(vec![
+            Constraint::single(Operator::GreaterThanOrEqual, low),
+            Constraint::single(Operator::LessThan, high),
+        ]);
+    }
+
+    // The groups of the from version are 2 to 8, those of the to version 10 to 16
+    let hyphen = regex!(format!(r"(?i)^({version}) +- +({version})$"));
+    if let Some(captures) = hyphen.captures(constraint) {
+        let from = normalize(&captures[1]).map_err(|error| invalid(&error.to_string()))?;
+        let to = normalize(&captures[9]).map_err(|error| invalid(&error.to_string()))?;
+        let low = format!("{from}{}", stability_suffix(&captures, 6));
+        let is_full_version = captures.get(11).is_some() && captures.get(12).is_some();
+        let has_modifier = (14..=16).any(|index| captures.get(index).is_some());
+
+        let high = match is_full_version || has_modifier {
+            true => Constraint::single(Operator::LessThanOrEqual, to),
+            false => {
+                let position = match captures.get(11) {
+                    Some(_) => 2,
+                    None => 1,
+                };
+                let high = manipulate_version(&captures, 10, position, 1);
+
+                Constraint::single(Operator::LessThan, format!("{high}-dev"))
+            }
+        };
+
+        return Ok(vec![
+            Constraint::single(Operator::GreaterThanOrEqual, low),
+            high,
+        ]);
+    }
+
+    let comparator = regex!(r"^(<>|!=|>=?|<=?|==?)?\s*(.*)$");
+    let captures = comparator
+        .captures(constraint)
+        .ok_or_else(|| invalid("Invalid constraint"))?;
+    let operator = captures.get(1).map_or("", |group| group.as_str());
+    let target = captures.get(2).map_or("", |group| group.as_str());
+
+    let mut version = match normalize(target) {
+        Ok(version) => version,
+        // Recover from constraints like foobar-dev, which should be dev-foobar
+        Err(_)
+            if target.len() > 4
+                && target.ends_with("-dev")
+                && regex!(r"^[0-9a-zA-Z./-]+$").is_match(target) =>
+        {
+            normalize(&format!("dev-{}", &target[..target.len() - 4]))
+                .map_err(|error| invalid(&error.to_string()))?
+        }
+        Err(error) => return Err(invalid(&error.to_string())),
+    };
+
+    let operator = match operator {
+        "" | "=" | "==" => Operator::Equal,
+        "!=" | "<>" => Operator::NotEqual,
+        "<" => Operator::LessThan,
+        "<=" => Operator::LessThanOrEqual,
+        ">" => Operator::GreaterThan,
+        _ => Operator::GreaterThanOrEqual,
+    };
+
+    match stability_modifier {
+        Some(stability)
+            if operator != Operator::Equal && Stability::of(&version) == Stability::Stable =>
+        {
+            version.push('-');
+            version.push_str(&stability.to_string());
+        }
+        _ if matches!(operator, Operator::LessThan | Operator::GreaterThanOrEqual) => {
+            let has_modifier = regex!(format!(r"(?i)-{MODIFIER}$")).is_match(target);
+
+            if !has_modifier && !target.starts_with("dev-") {
+                version.push_str("-dev");
+            }
+        }
+        _ => {}
+    }
+
+    Ok(vec![Constraint::single(operator, version)])
+}
+
+/// `-dev` when the version starting at the given group has neither a stability nor a dev suffix,
+/// so that ranges include the pre-releases of their lower bound
+fn stability_suffix(captures: &Captures, stability: usize) -> &'static str {
+    match captures.get(stability).is_none() && captures.get(stability + 2).is_none() {
+        true => "-dev",
+        false => "",
+    }
+}
+
+/// Builds a four part version from the captured parts, zeroing everything after the given
+/// position and incrementing the part at the position
+fn manipulate_version(
+    captures: &Captures,
+    first: usize,
+    position: usize,
+    increment: u64,
+) -> String {
+    (1..=4)
+        .map(|part| match part {
+            part if part > position => 0,
+            part => {
+                let value = captures
+                    .get(first + part - 1)
+                    .and_then(|group| group.as_str().parse::<u64>().ok())
+                    .unwrap_or(0);
+
+                match part == position {
+                    true => value + increment,
+                    false => value,
+                }
+            }
+        })
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[test]
+fn test_parse_constraints() {
+    for (constraint, expected) in [
+        ("*", "*"),
+        ("x", "*"),
+        ("v*", ">= 0.0.0.0-dev"),
+        ("1.0", "== 1.0.0.0"),
+        ("=1.0", "== 1.0.0.0"),
+        ("<>1.0", "!= 1.0.0.0"),
+        (">1.0", "> 1.0.0.0"),
+        (">=1.0", ">= 1.0.0.0-dev"),
+        (">=1.0@beta", ">= 1.0.0.0-beta"),
+        ("<1.0-beta", "< 1.0.0.0-beta"),
+        ("^1.2.3", "[>= 1.2.3.0-dev < 2.0.0.0-dev]"),
+        ("^0.3", "[>= 0.3.0.0-dev < 0.4.0.0-dev]"),
+        ("^0.0.3", "[>= 0.0.3.0-dev < 0.0.4.0-dev]"),
+        ("^1.0-beta", "[>= 1.0.0.0-beta < 2.0.0.0-dev]"),
+        ("~1", "[>= 1.0.0.0-dev < 2.0.0.0-dev]"),
+        ("~1.2", "[>= 1.2.0.0-dev < 2.0.0.0-dev]"),
+        ("~1.2.3", "[>= 1.2.3.0-dev < 1.3.0.0-dev]"),
+        ("1.2.*", "[>= 1.2.0.0-dev < 1.3.0.0-dev]"),
+        ("0.*", "< 1.0.0.0-dev"),
+        ("1.0 - 2.0", "[>= 1.0.0.0-dev < 2.1.0.0-dev]"),
+        ("1.2.3 - 2.3.4.5", "[>= 1.2.3.0-dev <= 2.3.4.5]"),
+        (">= 1.0, < 2.0", "[>= 1.0.0.0-dev < 2.0.0.0-dev]"),
+        (
+            "^1.0 || ^2.0",
+            "[[>= 1.0.0.0-dev < 2.0.0.0-dev] || [>= 2.0.0.0-dev < 3.0.0.0-dev]]",
+        ),
+        (
+            "^1.0 | ~2.1",
+            "[[>= 1.0.0.0-dev < 2.0.0.0-dev] || [>= 2.1.0.0-dev < 3.0.0.0-dev]]",
+        ),
+        ("dev-main", "== dev-main"),
+        ("dev-main#abc123", "== dev-main"),
+        ("feature-dev", "== dev-feature"),
+        ("1.x-dev", "== 1.9999999.9999999.9999999-dev"),
+        ("dev-main as 1.0.x-dev", "== dev-main"),
+        ("@dev", "*"),
+    ] {
+        assert_eq!(
+            Constraint::parse(constraint).unwrap().to_string(),
+            expected,
+            "{constraint}"
+        );
+    }
+
+    for constraint in ["", "~>1.0", "foo", "1.0 ||", ">=a"] {
+        assert!(Constraint::parse(constraint).is_err(), "{constraint}");
+    }
+
+    assert_eq!(
+        split_inline_alias("dev-main as 1.0.x-dev"),
+        ("dev-main", Some("1.0.x-dev"))
+    );
+}
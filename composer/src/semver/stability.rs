@@ -0,0 +1,191 @@
+use crate::php::php_array::PhpArray;
+use crate::semver::version::{regex, MODIFIER};
+use crate::semver::SemverError;
+use regex::Regex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The stability of a version, ordered from most to least stable
+///
+/// The priorities are the values Composer writes to the `stability-flags` of a lock file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Stability {
+    #[default]
+    Stable,
+    RC,
+    Beta,
+    Alpha,
+    Dev,
+}
+
+impl Stability {
+    pub const ALL: [Stability; 5] = [
+        Stability::Stable,
+        Stability::RC,
+        Stability::Beta,
+        Stability::Alpha,
+        Stability::Dev,
+    ];
+
+    /// Derives the stability of a version, e.g. `1.0.0-beta2` is beta and `dev-main` is dev
+    pub fn of(version: &str) -> Self {
+        let version = regex!(r"#.+$").replace(version.trim(), "").to_lowercase();
+
+        if version.starts_with("dev-") || version.ends_with("-dev") {
+            return Stability::Dev;
+        }
+
+        let modifier = regex!(format!(r"(?i){MODIFIER}(?:\+.*)?$"));
+        let Some(captures) = modifier.captures(&version) else {
+            return Stability::Stable;
+        };
+
+        if captures.get(3).is_some() {
+            return Stability::Dev;
+        }
+
+        match captures.get(1).map(|group| group.as_str()) {
+            Some("beta" | "b") => Stability::Beta,
+            Some("alpha" | "a") => Stability::Alpha,
+            Some("rc") => Stability::RC,
+            _ => Stability::Stable,
+        }
+    }
+
+    pub fn priority(self) -> u8 {
+        match self {
+            Stability::Stable => 0,
+            Stability::RC => 5,
+            Stability::Beta => 10,
+            Stability::Alpha => 15,
+            Stability::Dev => 20,
+        }
+    }
+
+    pub fn from_priority(priority: u8) -> Option<Self> {
+        Stability::ALL
+            .into_iter()
+            .find(|stability| stability.priority() == priority)
+    }
+
+    /// Whether a version of this stability is allowed by the given minimum stability
+    pub fn satisfies(self, minimum: Stability) -> bool {
+        self <= minimum
+    }
+}
+
+impl FromStr for Stability {
+    type Err = SemverError;
+
+    fn from_str(stability: &str) -> Result<Self, Self::Err> {
+        match stability.trim().to_lowercase().as_str() {
+            "stable" => Ok(Stability::Stable),
+            "rc" => Ok(Stability::RC),
+            "beta" => Ok(Stability::Beta),
+            "alpha" => Ok(Stability::Alpha),
+            "dev" => Ok(Stability::Dev),
+            _ => Err(SemverError::InvalidStability(stability.to_string())),
+        }
+    }
+}
+
+impl Display for Stability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Stability::Stable => "stable",
+                Stability::RC => "RC",
+                Stability::Beta => "beta",
+                Stability::Alpha => "alpha",
+                Stability::Dev => "dev",
+            }
+        )
+    }
+}
+
+impl Serialize for Stability {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Stability {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// (De)serializes stability flags as the priorities Composer uses in lock files, e.g.
+/// `{"vendor/package": 20}`
+pub(crate) mod priorities {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        flags: &PhpArray<Stability>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match flags {
+            PhpArray::Indexed(flags) => serializer.collect_seq(flags.iter().map(|s| s.priority())),
+            PhpArray::Associative(flags) => serializer.collect_map(
+                flags
+                    .iter()
+                    .map(|(name, stability)| (name, stability.priority())),
+            ),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PhpArray<Stability>, D::Error> {
+        let stability = |priority: u8| {
+            Stability::from_priority(priority)
+                .ok_or_else(|| D::Error::custom(format!("invalid stability priority {priority}")))
+        };
+
+        Ok(match PhpArray::<u8>::deserialize(deserializer)? {
+            PhpArray::Indexed(flags) => {
+                PhpArray::Indexed(flags.into_iter().map(stability).collect::<Result<_, _>>()?)
+            }
+            PhpArray::Associative(flags) => PhpArray::Associative(
+                flags
+                    .into_iter()
+                    .map(|(name, priority)| Ok((name, stability(priority)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+#[test]
+fn test_stability_of() {
+    for (version, expected) in [
+        ("1.0.0", Stability::Stable),
+        ("1.0.0.0", Stability::Stable),
+        ("1.0.0-patch1", Stability::Stable),
+        ("1.0.0-RC2", Stability::RC),
+        ("1.0.0.0-beta3", Stability::Beta),
+        ("1.0.0-b1", Stability::Beta),
+        ("1.0.0-alpha", Stability::Alpha),
+        ("1.0.0-dev", Stability::Dev),
+        ("1.0.x-dev", Stability::Dev),
+        ("dev-main", Stability::Dev),
+        ("dev-main#abc123", Stability::Dev),
+        ("3.1.2-beta1+build.7", Stability::Beta),
+    ] {
+        assert_eq!(Stability::of(version), expected, "{version}");
+    }
+
+    assert_eq!("rc".parse::<Stability>().unwrap(), Stability::RC);
+    assert_eq!(Stability::RC.to_string(), "RC");
+    assert!("unstable".parse::<Stability>().is_err());
+    assert!(Stability::Beta.satisfies(Stability::Alpha));
+    assert!(!Stability::Dev.satisfies(Stability::Stable));
+    assert_eq!(Stability::from_priority(10), Some(Stability::Beta));
+}
@@ -0,0 +1,297 @@
+use crate::semver::SemverError;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+
+/// Matches the stability suffix of a version, e.g. `-beta.2`, `RC1` or `-dev`
+pub(crate) const MODIFIER: &str =
+    r"[._-]?(?:(stable|beta|b|RC|alpha|a|patch|pl|p)((?:[.-]?\d+)*)?)?([.-]?dev)?";
+
+/// The stabilities that can be used as `@stability` flag
+pub(crate) const STABILITIES: &str = "stable|RC|beta|alpha|dev";
+
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static REGEX: OnceLock<Regex> = OnceLock::new();
+        REGEX.get_or_init(|| Regex::new(&$pattern).expect("valid regex"))
+    }};
+}
+
+pub(crate) use regex;
+
+/// Normalizes a version to Composer's canonical form, e.g. `v1.2` becomes `1.2.0.0` and
+/// `1.0.0-beta.3` becomes `1.0.0.0-beta3`
+///
+/// Branches are returned as `dev-<name>`, numeric branches such as `1.x-dev` as `1.9999999.9999999.9999999-dev`.
+pub fn normalize(version: &str) -> Result<String, SemverError> {
+    let original = version.trim();
+    let mut version = original;
+
+    // Inline aliases are resolved by the caller, only the aliased version is normalized
+    if let Some(captures) = regex!(r"^([^,\s]+) +as +([^,\s]+)$").captures(version) {
+        version = captures.get(1).map_or(version, |group| group.as_str());
+    }
+
+    if let Some(flag) = regex!(format!("(?i)@(?:{STABILITIES})$")).find(version) {
+        version = &version[..flag.start()];
+    }
+
+    if matches!(version, "master" | "trunk" | "default") {
+        return Ok(format!("dev-{version}"));
+    }
+
+    if version.len() >= 4 && version[..4].eq_ignore_ascii_case("dev-") {
+        return Ok(format!("dev-{}", &version[4..]));
+    }
+
+    if let Some(captures) = regex!(r"^([^,\s+]+)\+\S+$").captures(version) {
+        version = captures.get(1).map_or(version, |group| group.as_str());
+    }
+
+    let classic = regex!(format!(
+        r"(?i)^v?(\d{{1,5}})(\.\d+)?(\.\d+)?(\.\d+)?{MODIFIER}$"
+    ));
+    let date = regex!(format!(
+        r"(?i)^v?(\d{{4}}(?:[.:-]?\d{{2}}){{1,6}}(?:[.:-]?\d{{1,3}}){{0,2}}){MODIFIER}$"
+    ));
+
+    let matched = if let Some(captures) = classic.captures(version) {
+        let mut normalized = captures[1].to_string();
+
+        for index in 2..=4 {
+            normalized.push_str(captures.get(index).map_or(".0", |group| group.as_str()));
+        }
+
+        Some((normalized, captures, 5))
+    } else if let Some(captures) = date.captures(version) {
+        let normalized = captures[1].replace(|character: char| !character.is_ascii_digit(), ".");
+
+        Some((normalized, captures, 2))
+    } else {
+        None
+    };
+
+    if let Some((mut normalized, captures, index)) = matched {
+        if let Some(stability) = captures.get(index) {
+            if stability.as_str() == "stable" {
+                return Ok(normalized);
+            }
+
+            normalized.push('-');
+            normalized.push_str(expand_stability(stability.as_str()));
+
+            if let Some(number) = captures.get(index + 1) {
+                normalized.push_str(number.as_str().trim_start_matches(['.', '-']));
+            }
+        }
+
+        if captures.get(index + 2).is_some() {
+            normalized.push_str("-dev");
+        }
+
+        return Ok(normalized);
+    }
+
+    if let Some(captures) = regex!(r"(?i)^(.*?)[.-]?dev$").captures(version) {
+        let normalized = normalize_branch(&captures[1]);
+
+        // A branch ending with -dev is only valid when it is numeric
+        if !normalized.starts_with("dev-") {
+            return Ok(normalized);
+        }
+    }
+
+    Err(SemverError::InvalidVersion(original.to_string()))
+}
+
+/// Normalizes a branch name, turning numeric branches such as `1.x` into `1.9999999.9999999.9999999-dev`
+/// and everything else into `dev-<name>`
+pub fn normalize_branch(name: &str) -> String {
+    let name = name.trim();
+    let numeric = regex!(r"(?i)^v?(\d+)(\.(?:\d+|[x*]))?(\.(?:\d+|[x*]))?(\.(?:\d+|[x*]))?$");
+
+    let Some(captures) = numeric.captures(name) else {
+        return format!("dev-{name}");
+    };
+
+    let mut version = captures[1].to_string();
+
+    for index in 2..=4 {
+        let part = captures.get(index).map_or(".x", |group| group.as_str());
+        version.push_str(&part.replace(['*', 'X'], "x"));
+    }
+
+    format!("{}-dev", version.replace('x', "9999999"))
+}
+
+/// The numeric prefix of a branch such as `2.1.x-dev`, i.e. `2.1.`, or `None` for other branches
+pub(crate) fn numeric_alias_prefix(branch: &str) -> Option<String> {
+    let numeric = regex!(r"(?i)^((?:\d+\.)*\d+)(?:\.x)?-dev$");
+
+    numeric
+        .captures(branch)
+        .map(|captures| format!("{}.", &captures[1]))
+}
+
+/// Expands shorthand stabilities such as `b` or `rc` to their full name
+pub(crate) fn expand_stability(stability: &str) -> &'static str {
+    match stability.to_lowercase().as_str() {
+        "a" | "alpha" => "alpha",
+        "b" | "beta" => "beta",
+        "p" | "pl" | "patch" => "patch",
+        "rc" => "RC",
+        "stable" => "stable",
+        _ => "dev",
+    }
+}
+
+/// Compares two versions the same way PHP's `version_compare` does
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let a = canonicalize(a);
+    let b = canonicalize(b);
+    let mut a_parts = a.split('.').filter(|part| !part.is_empty());
+    let mut b_parts = b.split('.').filter(|part| !part.is_empty());
+
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(a), None) => compare_remainder(a),
+            (None, Some(b)) => compare_remainder(b).reverse(),
+            (Some(a), Some(b)) => compare_parts(a, b),
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Separates the parts of a version with dots, e.g. `1.0.0-beta2` becomes `1.0.0.beta.2`
+fn canonicalize(version: &str) -> String {
+    let mut canonical = String::with_capacity(version.len() + 4);
+    let mut previous: Option<char> = None;
+
+    for character in version.chars() {
+        let is_separator = !character.is_ascii_alphanumeric();
+        let switches_kind = previous.is_some_and(|previous| {
+            previous != '.' && previous.is_ascii_digit() != character.is_ascii_digit()
+        });
+
+        if (is_separator || switches_kind) && !canonical.is_empty() && !canonical.ends_with('.') {
+            canonical.push('.');
+        }
+
+        if !is_separator {
+            canonical.push(character);
+        }
+
+        previous = Some(if is_separator { '.' } else { character });
+    }
+
+    canonical
+}
+
+/// Compares the first extra part of the longer version, which makes `1.0.1` greater than `1.0` but
+/// `1.0-dev` less than `1.0`
+fn compare_remainder(part: &str) -> Ordering {
+    match part.starts_with(|character: char| character.is_ascii_digit()) {
+        true => Ordering::Greater,
+        false => special_form_order(part).cmp(&special_form_order("#")),
+    }
+}
+
+fn compare_parts(a: &str, b: &str) -> Ordering {
+    let a_is_number = a.starts_with(|character: char| character.is_ascii_digit());
+    let b_is_number = b.starts_with(|character: char| character.is_ascii_digit());
+
+    match (a_is_number, b_is_number) {
+        (true, true) => {
+            let a = a.trim_start_matches('0');
+            let b = b.trim_start_matches('0');
+
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        }
+        (true, false) => special_form_order("#").cmp(&special_form_order(b)),
+        (false, true) => special_form_order(a).cmp(&special_form_order("#")),
+        (false, false) => special_form_order(a).cmp(&special_form_order(b)),
+    }
+}
+
+/// The order of the special forms known to `version_compare`, matched by prefix like PHP does
+fn special_form_order(form: &str) -> i8 {
+    const FORMS: [(&str, i8); 10] = [
+        ("dev", 0),
+        ("alpha", 1),
+        ("a", 1),
+        ("beta", 2),
+        ("b", 2),
+        ("RC", 3),
+        ("rc", 3),
+        ("#", 4),
+        ("pl", 5),
+        ("p", 5),
+    ];
+
+    FORMS
+        .iter()
+        .find(|(name, _)| form.starts_with(name))
+        .map_or(-6, |(_, order)| *order)
+}
+
+#[test]
+fn test_normalize() {
+    for (version, expected) in [
+        ("1.0.0", "1.0.0.0"),
+        ("1.2.3.4", "1.2.3.4"),
+        ("v1.2", "1.2.0.0"),
+        ("1.0.0RC1dev", "1.0.0.0-RC1-dev"),
+        ("1.0.0-rC15-dev", "1.0.0.0-RC15-dev"),
+        ("1.0.0.RC.15-dev", "1.0.0.0-RC15-dev"),
+        ("1.0.0-rc1", "1.0.0.0-RC1"),
+        ("1.0.0-beta.3", "1.0.0.0-beta3"),
+        ("1.0.0-b5", "1.0.0.0-beta5"),
+        ("1.0.0-pl3", "1.0.0.0-patch3"),
+        ("1.0.0-stable", "1.0.0.0"),
+        ("1.0.0+build.5", "1.0.0.0"),
+        ("20100102", "20100102"),
+        ("2010.01.02", "2010.01.02.0"),
+        ("2010-01-02.5", "2010.01.02.5"),
+        ("20100102-203040-p1", "20100102.203040-patch1"),
+        ("dev-master", "dev-master"),
+        ("master", "dev-master"),
+        ("dev-feature/foo", "dev-feature/foo"),
+        ("1.x-dev", "1.9999999.9999999.9999999-dev"),
+        ("2.0.x-dev", "2.0.9999999.9999999-dev"),
+        ("1.0.0-dev", "1.0.0.0-dev"),
+        ("1.0@dev", "1.0.0.0"),
+        ("1.0 as 2.0", "1.0.0.0"),
+    ] {
+        assert_eq!(normalize(version).unwrap(), expected, "{version}");
+    }
+
+    for version in ["", "a", "1.0.0-meh", "1.0.0.0.0", "feature-dev", "1.0 as"] {
+        assert!(normalize(version).is_err(), "{version}");
+    }
+}
+
+#[test]
+fn test_compare_versions() {
+    for (a, b, expected) in [
+        ("1.0.0.0", "1.0.0.0", Ordering::Equal),
+        ("1.0.0.0", "1.0.0.1", Ordering::Less),
+        ("1.10.0.0", "1.9.0.0", Ordering::Greater),
+        ("1.0.0.0-dev", "1.0.0.0-alpha1", Ordering::Less),
+        ("1.0.0.0-alpha1", "1.0.0.0-beta1", Ordering::Less),
+        ("1.0.0.0-beta2", "1.0.0.0-RC1", Ordering::Less),
+        ("1.0.0.0-RC1", "1.0.0.0", Ordering::Less),
+        ("1.0.0.0", "1.0.0.0-patch1", Ordering::Less),
+        ("1.0.0.0-beta10", "1.0.0.0-beta9", Ordering::Greater),
+        (
+            "1.9999999.9999999.9999999-dev",
+            "1.5.0.0",
+            Ordering::Greater,
+        ),
+    ] {
+        assert_eq!(compare_versions(a, b), expected, "{a} <=> {b}");
+    }
+}